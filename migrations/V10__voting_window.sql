@@ -0,0 +1,12 @@
+-- NOTE: the votes on the posts older than the guild's voting window are
+-- ignored, and the posts are finalized once, showing their final tally.
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS voting_window_days integer
+        CHECK (voting_window_days > 0),
+    ADD COLUMN IF NOT EXISTS show_final_tally boolean NOT NULL DEFAULT false;
+
+ALTER TABLE post
+    ADD COLUMN IF NOT EXISTS finalized boolean NOT NULL DEFAULT false;
+
+CREATE INDEX IF NOT EXISTS post_unfinalized_idx
+    ON post(created_at) WHERE NOT finalized;
@@ -0,0 +1,23 @@
+-- NOTE: the moderation actions are recorded along with the moderator that
+-- took them. The target is a user or a post, depending on the action.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id bigserial NOT NULL,
+    guild_id bigint NOT NULL,
+    moderator_id bigint NOT NULL,
+    action text NOT NULL,
+    target_id bigint,
+    amount bigint,
+    reason text,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(id)
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_guild_id_idx
+    ON audit_log(guild_id, created_at);
+
+ALTER TABLE "user"
+    ADD COLUMN IF NOT EXISTS voting_banned boolean NOT NULL DEFAULT false;
+
+-- NOTE: the votes on the voided posts are removed, and no longer recorded.
+ALTER TABLE post
+    ADD COLUMN IF NOT EXISTS voided boolean NOT NULL DEFAULT false;
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS report (
+    post_id bigint NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    reporter_id bigint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(post_id, reporter_id)
+);
+
+-- NOTE: a review is opened with the first report of a post, and holds the
+-- review card sent to the guild's report channel.
+CREATE TABLE IF NOT EXISTS review (
+    post_id bigint NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    card_id bigint,
+    hidden boolean NOT NULL DEFAULT false,
+    approved boolean NOT NULL DEFAULT false,
+    PRIMARY KEY(post_id)
+);
+
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS report_channel_id bigint,
+    ADD COLUMN IF NOT EXISTS report_threshold integer
+        CHECK (report_threshold > 0),
+    ADD COLUMN IF NOT EXISTS report_hide_mode text NOT NULL DEFAULT 'spoiler'
+        CHECK (report_hide_mode IN ('spoiler', 'delete'));
@@ -0,0 +1,11 @@
+-- NOTE: the allowed types are prefixes of the attachments' content types,
+-- such as 'image/' for all the images or 'video/mp4' for the mp4 videos.
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS allowed_attachment_types text[] NOT NULL
+        DEFAULT ARRAY['image/', 'video/'],
+    ADD COLUMN IF NOT EXISTS max_attachment_mb integer
+        CHECK (max_attachment_mb > 0),
+    ADD COLUMN IF NOT EXISTS max_attachment_dimension integer
+        CHECK (max_attachment_dimension > 0),
+    ADD COLUMN IF NOT EXISTS max_attachments integer
+        CHECK (max_attachments > 0);
@@ -0,0 +1,14 @@
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS spoiler_channel_ids bigint[] NOT NULL
+        DEFAULT '{}';
+
+-- NOTE: whether the post was sent as a spoiler, and its content warning,
+-- so that its content may be composed again once it is edited.
+ALTER TABLE post
+    ADD COLUMN IF NOT EXISTS spoiler boolean NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS cw text;
+
+-- NOTE: the posts hidden by deleting them may not be restored once they
+-- are approved.
+ALTER TABLE review
+    ADD COLUMN IF NOT EXISTS deleted boolean NOT NULL DEFAULT false;
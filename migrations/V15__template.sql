@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS template (
+    guild_id bigint NOT NULL,
+    name text NOT NULL,
+    image bytea NOT NULL,
+    created_by bigint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(guild_id, name)
+);
+
+-- NOTE: the position and the size of the text boxes are percentages of the
+-- template's size, so that they still apply once the image is downscaled.
+-- The text is placed at the top, the center or the bottom of its box.
+CREATE TABLE IF NOT EXISTS template_box (
+    guild_id bigint NOT NULL,
+    name text NOT NULL,
+    position smallint NOT NULL,
+    x smallint NOT NULL CHECK (x >= 0),
+    y smallint NOT NULL CHECK (y >= 0),
+    width smallint NOT NULL CHECK (width > 0 AND x + width <= 100),
+    height smallint NOT NULL CHECK (height > 0 AND y + height <= 100),
+    align text NOT NULL DEFAULT 'center'
+        CHECK (align IN ('top', 'center', 'bottom')),
+    PRIMARY KEY(guild_id, name, position),
+    FOREIGN KEY(guild_id, name) REFERENCES template(guild_id, name)
+        ON DELETE CASCADE
+);
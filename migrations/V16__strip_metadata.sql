@@ -0,0 +1,2 @@
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS strip_metadata boolean NOT NULL DEFAULT true;
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS guild_config (
+    guild_id bigint NOT NULL,
+    disabled_commands text[] NOT NULL DEFAULT '{}',
+    PRIMARY KEY(guild_id)
+);
@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS post (
+    id bigint NOT NULL,
+    guild_id bigint NOT NULL,
+    channel_id bigint NOT NULL,
+    author_id bigint NOT NULL,
+    kind text NOT NULL,
+    -- NOTE: false for the posts sent before the votes were recorded,
+    -- these are recorded once they receive a vote, and may have
+    -- votes that are not recorded.
+    tracked boolean NOT NULL DEFAULT true,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(id)
+);
+
+CREATE INDEX IF NOT EXISTS post_guild_id_author_id_idx
+    ON post(guild_id, author_id);
+
+CREATE TABLE IF NOT EXISTS vote (
+    post_id bigint NOT NULL REFERENCES post(id) ON DELETE CASCADE,
+    voter_id bigint NOT NULL,
+    value smallint NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(post_id, voter_id, value)
+);
+
+CREATE INDEX IF NOT EXISTS vote_voter_id_idx ON vote(voter_id);
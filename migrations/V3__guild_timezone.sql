@@ -0,0 +1,4 @@
+-- NOTE: the time zone determines the day, week and month boundaries
+-- of the guild's leaderboards.
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS timezone text NOT NULL DEFAULT 'UTC';
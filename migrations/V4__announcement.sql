@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS announcement (
+    guild_id bigint NOT NULL,
+    -- NOTE: the name is used as the title of the announcement,
+    -- such as 'Meme of the Day'.
+    name text NOT NULL,
+    schedule text NOT NULL,
+    channel_id bigint NOT NULL,
+    bonus bigint NOT NULL DEFAULT 0,
+    role_id bigint,
+    -- NOTE: the scheduled time of the last announcement, the next
+    -- announcement includes the posts sent since then.
+    last_run timestamptz NOT NULL DEFAULT now(),
+    last_winner_id bigint,
+    PRIMARY KEY(guild_id, name)
+);
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS reward_role (
+    guild_id bigint NOT NULL,
+    role_id bigint NOT NULL,
+    -- NOTE: the role is rewarded either for reaching the score, or for
+    -- being ranked at or above the rank in the leaderboard of the period.
+    min_score bigint,
+    max_rank bigint,
+    period text NOT NULL DEFAULT 'all-time',
+    PRIMARY KEY(guild_id, role_id),
+    CHECK ((min_score IS NULL) <> (max_rank IS NULL))
+);
+
+-- NOTE: the roles granted by the bot, so that only those are revoked.
+CREATE TABLE IF NOT EXISTS reward_grant (
+    guild_id bigint NOT NULL,
+    role_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    PRIMARY KEY(guild_id, role_id, user_id),
+    FOREIGN KEY(guild_id, role_id)
+        REFERENCES reward_role(guild_id, role_id) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS achievement (
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    -- NOTE: the id of the achievement's rule, defined by the bot.
+    name text NOT NULL,
+    unlocked_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(guild_id, user_id, name)
+);
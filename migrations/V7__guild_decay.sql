@@ -0,0 +1,23 @@
+-- NOTE: the scores are not decayed when the decay mode is not set.
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS decay_mode text
+        CHECK (decay_mode IN ('step', 'exponential')),
+    ADD COLUMN IF NOT EXISTS decay_half_life_days integer
+        CHECK (decay_half_life_days > 0),
+    ADD CONSTRAINT guild_config_decay_check
+        CHECK ((decay_mode IS NULL) = (decay_half_life_days IS NULL));
+
+-- NOTE: the points added to the scores outside of the votes, such as the
+-- announcements' bonuses, are recorded with their time, so that they are
+-- counted in the windowed and decayed scores as well.
+CREATE TABLE IF NOT EXISTS score_bonus (
+    id bigserial NOT NULL,
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    amount double precision NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY(id)
+);
+
+CREATE INDEX IF NOT EXISTS score_bonus_guild_id_idx
+    ON score_bonus(guild_id, created_at);
@@ -0,0 +1,21 @@
+-- NOTE: the votes of the suspicious voters may carry less weight. The
+-- all-time scores count the votes by their weight, same as the scores
+-- computed from the votes. The weighted sum is kept along with the score,
+-- which is the sum rounded, so that the fractional votes add up.
+ALTER TABLE vote
+    ADD COLUMN IF NOT EXISTS weight real NOT NULL DEFAULT 1,
+    ADD COLUMN IF NOT EXISTS voter_joined_at timestamptz;
+
+ALTER TABLE "user"
+    ADD COLUMN IF NOT EXISTS weighted_score double precision NOT NULL DEFAULT 0;
+
+UPDATE "user"
+SET weighted_score = "user".score;
+
+CREATE INDEX IF NOT EXISTS post_guild_id_idx ON post(guild_id);
+
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS min_member_days integer
+        CHECK (min_member_days > 0),
+    ADD COLUMN IF NOT EXISTS flagged_vote_weight real
+        CHECK (flagged_vote_weight >= 0 AND flagged_vote_weight <= 1);
@@ -0,0 +1,17 @@
+-- NOTE: the votes beyond the daily budget are recorded with zero weight,
+-- and the voters are notified at most once a day.
+ALTER TABLE guild_config
+    ADD COLUMN IF NOT EXISTS max_daily_downvotes integer
+        CHECK (max_daily_downvotes > 0),
+    ADD COLUMN IF NOT EXISTS max_daily_author_votes integer
+        CHECK (max_daily_author_votes > 0),
+    ADD COLUMN IF NOT EXISTS min_account_days integer
+        CHECK (min_account_days > 0),
+    ADD COLUMN IF NOT EXISTS notify_over_budget boolean NOT NULL DEFAULT false;
+
+CREATE TABLE IF NOT EXISTS budget_notice (
+    guild_id bigint NOT NULL,
+    user_id bigint NOT NULL,
+    notified_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (guild_id, user_id)
+);
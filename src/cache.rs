@@ -0,0 +1,47 @@
+use std::{
+    hash::Hash,
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+
+/// A thread safe least recently used cache, whose entries
+/// expire after the provided time to live.
+pub struct TtlCache<K: Hash + Eq, V: Clone> {
+    entries: Mutex<LruCache<K, (Instant, V)>>,
+    ttl: Duration,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+    /// Create a cache holding at most `capacity` entries, each
+    /// of them valid for `ttl` after being inserted.
+    pub fn new(capacity: usize, ttl: Duration) -> TtlCache<K, V> {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        TtlCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    /// Get the value cached for the provided key, if
+    /// it exists and has not yet expired.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            | Some((inserted, value)) if inserted.elapsed() < self.ttl => {
+                Some(value.clone())
+            }
+            | Some(_) => {
+                entries.pop(key);
+                None
+            }
+            | None => None,
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V) {
+        self.entries.lock().unwrap().put(key, (Instant::now(), value));
+    }
+}
@@ -3,6 +3,15 @@ use std::{env, ops::DerefMut};
 use deadpool_postgres::{Config, ManagerConfig, Pool, RecyclingMethod};
 use tokio_postgres::NoTls;
 
+pub mod abuse;
+pub mod achievement;
+pub mod announcement;
+pub mod audit;
+pub mod guild;
+pub mod post;
+pub mod report;
+pub mod reward;
+pub mod template;
 pub mod user;
 
 mod embedded {
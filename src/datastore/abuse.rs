@@ -0,0 +1,344 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, UserId};
+
+/// The upvotes each voter of a pair must have given the other, for the
+/// pair to be flagged as reciprocal voters.
+pub const MIN_PAIR_VOTES: i64 = 5;
+
+/// The share of each voter's upvotes that must have gone to the other,
+/// for the pair to be flagged as reciprocal voters.
+pub const MIN_PAIR_SHARE: f64 = 0.5;
+
+/// The upvotes a voter must have given a single author, and no one else,
+/// to be flagged as a single target voter.
+pub const MIN_SINGLE_TARGET_VOTES: i64 = 5;
+
+/// The days after joining the guild during which a member's votes are
+/// considered for the mass voting of the new members.
+pub const NEW_MEMBER_DAYS: i32 = 7;
+
+/// The votes a member must have given within their first days in the
+/// guild to be flagged as a new member mass voting.
+pub const MIN_NEW_MEMBER_VOTES: i64 = 20;
+
+/// The subquery of the upvotes each voter has given each author, along
+/// with the voter's total upvotes.
+const GIVEN: &str = r#"
+    SELECT vote.voter_id, post.author_id,
+        COUNT(*) AS votes,
+        (SUM(COUNT(*)) OVER (PARTITION BY vote.voter_id))::bigint AS total
+    FROM vote
+    JOIN post ON post.id = vote.post_id
+    WHERE post.guild_id = $1 AND
+        vote.value > 0 AND
+        vote.voter_id <> post.author_id
+    GROUP BY vote.voter_id, post.author_id
+"#;
+
+/// A pair of users upvoting mostly each other.
+pub struct ReciprocalPair {
+    pub first_id: UserId,
+    pub second_id: UserId,
+    /// The upvotes the first user has given the second.
+    pub first_votes: i64,
+    /// The upvotes the second user has given the first.
+    pub second_votes: i64,
+}
+
+/// A user that has only ever upvoted a single author.
+pub struct SingleTargetVoter {
+    pub voter_id: UserId,
+    pub author_id: UserId,
+    pub votes: i64,
+}
+
+/// A user that voted a lot right after joining the guild.
+pub struct NewMemberVoter {
+    pub voter_id: UserId,
+    /// The votes given within the first days in the guild.
+    pub votes: i64,
+    /// When the user joined the guild, as a unix timestamp.
+    pub joined_at: i64,
+}
+
+/// Gets up to the provided number of reciprocal voting pairs of the guild
+/// identified by the provided id, with the most votes listed first.
+pub async fn get_reciprocal_pairs(
+    pool: &Pool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<ReciprocalPair>, String> {
+    log::trace!("Fetching reciprocal voting pairs for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            format!(
+                r#"
+                WITH given AS ({})
+                SELECT a.voter_id, a.author_id, a.votes, b.votes
+                FROM given a
+                JOIN given b ON b.voter_id = a.author_id AND
+                    b.author_id = a.voter_id
+                WHERE a.voter_id < a.author_id AND
+                    a.votes >= $2 AND
+                    b.votes >= $2 AND
+                    a.votes >= a.total * $3::float8 AND
+                    b.votes >= b.total * $3::float8
+                ORDER BY a.votes + b.votes DESC
+                LIMIT $4;
+                "#,
+                GIVEN
+            )
+            .as_str(),
+            &[&(i64::from(guild_id)), &MIN_PAIR_VOTES, &MIN_PAIR_SHARE, &limit],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| ReciprocalPair {
+                    first_id: UserId::from(row.get::<usize, i64>(0) as u64),
+                    second_id: UserId::from(row.get::<usize, i64>(1) as u64),
+                    first_votes: row.get(2),
+                    second_votes: row.get(3),
+                })
+                .collect()
+        })
+}
+
+/// Gets up to the provided number of single target voters of the guild
+/// identified by the provided id, with the most votes listed first.
+pub async fn get_single_target_voters(
+    pool: &Pool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<SingleTargetVoter>, String> {
+    log::trace!("Fetching single target voters for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            format!(
+                r#"
+                WITH given AS ({})
+                SELECT voter_id, author_id, votes
+                FROM given
+                WHERE votes = total AND
+                    votes >= $2
+                ORDER BY votes DESC
+                LIMIT $3;
+                "#,
+                GIVEN
+            )
+            .as_str(),
+            &[&(i64::from(guild_id)), &MIN_SINGLE_TARGET_VOTES, &limit],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| SingleTargetVoter {
+                    voter_id: UserId::from(row.get::<usize, i64>(0) as u64),
+                    author_id: UserId::from(row.get::<usize, i64>(1) as u64),
+                    votes: row.get(2),
+                })
+                .collect()
+        })
+}
+
+/// Gets up to the provided number of new members of the guild identified
+/// by the provided id that mass voted, with the most votes listed first.
+pub async fn get_new_member_voters(
+    pool: &Pool,
+    guild_id: GuildId,
+    limit: i64,
+) -> Result<Vec<NewMemberVoter>, String> {
+    log::trace!("Fetching new member voters for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT vote.voter_id, COUNT(*) AS votes,
+                extract(epoch FROM MIN(vote.voter_joined_at))::bigint
+            FROM vote
+            JOIN post ON post.id = vote.post_id
+            WHERE post.guild_id = $1 AND
+                vote.created_at < vote.voter_joined_at +
+                    make_interval(days => $2)
+            GROUP BY vote.voter_id
+            HAVING COUNT(*) >= $3
+            ORDER BY votes DESC
+            LIMIT $4;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &NEW_MEMBER_DAYS,
+                &MIN_NEW_MEMBER_VOTES,
+                &limit,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| NewMemberVoter {
+                    voter_id: UserId::from(row.get::<usize, i64>(0) as u64),
+                    votes: row.get(1),
+                    joined_at: row.get(2),
+                })
+                .collect()
+        })
+}
+
+/// The subquery of the upvotes each user of the pair identified by `$2`
+/// and `$3` has given the other, along with the user's total upvotes. Only
+/// the votes of the pair are read, through the index on the voters, as it
+/// is checked on each vote.
+const PAIR_GIVEN: &str = r#"
+    SELECT vote.voter_id,
+        COUNT(*) FILTER (
+            WHERE post.author_id IN ($2, $3)
+        ) AS votes,
+        COUNT(*) AS total
+    FROM vote
+    JOIN post ON post.id = vote.post_id
+    WHERE vote.voter_id IN ($2, $3) AND
+        post.guild_id = $1 AND
+        vote.value > 0 AND
+        vote.voter_id <> post.author_id
+    GROUP BY vote.voter_id
+"#;
+
+/// Whether the votes of the provided voter on the provided author's posts
+/// are flagged, as the two are a reciprocal voting pair, the voter only
+/// upvotes the author, or the voter is a new member mass voting.
+pub async fn is_flagged(
+    pool: &Pool,
+    guild_id: GuildId,
+    voter_id: UserId,
+    author_id: UserId,
+) -> Result<bool, String> {
+    log::trace!(
+        "Checking whether votes of {} on {} are flagged",
+        voter_id,
+        author_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_one(
+            format!(
+                r#"
+                WITH given AS ({})
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM given a
+                    JOIN given b ON b.voter_id = $3
+                    WHERE a.voter_id = $2 AND
+                        a.votes >= $4 AND
+                        b.votes >= $4 AND
+                        a.votes >= a.total * $5::float8 AND
+                        b.votes >= b.total * $5::float8
+                ) OR EXISTS (
+                    SELECT 1
+                    FROM given
+                    WHERE voter_id = $2 AND
+                        votes = total AND
+                        votes >= $6
+                ) OR EXISTS (
+                    SELECT 1
+                    FROM vote
+                    JOIN post ON post.id = vote.post_id
+                    WHERE post.guild_id = $1 AND
+                        vote.voter_id = $2 AND
+                        vote.created_at < vote.voter_joined_at +
+                            make_interval(days => $7)
+                    HAVING COUNT(*) >= $8
+                );
+                "#,
+                PAIR_GIVEN
+            )
+            .as_str(),
+            &[
+                &(i64::from(guild_id)),
+                &(i64::from(voter_id)),
+                &(i64::from(author_id)),
+                &MIN_PAIR_VOTES,
+                &MIN_PAIR_SHARE,
+                &MIN_SINGLE_TARGET_VOTES,
+                &NEW_MEMBER_DAYS,
+                &MIN_NEW_MEMBER_VOTES,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.get(0))
+}
+
+/// Gets the number of counted downvotes the provided voter has given in
+/// the guild identified by the provided id since the provided time, along
+/// with the number of counted votes they have given the provided author.
+pub async fn count_votes_since(
+    pool: &Pool,
+    guild_id: GuildId,
+    voter_id: UserId,
+    author_id: UserId,
+    since: i64,
+) -> Result<(i64, i64), String> {
+    log::trace!("Counting votes of {} since {}", voter_id, since);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_one(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE vote.value < 0),
+                COUNT(*) FILTER (WHERE post.author_id = $3)
+            FROM vote
+            JOIN post ON post.id = vote.post_id
+            WHERE post.guild_id = $1 AND
+                vote.voter_id = $2 AND
+                vote.weight > 0 AND
+                vote.created_at >= to_timestamp($4::bigint);
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &(i64::from(voter_id)),
+                &(i64::from(author_id)),
+                &since,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| (row.get(0), row.get(1)))
+}
+
+/// Record that the provided voter was notified of going over their vote
+/// budget, unless they were already notified since the provided time.
+/// Returns false when they were, so that they are only notified once.
+pub async fn claim_budget_notice(
+    pool: &Pool,
+    guild_id: GuildId,
+    voter_id: UserId,
+    since: i64,
+) -> Result<bool, String> {
+    log::trace!("Claiming budget notice for {}", voter_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO budget_notice(guild_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT(guild_id, user_id)
+                DO UPDATE
+                SET notified_at = now()
+                WHERE budget_notice.notified_at < to_timestamp($3::bigint);
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(voter_id)), &since],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|claimed| claimed > 0)
+}
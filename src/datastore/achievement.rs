@@ -0,0 +1,54 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, UserId};
+
+/// Record the achievement with the provided name as unlocked by the user
+/// identified by the provided id. Returns false when it was already
+/// unlocked.
+pub async fn unlock(
+    pool: &Pool,
+    guild_id: GuildId,
+    user_id: UserId,
+    name: &str,
+) -> Result<bool, String> {
+    log::trace!("Unlocking achievement '{}' for user {}", name, user_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO achievement(guild_id, user_id, name)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(user_id)), &name],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|inserted| inserted > 0)
+}
+
+/// Gets a vector of name, unlock time pairs of the achievements unlocked by
+/// the user identified by the provided id. The unlock times are unix
+/// timestamps.
+pub async fn get_unlocked(
+    pool: &Pool,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Vec<(String, i64)>, String> {
+    log::trace!("Fetching achievements of user {}", user_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT name, extract(epoch FROM unlocked_at)::bigint
+            FROM achievement
+            WHERE achievement.guild_id = $1 AND
+                achievement.user_id = $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(user_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
@@ -0,0 +1,270 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{ChannelId, GuildId, RoleId, UserId};
+use tokio_postgres::Row;
+
+use super::user;
+
+/// An announcement of the guild's top post, sent on a schedule.
+pub struct Announcement {
+    pub guild_id: GuildId,
+    pub name: String,
+    /// A cron expression, evaluated in the guild's time zone.
+    pub schedule: String,
+    pub channel_id: ChannelId,
+    /// The points added to the score of the top post's author.
+    pub bonus: i64,
+    /// The role granted to the top post's author, and revoked from the
+    /// previous one.
+    pub role_id: Option<RoleId>,
+    /// The scheduled time of the last announcement, as a unix timestamp.
+    pub last_run: i64,
+    pub last_winner_id: Option<UserId>,
+}
+
+impl Announcement {
+    fn from_row(row: &Row) -> Announcement {
+        Announcement {
+            guild_id: GuildId::from(row.get::<usize, i64>(0) as u64),
+            name: row.get(1),
+            schedule: row.get(2),
+            channel_id: ChannelId::from(row.get::<usize, i64>(3) as u64),
+            bonus: row.get(4),
+            role_id: row
+                .get::<usize, Option<i64>>(5)
+                .map(|id| RoleId::from(id as u64)),
+            last_run: row.get(6),
+            last_winner_id: row
+                .get::<usize, Option<i64>>(7)
+                .map(|id| UserId::from(id as u64)),
+        }
+    }
+}
+
+const COLUMNS: &str = r#"
+    guild_id, name, schedule, channel_id, bonus, role_id,
+    extract(epoch FROM last_run)::bigint, last_winner_id
+"#;
+
+/// Gets the announcements of the guild identified by the provided id.
+pub async fn get_announcements(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<Vec<Announcement>, String> {
+    log::trace!("Fetching announcements for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            format!(
+                r#"
+                SELECT {}
+                FROM announcement
+                WHERE announcement.guild_id = $1
+                ORDER BY name;
+                "#,
+                COLUMNS
+            )
+            .as_str(),
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(Announcement::from_row).collect())
+}
+
+/// Gets the announcements of all the guilds.
+pub async fn get_all_announcements(
+    pool: &Pool,
+) -> Result<Vec<Announcement>, String> {
+    log::trace!("Fetching all announcements");
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(format!("SELECT {} FROM announcement;", COLUMNS).as_str(), &[])
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(Announcement::from_row).collect())
+}
+
+/// Add the announcement with the provided name to the guild identified by
+/// the provided id, or update it if it already exists. The announcements
+/// are first sent on the schedule's next run after they are added.
+pub async fn set_announcement(
+    pool: &Pool,
+    guild_id: GuildId,
+    name: &str,
+    schedule: &str,
+    channel_id: ChannelId,
+    bonus: i64,
+    role_id: Option<RoleId>,
+) -> Result<(), String> {
+    log::trace!("Setting announcement '{}' for guild: {}", name, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO announcement(
+                guild_id, name, schedule, channel_id, bonus, role_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT(guild_id, name)
+                DO UPDATE
+                SET schedule = $3,
+                    channel_id = $4,
+                    bonus = $5,
+                    role_id = $6;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &name,
+                &schedule,
+                &(i64::from(channel_id)),
+                &bonus,
+                &role_id.map(i64::from),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Remove the announcement with the provided name from the guild
+/// identified by the provided id. Returns false if there was no such
+/// announcement.
+pub async fn remove_announcement(
+    pool: &Pool,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<bool, String> {
+    log::trace!("Removing announcement '{}' for guild: {}", name, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            DELETE FROM announcement
+            WHERE announcement.guild_id = $1 AND
+                announcement.name = $2;
+            "#,
+            &[&(i64::from(guild_id)), &name],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|removed| removed > 0)
+}
+
+/// Move the last run of the provided announcement to the provided time,
+/// if it was not moved since the announcement was fetched. Returns false
+/// when the run was already claimed, so that it is not announced twice.
+/// The provided winner is recorded with the claim, and the announcement's
+/// bonus is added to their score, so that a claimed run always has its
+/// winner.
+pub async fn claim_run(
+    pool: &Pool,
+    announcement: &Announcement,
+    run: i64,
+    winner_id: Option<UserId>,
+) -> Result<bool, String> {
+    log::trace!(
+        "Claiming run {} of announcement '{}' for guild: {}",
+        run,
+        announcement.name,
+        announcement.guild_id
+    );
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    let claimed = transaction
+        .execute(
+            r#"
+            UPDATE announcement
+            SET last_run = to_timestamp($3::bigint),
+                last_winner_id = COALESCE($5, announcement.last_winner_id)
+            WHERE announcement.guild_id = $1 AND
+                announcement.name = $2 AND
+                extract(epoch FROM announcement.last_run)::bigint = $4;
+            "#,
+            &[
+                &(i64::from(announcement.guild_id)),
+                &announcement.name,
+                &run,
+                &announcement.last_run,
+                &winner_id.map(i64::from),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?
+        > 0;
+    if !claimed {
+        return Ok(false);
+    }
+    if let Some(winner_id) = winner_id {
+        if announcement.bonus != 0 {
+            user::add_bonus(
+                &*transaction,
+                winner_id,
+                announcement.guild_id,
+                announcement.bonus as f64,
+            )
+            .await?;
+        }
+    }
+    transaction.commit().await.map_err(|err| err.to_string())?;
+    Ok(true)
+}
+
+/// Move the last run of the provided announcement back, after the claimed
+/// run failed, so that it is retried. The winner recorded with the claim
+/// is replaced by the previous one, and their bonus is taken back.
+pub async fn release_run(
+    pool: &Pool,
+    announcement: &Announcement,
+    run: i64,
+    winner_id: Option<UserId>,
+) -> Result<(), String> {
+    log::trace!(
+        "Releasing run {} of announcement '{}' for guild: {}",
+        run,
+        announcement.name,
+        announcement.guild_id
+    );
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    let released = transaction
+        .execute(
+            r#"
+            UPDATE announcement
+            SET last_run = to_timestamp($4::bigint),
+                last_winner_id = $5
+            WHERE announcement.guild_id = $1 AND
+                announcement.name = $2 AND
+                extract(epoch FROM announcement.last_run)::bigint = $3;
+            "#,
+            &[
+                &(i64::from(announcement.guild_id)),
+                &announcement.name,
+                &run,
+                &announcement.last_run,
+                &announcement.last_winner_id.map(i64::from),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?
+        > 0;
+    if let Some(winner_id) = winner_id {
+        if released && announcement.bonus != 0 {
+            user::add_bonus(
+                &*transaction,
+                winner_id,
+                announcement.guild_id,
+                -(announcement.bonus as f64),
+            )
+            .await?;
+        }
+    }
+    transaction.commit().await.map_err(|err| err.to_string())
+}
@@ -0,0 +1,213 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, MessageId, UserId};
+use tokio_postgres::Row;
+
+use super::{post, report, user};
+
+/// A moderation action, along with its target.
+#[derive(Clone, Copy)]
+pub enum Action {
+    /// Add the provided amount to the user's score.
+    Adjust(UserId, i64),
+    /// Reset the user's score, and remove the votes on their posts.
+    ResetUser(UserId),
+    /// Reset the scores of all the guild's users, and remove all the votes.
+    ResetGuild,
+    /// Remove the votes on the post, and ignore the future ones.
+    VoidPost(MessageId),
+    /// Ban or unban the user from voting.
+    BanFromVoting(UserId, bool),
+    /// Approve the reported post, so that it may no longer be reported.
+    ApprovePost(MessageId),
+    /// Remove the reported post, along with its votes.
+    RemovePost(MessageId),
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Action::Adjust(..) => "adjust",
+            | Action::ResetUser(_) => "reset-user",
+            | Action::ResetGuild => "reset-guild",
+            | Action::VoidPost(_) => "void-post",
+            | Action::BanFromVoting(_, true) => "ban-from-voting",
+            | Action::BanFromVoting(_, false) => "unban-from-voting",
+            | Action::ApprovePost(_) => "approve-post",
+            | Action::RemovePost(_) => "remove-post",
+        }
+    }
+
+    fn target_id(&self) -> Option<i64> {
+        match self {
+            | Action::Adjust(user_id, _)
+            | Action::ResetUser(user_id)
+            | Action::BanFromVoting(user_id, _) => Some(i64::from(*user_id)),
+            | Action::VoidPost(post_id)
+            | Action::ApprovePost(post_id)
+            | Action::RemovePost(post_id) => Some(i64::from(*post_id)),
+            | Action::ResetGuild => None,
+        }
+    }
+
+    fn amount(&self) -> Option<i64> {
+        match self {
+            | Action::Adjust(_, amount) => Some(*amount),
+            | _ => None,
+        }
+    }
+
+    fn from_row(
+        name: &str,
+        target_id: Option<i64>,
+        amount: Option<i64>,
+    ) -> Option<Action> {
+        let user_id = target_id.map(|id| UserId::from(id as u64));
+        let post_id = target_id.map(|id| MessageId::from(id as u64));
+        match name {
+            | "adjust" => Some(Action::Adjust(user_id?, amount?)),
+            | "reset-user" => Some(Action::ResetUser(user_id?)),
+            | "reset-guild" => Some(Action::ResetGuild),
+            | "void-post" => Some(Action::VoidPost(post_id?)),
+            | "ban-from-voting" => Some(Action::BanFromVoting(user_id?, true)),
+            | "unban-from-voting" => {
+                Some(Action::BanFromVoting(user_id?, false))
+            }
+            | "approve-post" => Some(Action::ApprovePost(post_id?)),
+            | "remove-post" => Some(Action::RemovePost(post_id?)),
+            | _ => None,
+        }
+    }
+}
+
+/// An entry of the guild's audit log.
+pub struct Entry {
+    pub moderator_id: UserId,
+    pub action: Action,
+    pub reason: Option<String>,
+    /// When the action was taken, as a unix timestamp.
+    pub created_at: i64,
+}
+
+impl Entry {
+    fn from_row(row: &Row) -> Option<Entry> {
+        Some(Entry {
+            moderator_id: UserId::from(row.get::<usize, i64>(0) as u64),
+            action: Action::from_row(row.get(1), row.get(2), row.get(3))?,
+            reason: row.get(4),
+            created_at: row.get(5),
+        })
+    }
+}
+
+/// Take the provided action in the guild identified by the provided id,
+/// and record it in the guild's audit log along with the moderator that
+/// took it. Returns false when the action's target does not exist, in
+/// which case nothing is recorded.
+pub async fn take_action(
+    pool: &Pool,
+    guild_id: GuildId,
+    moderator_id: UserId,
+    action: Action,
+    reason: Option<&str>,
+) -> Result<bool, String> {
+    log::trace!(
+        "Taking action '{}' by {} in guild: {}",
+        action.name(),
+        moderator_id,
+        guild_id
+    );
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    match action {
+        | Action::Adjust(user_id, amount) => {
+            user::add_bonus(&*transaction, user_id, guild_id, amount as f64)
+                .await?
+        }
+        | Action::ResetUser(user_id) => {
+            post::clear_votes(&*transaction, guild_id, Some(user_id)).await?;
+            user::reset_score(&*transaction, Some(user_id), guild_id).await?;
+        }
+        | Action::ResetGuild => {
+            post::clear_votes(&*transaction, guild_id, None).await?;
+            user::reset_score(&*transaction, None, guild_id).await?;
+        }
+        | Action::VoidPost(post_id) => {
+            if post::void_post(&*transaction, guild_id, post_id)
+                .await?
+                .is_none()
+            {
+                return Ok(false);
+            }
+        }
+        | Action::BanFromVoting(user_id, banned) => {
+            user::set_voting_banned(&*transaction, user_id, guild_id, banned)
+                .await?
+        }
+        | Action::ApprovePost(post_id) => {
+            if !report::approve(&*transaction, guild_id, post_id).await? {
+                return Ok(false);
+            }
+        }
+        | Action::RemovePost(post_id) => {
+            if post::remove_post(&*transaction, guild_id, post_id)
+                .await?
+                .is_none()
+            {
+                return Ok(false);
+            }
+        }
+    }
+    transaction
+        .execute(
+            r#"
+            INSERT INTO audit_log(
+                guild_id, moderator_id, action, target_id, amount, reason
+            )
+            VALUES ($1, $2, $3, $4, $5, $6);
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &(i64::from(moderator_id)),
+                &action.name(),
+                &action.target_id(),
+                &action.amount(),
+                &reason,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    transaction.commit().await.map_err(|err| err.to_string())?;
+    Ok(true)
+}
+
+/// Gets up to the provided number of the latest entries of the audit log
+/// of the guild identified by the provided id, only those targeting the
+/// provided user when one is provided.
+pub async fn get_entries(
+    pool: &Pool,
+    guild_id: GuildId,
+    target_id: Option<UserId>,
+    limit: i64,
+) -> Result<Vec<Entry>, String> {
+    log::trace!("Fetching audit log for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT moderator_id, action, target_id, amount, reason,
+                extract(epoch FROM created_at)::bigint
+            FROM audit_log
+            WHERE audit_log.guild_id = $1 AND
+                ($2::bigint IS NULL OR audit_log.target_id = $2)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $3;
+            "#,
+            &[&(i64::from(guild_id)), &target_id.map(i64::from), &limit],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().filter_map(Entry::from_row).collect())
+}
@@ -0,0 +1,641 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{ChannelId, GuildId};
+
+use super::user::{Decay, DecayMode, Period, Window};
+
+/// Gets the names of the commands disabled in the guild identified
+/// by the provided id. If there is no config for the guild, an empty
+/// vector is returned.
+pub async fn get_disabled_commands(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<Vec<String>, String> {
+    log::trace!("Fetching disabled commands for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT disabled_commands
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.map_or(Vec::new(), |row| row.get(0)))
+}
+
+/// Enable or disable the command with the provided name in the
+/// guild identified by the provided id. If there is no config for
+/// the guild yet, a new record is added.
+pub async fn set_command_enabled(
+    pool: &Pool,
+    guild_id: GuildId,
+    name: &str,
+    enabled: bool,
+) -> Result<(), String> {
+    log::trace!(
+        "Setting command '{}' enabled to {} for guild: {}",
+        name,
+        enabled,
+        guild_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let query = if enabled {
+        r#"
+        INSERT INTO guild_config(guild_id)
+        VALUES ($1)
+        ON CONFLICT(guild_id)
+            DO UPDATE
+            SET disabled_commands = array_remove(
+                guild_config.disabled_commands, $2
+            );
+        "#
+    } else {
+        r#"
+        INSERT INTO guild_config(guild_id, disabled_commands)
+        VALUES ($1, ARRAY[$2::text])
+        ON CONFLICT(guild_id)
+            DO UPDATE
+            SET disabled_commands = array_append(
+                array_remove(guild_config.disabled_commands, $2), $2
+            );
+        "#
+    };
+    client
+        .execute(query, &[&(i64::from(guild_id)), &name])
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Gets the channels of the guild identified by the provided id where the
+/// memes are always sent as spoilers. If there is no config for the guild,
+/// an empty vector is returned.
+pub async fn get_spoiler_channels(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<Vec<ChannelId>, String> {
+    log::trace!("Fetching spoiler channels for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT spoiler_channel_ids
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map_or(Vec::new(), |row| {
+                row.get::<usize, Vec<i64>>(0)
+                    .into_iter()
+                    .map(|id| ChannelId::from(id as u64))
+                    .collect()
+            })
+        })
+}
+
+/// Set whether the memes are always sent as spoilers in the provided
+/// channel of the guild identified by the provided id. If there is no
+/// config for the guild yet, a new record is added.
+pub async fn set_spoiler_channel(
+    pool: &Pool,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    forced: bool,
+) -> Result<(), String> {
+    log::trace!(
+        "Setting spoilers forced to {} in channel {} for guild: {}",
+        forced,
+        channel_id,
+        guild_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let query = if forced {
+        r#"
+        INSERT INTO guild_config(guild_id, spoiler_channel_ids)
+        VALUES ($1, ARRAY[$2::bigint])
+        ON CONFLICT(guild_id)
+            DO UPDATE
+            SET spoiler_channel_ids = array_append(
+                array_remove(guild_config.spoiler_channel_ids, $2), $2
+            );
+        "#
+    } else {
+        r#"
+        INSERT INTO guild_config(guild_id)
+        VALUES ($1)
+        ON CONFLICT(guild_id)
+            DO UPDATE
+            SET spoiler_channel_ids = array_remove(
+                guild_config.spoiler_channel_ids, $2
+            );
+        "#
+    };
+    client
+        .execute(query, &[&(i64::from(guild_id)), &(i64::from(channel_id))])
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Gets the time zone of the guild identified by the provided id.
+/// If there is no config for the guild, UTC is returned.
+pub async fn get_timezone(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    log::trace!("Fetching time zone for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT timezone
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.map_or(String::from("UTC"), |row| row.get(0)))
+}
+
+/// Set the time zone of the guild identified by the provided id. The
+/// time zone is not set, and false is returned, when it is not known
+/// to the database.
+pub async fn set_timezone(
+    pool: &Pool,
+    guild_id: GuildId,
+    timezone: &str,
+) -> Result<bool, String> {
+    log::trace!("Setting time zone '{}' for guild: {}", timezone, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO guild_config(guild_id, timezone)
+            SELECT $1, name
+            FROM pg_timezone_names
+            WHERE pg_timezone_names.name = $2
+            ON CONFLICT(guild_id)
+                DO UPDATE
+                SET timezone = excluded.timezone;
+            "#,
+            &[&(i64::from(guild_id)), &timezone],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|updated| updated > 0)
+}
+
+/// Gets the bounds of the provided period, as unix timestamps, with the
+/// days, weeks and months starting at midnight in the time zone of the
+/// guild identified by the provided id. `None` is returned for the
+/// all-time period.
+pub async fn get_window(
+    pool: &Pool,
+    guild_id: GuildId,
+    period: &Period,
+) -> Result<Option<Window>, String> {
+    let (start, end) = match period {
+        | Period::AllTime => return Ok(None),
+        | Period::Today => (
+            "date_trunc('day', now() AT TIME ZONE tz)",
+            "date_trunc('day', now() AT TIME ZONE tz) + interval '1 day'",
+        ),
+        | Period::Week => (
+            "date_trunc('week', now() AT TIME ZONE tz)",
+            "date_trunc('week', now() AT TIME ZONE tz) + interval '1 week'",
+        ),
+        | Period::Month => (
+            "date_trunc('month', now() AT TIME ZONE tz)",
+            "date_trunc('month', now() AT TIME ZONE tz) + interval '1 month'",
+        ),
+        | Period::Last7Days => (
+            "now() AT TIME ZONE tz - interval '7 days'",
+            "now() AT TIME ZONE tz",
+        ),
+        | Period::Last30Days => (
+            "now() AT TIME ZONE tz - interval '30 days'",
+            "now() AT TIME ZONE tz",
+        ),
+        | Period::Range(..) => (
+            "$2::text::date::timestamp",
+            "$3::text::date::timestamp + interval '1 day'",
+        ),
+    };
+    log::trace!("Fetching {} window for guild: {}", period.name(), guild_id);
+
+    let timezone = get_timezone(pool, guild_id).await?;
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let query = format!(
+        r#"
+        SELECT extract(epoch FROM ({}) AT TIME ZONE tz)::bigint,
+            extract(epoch FROM ({}) AT TIME ZONE tz)::bigint
+        FROM (SELECT $1::text AS tz) guild;
+        "#,
+        start, end
+    );
+    let row = match period {
+        | Period::Range(from, to) => {
+            client.query_one(query.as_str(), &[&timezone, from, to]).await
+        }
+        | _ => client.query_one(query.as_str(), &[&timezone]).await,
+    }
+    .map_err(|err| err.to_string())?;
+    Ok(Some(Window {
+        start: row.get(0),
+        end: row.get(1),
+    }))
+}
+
+/// Gets the decay of the scores of the guild identified by the provided
+/// id, measured from the start of the current day in the guild's time
+/// zone. If the scores are not decayed, `None` is returned.
+pub async fn get_decay(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<Option<Decay>, String> {
+    log::trace!("Fetching decay for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let row = client
+        .query_opt(
+            r#"
+            SELECT decay_mode, decay_half_life_days,
+                extract(epoch FROM
+                    date_trunc('day', now() AT TIME ZONE timezone)
+                    AT TIME ZONE timezone
+                )::bigint
+            FROM guild_config
+            WHERE guild_config.guild_id = $1 AND
+                guild_config.decay_mode IS NOT NULL;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(row.and_then(|row| {
+        Some(Decay {
+            mode: DecayMode::from_name(row.get::<usize, &str>(0))?,
+            half_life_days: row.get(1),
+            reference: row.get(2),
+        })
+    }))
+}
+
+/// Set the decay of the scores of the guild identified by the provided
+/// id, as the mode and the half-life in days. The scores are no longer
+/// decayed when `None` is provided.
+pub async fn set_decay(
+    pool: &Pool,
+    guild_id: GuildId,
+    decay: Option<(DecayMode, i32)>,
+) -> Result<(), String> {
+    log::trace!("Setting decay for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO guild_config(
+                guild_id, decay_mode, decay_half_life_days
+            )
+            VALUES ($1, $2, $3)
+            ON CONFLICT(guild_id)
+                DO UPDATE
+                SET decay_mode = $2,
+                    decay_half_life_days = $3;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &decay.map(|(mode, _)| mode.name()),
+                &decay.map(|(_, days)| days),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// The mitigations applied to the votes of the guild's suspicious voters.
+#[derive(Clone, Copy, Default)]
+pub struct VotingConfig {
+    /// The days a member must have been in the guild for before their
+    /// votes are counted.
+    pub min_member_days: Option<i32>,
+    /// The weight of the votes of the flagged voters, between 0 and 1.
+    pub flagged_vote_weight: Option<f32>,
+    /// The downvotes a voter may give each day.
+    pub max_daily_downvotes: Option<i32>,
+    /// The votes a voter may give each author each day.
+    pub max_daily_author_votes: Option<i32>,
+    /// The days a voter's account must have existed for before their votes
+    /// are counted.
+    pub min_account_days: Option<i32>,
+    /// Whether the voters are notified once a day when they go over their
+    /// daily vote budget.
+    pub notify_over_budget: bool,
+    /// The days after which the votes on a post are no longer counted.
+    pub voting_window_days: Option<i32>,
+    /// Whether the posts are edited to show their final tally once their
+    /// voting window closes.
+    pub show_final_tally: bool,
+}
+
+/// Gets the voting config of the guild identified by the provided id.
+/// If there is no config for the guild, no mitigations are returned.
+pub async fn get_voting_config(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<VotingConfig, String> {
+    log::trace!("Fetching voting config for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT min_member_days, flagged_vote_weight, max_daily_downvotes,
+                max_daily_author_votes, min_account_days, notify_over_budget,
+                voting_window_days, show_final_tally
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map_or(VotingConfig::default(), |row| VotingConfig {
+                min_member_days: row.get(0),
+                flagged_vote_weight: row.get(1),
+                max_daily_downvotes: row.get(2),
+                max_daily_author_votes: row.get(3),
+                min_account_days: row.get(4),
+                notify_over_budget: row.get(5),
+                voting_window_days: row.get(6),
+                show_final_tally: row.get(7),
+            })
+        })
+}
+
+/// Set the voting config of the guild identified by the provided id.
+pub async fn set_voting_config(
+    pool: &Pool,
+    guild_id: GuildId,
+    config: &VotingConfig,
+) -> Result<(), String> {
+    log::trace!("Setting voting config for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO guild_config(
+                guild_id, min_member_days, flagged_vote_weight,
+                max_daily_downvotes, max_daily_author_votes, min_account_days,
+                notify_over_budget, voting_window_days, show_final_tally
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT(guild_id)
+                DO UPDATE
+                SET min_member_days = $2,
+                    flagged_vote_weight = $3,
+                    max_daily_downvotes = $4,
+                    max_daily_author_votes = $5,
+                    min_account_days = $6,
+                    notify_over_budget = $7,
+                    voting_window_days = $8,
+                    show_final_tally = $9;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &config.min_member_days,
+                &config.flagged_vote_weight,
+                &config.max_daily_downvotes,
+                &config.max_daily_author_votes,
+                &config.min_account_days,
+                &config.notify_over_budget,
+                &config.voting_window_days,
+                &config.show_final_tally,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// How a post is hidden once it reaches the report threshold.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum HideMode {
+    /// The post's attachments and content are marked as spoilers.
+    #[default]
+    Spoiler,
+    /// The post's message is deleted, its votes are kept until reviewed.
+    Delete,
+}
+
+impl HideMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | HideMode::Spoiler => "spoiler",
+            | HideMode::Delete => "delete",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<HideMode> {
+        match name {
+            | "spoiler" => Some(HideMode::Spoiler),
+            | "delete" => Some(HideMode::Delete),
+            | _ => None,
+        }
+    }
+}
+
+/// How the guild's members may report the posts.
+#[derive(Clone, Copy, Default)]
+pub struct ReportConfig {
+    /// The channel the review cards of the reported posts are sent to.
+    pub channel_id: Option<ChannelId>,
+    /// The reports after which a post is hidden until reviewed.
+    pub threshold: Option<i32>,
+    pub hide_mode: HideMode,
+}
+
+/// Gets the report config of the guild identified by the provided id.
+/// If there is no config for the guild, the reported posts are never
+/// hidden and no review cards are sent.
+pub async fn get_report_config(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<ReportConfig, String> {
+    log::trace!("Fetching report config for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT report_channel_id, report_threshold, report_hide_mode
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map_or(ReportConfig::default(), |row| ReportConfig {
+                channel_id: row
+                    .get::<usize, Option<i64>>(0)
+                    .map(|id| ChannelId::from(id as u64)),
+                threshold: row.get(1),
+                hide_mode: HideMode::from_name(row.get(2)).unwrap_or_default(),
+            })
+        })
+}
+
+/// Set the report config of the guild identified by the provided id.
+pub async fn set_report_config(
+    pool: &Pool,
+    guild_id: GuildId,
+    config: &ReportConfig,
+) -> Result<(), String> {
+    log::trace!("Setting report config for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO guild_config(
+                guild_id, report_channel_id, report_threshold, report_hide_mode
+            )
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT(guild_id)
+                DO UPDATE
+                SET report_channel_id = $2,
+                    report_threshold = $3,
+                    report_hide_mode = $4;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &config.channel_id.map(i64::from),
+                &config.threshold,
+                &config.hide_mode.name(),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// The rules the attachments of the guild's memes must follow.
+#[derive(Clone)]
+pub struct AttachmentRules {
+    /// The prefixes of the content types that may be posted.
+    pub allowed_types: Vec<String>,
+    /// The size of the largest attachment, in megabytes.
+    pub max_size_mb: Option<i32>,
+    /// The width and height of the largest image or video, in pixels.
+    pub max_dimension: Option<i32>,
+    /// The attachments that may be posted at once.
+    pub max_count: Option<i32>,
+    /// Whether the metadata of the images, such as the EXIF location, is
+    /// stripped before they are posted.
+    pub strip_metadata: bool,
+}
+
+impl Default for AttachmentRules {
+    fn default() -> AttachmentRules {
+        AttachmentRules {
+            allowed_types: vec![String::from("image/"), String::from("video/")],
+            max_size_mb: None,
+            max_dimension: None,
+            max_count: None,
+            strip_metadata: true,
+        }
+    }
+}
+
+/// Gets the attachment rules of the guild identified by the provided id.
+/// If there is no config for the guild, the images and videos may be
+/// posted without limits, with their metadata stripped.
+pub async fn get_attachment_rules(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<AttachmentRules, String> {
+    log::trace!("Fetching attachment rules for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT allowed_attachment_types, max_attachment_mb,
+                max_attachment_dimension, max_attachments, strip_metadata
+            FROM guild_config
+            WHERE guild_config.guild_id = $1;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map_or(AttachmentRules::default(), |row| AttachmentRules {
+                allowed_types: row.get(0),
+                max_size_mb: row.get(1),
+                max_dimension: row.get(2),
+                max_count: row.get(3),
+                strip_metadata: row.get(4),
+            })
+        })
+}
+
+/// Set the attachment rules of the guild identified by the provided id.
+pub async fn set_attachment_rules(
+    pool: &Pool,
+    guild_id: GuildId,
+    rules: &AttachmentRules,
+) -> Result<(), String> {
+    log::trace!("Setting attachment rules for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO guild_config(
+                guild_id, allowed_attachment_types, max_attachment_mb,
+                max_attachment_dimension, max_attachments, strip_metadata
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT(guild_id)
+                DO UPDATE
+                SET allowed_attachment_types = $2,
+                    max_attachment_mb = $3,
+                    max_attachment_dimension = $4,
+                    max_attachments = $5,
+                    strip_metadata = $6;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &rules.allowed_types,
+                &rules.max_size_mb,
+                &rules.max_dimension,
+                &rules.max_count,
+                &rules.strip_metadata,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
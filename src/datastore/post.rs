@@ -0,0 +1,863 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{
+    ChannelId, GuildId, Message, MessageId, UserId,
+};
+use tokio_postgres::{GenericClient, Row};
+
+use super::user;
+
+/// A post sent through memsther, along with the votes it received.
+pub struct PostVotes {
+    pub id: MessageId,
+    pub channel_id: ChannelId,
+    pub author_id: UserId,
+    pub upvotes: i64,
+    pub downvotes: i64,
+}
+
+impl PostVotes {
+    fn from_row(row: &Row) -> PostVotes {
+        PostVotes {
+            id: MessageId::from(row.get::<usize, i64>(0) as u64),
+            channel_id: ChannelId::from(row.get::<usize, i64>(1) as u64),
+            author_id: UserId::from(row.get::<usize, i64>(2) as u64),
+            upvotes: row.get(3),
+            downvotes: row.get(4),
+        }
+    }
+}
+
+/// A post sent through memsther.
+pub struct Post {
+    pub id: MessageId,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub author_id: UserId,
+    /// The name of the command the post was sent with.
+    pub kind: String,
+    /// Whether the post was sent as a spoiler.
+    pub spoiler: bool,
+    /// The content warning shown above the post, if any.
+    pub cw: Option<String>,
+}
+
+impl Post {
+    fn from_row(row: &Row) -> Post {
+        Post {
+            id: MessageId::from(row.get::<usize, i64>(0) as u64),
+            guild_id: GuildId::from(row.get::<usize, i64>(1) as u64),
+            channel_id: ChannelId::from(row.get::<usize, i64>(2) as u64),
+            author_id: UserId::from(row.get::<usize, i64>(3) as u64),
+            kind: row.get(4),
+            spoiler: row.get(5),
+            cw: row.get(6),
+        }
+    }
+}
+
+/// Gets the post identified by the provided id. If there is no such post,
+/// `None` is returned.
+pub async fn get_post(
+    pool: &Pool,
+    post_id: MessageId,
+) -> Result<Option<Post>, String> {
+    log::trace!("Fetching post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT id, guild_id, channel_id, author_id, kind, spoiler, cw
+            FROM post
+            WHERE post.id = $1;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(Post::from_row))
+}
+
+/// Gets the latest post of the user identified by the provided id in the
+/// provided guild. If the user has no posts, `None` is returned.
+pub async fn get_latest_post(
+    pool: &Pool,
+    guild_id: GuildId,
+    author_id: UserId,
+) -> Result<Option<Post>, String> {
+    log::trace!("Fetching the latest post of user {}", author_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT id, guild_id, channel_id, author_id, kind, spoiler, cw
+            FROM post
+            WHERE post.guild_id = $1 AND
+                post.author_id = $2
+            ORDER BY post.created_at DESC, post.id DESC
+            LIMIT 1;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(author_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(Post::from_row))
+}
+
+/// Whether a post is sent as a spoiler, and the content warning shown above
+/// it, so that its content may be composed again.
+#[derive(Clone, Copy, Default)]
+pub struct Marking<'a> {
+    pub spoiler: bool,
+    pub cw: Option<&'a str>,
+}
+
+/// Record the provided message as a post of the provided kind, sent by
+/// the provided author. `tracked` should be false when the post is
+/// recorded only once it receives a vote, as the votes it received before
+/// that are not recorded. Existing posts are not modified.
+pub async fn record_post(
+    pool: &Pool,
+    message: &Message,
+    guild_id: GuildId,
+    author_id: UserId,
+    kind: &str,
+    tracked: bool,
+    marking: Marking<'_>,
+) -> Result<(), String> {
+    log::trace!("Recording {} post {}", kind, message.id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO post(
+                id, guild_id, channel_id, author_id, kind, tracked, created_at,
+                spoiler, cw
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, to_timestamp($7::bigint), $8, $9
+            )
+            ON CONFLICT(id) DO NOTHING;
+            "#,
+            &[
+                &(i64::from(message.id)),
+                &(i64::from(guild_id)),
+                &(i64::from(message.channel_id)),
+                &(i64::from(author_id)),
+                &kind,
+                &tracked,
+                &message.timestamp.unix_timestamp(),
+                &marking.spoiler,
+                &marking.cw,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// A vote of a user on a post.
+pub struct Vote {
+    pub post_id: MessageId,
+    pub voter_id: UserId,
+    pub value: i16,
+    /// The weight the vote is counted with, between 0 and 1.
+    pub weight: f32,
+    /// When the voter joined the guild, as a unix timestamp.
+    pub voter_joined_at: Option<i64>,
+}
+
+impl Vote {
+    /// The value of the vote, counted by its weight.
+    pub fn weighted_value(&self) -> f64 {
+        f64::from(self.value) * f64::from(self.weight)
+    }
+}
+
+/// Record the provided vote, and add its value, counted by its weight, to
+/// the score of the post's author. The score is not modified when the vote
+/// is already recorded, or the post was voided.
+pub async fn add_vote(pool: &Pool, vote: &Vote) -> Result<(), String> {
+    log::trace!(
+        "Adding vote {} with weight {} on post {}",
+        vote.value,
+        vote.weight,
+        vote.post_id
+    );
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    let added = transaction
+        .execute(
+            r#"
+            INSERT INTO vote(post_id, voter_id, value, weight, voter_joined_at)
+            SELECT $1, $2, $3, $4, to_timestamp($5::bigint)
+            WHERE NOT EXISTS (
+                SELECT 1
+                FROM post
+                WHERE post.id = $1 AND
+                    post.voided
+            )
+            ON CONFLICT DO NOTHING;
+            "#,
+            &[
+                &(i64::from(vote.post_id)),
+                &(i64::from(vote.voter_id)),
+                &vote.value,
+                &vote.weight,
+                &vote.voter_joined_at,
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    if added > 0 && vote.weighted_value() != 0.0 {
+        let post = transaction
+            .query_one(
+                r#"
+                SELECT author_id, guild_id
+                FROM post
+                WHERE post.id = $1;
+                "#,
+                &[&(i64::from(vote.post_id))],
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+        let author_id = UserId::from(post.get::<usize, i64>(0) as u64);
+        let guild_id = GuildId::from(post.get::<usize, i64>(1) as u64);
+        user::add_score(
+            &*transaction,
+            author_id,
+            guild_id,
+            vote.weighted_value(),
+        )
+        .await?;
+    }
+    transaction.commit().await.map_err(|err| err.to_string())
+}
+
+/// Remove the vote of the provided voter from the post identified by the
+/// provided id, and subtract its value, counted by its weight, from the
+/// score of the post's author. The score is not modified when the vote was
+/// not recorded, unless the post is not tracked, as its votes may not have
+/// been recorded, in which case the vote is assumed to have full weight if
+/// the voter is not banned from voting, as their votes are never counted.
+pub async fn remove_vote(
+    pool: &Pool,
+    post_id: MessageId,
+    voter_id: UserId,
+    value: i16,
+) -> Result<(), String> {
+    log::trace!("Removing vote {} from post {}", value, post_id);
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    let removed = transaction
+        .query_opt(
+            r#"
+            DELETE FROM vote
+            WHERE vote.post_id = $1 AND
+                vote.voter_id = $2 AND
+                vote.value = $3
+            RETURNING weight;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(voter_id)), &value],
+        )
+        .await
+        .map_err(|err| err.to_string())?
+        .map(|row| row.get::<usize, f32>(0));
+    let post = transaction
+        .query_opt(
+            r#"
+            SELECT author_id, guild_id, tracked, EXISTS(
+                SELECT 1
+                FROM "user"
+                WHERE "user".id = $2 AND
+                    "user".guild_id = post.guild_id AND
+                    "user".voting_banned
+            )
+            FROM post
+            WHERE post.id = $1;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(voter_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    if let Some(post) = post {
+        let tracked = post.get::<usize, bool>(2);
+        let banned = post.get::<usize, bool>(3);
+        let weight = match removed {
+            | Some(weight) => weight,
+            | None if !tracked && !banned => 1.0,
+            | None => 0.0,
+        };
+        if weight > 0.0 {
+            let author_id = UserId::from(post.get::<usize, i64>(0) as u64);
+            let guild_id = GuildId::from(post.get::<usize, i64>(1) as u64);
+            user::add_score(
+                &*transaction,
+                author_id,
+                guild_id,
+                -f64::from(value) * f64::from(weight),
+            )
+            .await?;
+        }
+    }
+    transaction.commit().await.map_err(|err| err.to_string())
+}
+
+/// Gets the posts of the provided guild that received the most downvotes,
+/// limited by the provided limit.
+pub async fn get_most_downvoted_posts(
+    pool: &Pool,
+    guild_id: GuildId,
+    limit: u16,
+) -> Result<Vec<PostVotes>, String> {
+    log::trace!(
+        "Fetching {} most downvoted posts for guild: {}",
+        limit,
+        guild_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT post.id, post.channel_id, post.author_id,
+                COUNT(*) FILTER (WHERE vote.value > 0) AS upvotes,
+                COUNT(*) FILTER (WHERE vote.value < 0) AS downvotes
+            FROM post
+            JOIN vote ON vote.post_id = post.id
+            WHERE post.guild_id = $1
+            GROUP BY post.id
+            HAVING COUNT(*) FILTER (WHERE vote.value < 0) > 0
+            ORDER BY downvotes DESC, upvotes ASC, post.id DESC
+            LIMIT $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(limit))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(PostVotes::from_row).collect())
+}
+
+/// Gets a vector of userId, downvotes pairs of the users of the provided
+/// guild who received the most downvotes on their posts, limited by the
+/// provided limit.
+pub async fn get_most_downvoted_users(
+    pool: &Pool,
+    guild_id: GuildId,
+    limit: u16,
+) -> Result<Vec<(UserId, i64)>, String> {
+    log::trace!(
+        "Fetching {} most downvoted users for guild: {}",
+        limit,
+        guild_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT post.author_id, COUNT(*) AS downvotes
+            FROM post
+            JOIN vote ON vote.post_id = post.id
+            WHERE post.guild_id = $1 AND
+                vote.value < 0
+            GROUP BY post.author_id
+            ORDER BY downvotes DESC, post.author_id
+            LIMIT $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(limit))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| {
+                    (
+                        UserId::from(row.get::<usize, i64>(0) as u64),
+                        row.get::<usize, i64>(1),
+                    )
+                })
+                .collect()
+        })
+}
+
+/// Gets the number of upvotes and downvotes received on the posts of the
+/// user identified by the provided id.
+pub async fn get_received_votes(
+    pool: &Pool,
+    author_id: UserId,
+    guild_id: GuildId,
+) -> Result<(i64, i64), String> {
+    log::trace!("Fetching votes received by user {}", author_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_one(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE vote.value > 0),
+                COUNT(*) FILTER (WHERE vote.value < 0)
+            FROM post
+            JOIN vote ON vote.post_id = post.id
+            WHERE post.guild_id = $1 AND
+                post.author_id = $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(author_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| (row.get(0), row.get(1)))
+}
+
+/// Gets a vector of kind, count pairs of the posts of the user identified
+/// by the provided id, sorted by the count.
+pub async fn count_posts_by_kind(
+    pool: &Pool,
+    author_id: UserId,
+    guild_id: GuildId,
+) -> Result<Vec<(String, i64)>, String> {
+    log::trace!("Counting posts of user {}", author_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT kind, COUNT(*) AS posts
+            FROM post
+            WHERE post.guild_id = $1 AND
+                post.author_id = $2
+            GROUP BY kind
+            ORDER BY posts DESC, kind;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(author_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Gets the post of the user identified by the provided id with the
+/// highest difference between its upvotes and downvotes. If the user
+/// has no voted posts, `None` is returned.
+pub async fn get_best_post(
+    pool: &Pool,
+    author_id: UserId,
+    guild_id: GuildId,
+) -> Result<Option<PostVotes>, String> {
+    log::trace!("Fetching the best post of user {}", author_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT post.id, post.channel_id, post.author_id,
+                COUNT(*) FILTER (WHERE vote.value > 0) AS upvotes,
+                COUNT(*) FILTER (WHERE vote.value < 0) AS downvotes
+            FROM post
+            JOIN vote ON vote.post_id = post.id
+            WHERE post.guild_id = $1 AND
+                post.author_id = $2
+            GROUP BY post.id
+            ORDER BY SUM(vote.value) DESC, upvotes DESC, post.id DESC
+            LIMIT 1;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(author_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(PostVotes::from_row))
+}
+
+/// Gets the number of votes the user identified by the provided id
+/// has given on the posts of the provided guild.
+pub async fn count_votes_given(
+    pool: &Pool,
+    voter_id: UserId,
+    guild_id: GuildId,
+) -> Result<i64, String> {
+    log::trace!("Counting votes given by user {}", voter_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_one(
+            r#"
+            SELECT COUNT(*)
+            FROM vote
+            JOIN post ON post.id = vote.post_id
+            WHERE post.guild_id = $1 AND
+                vote.voter_id = $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(voter_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.get(0))
+}
+
+/// Gets the number of consecutive days on which the user identified by
+/// the provided id posted, ending today or yesterday, so that the streak
+/// is not lost before the day is over. The days start at midnight in the
+/// guild's time zone. Returns 0 when there is no such streak.
+pub async fn get_streak(
+    pool: &Pool,
+    author_id: UserId,
+    guild_id: GuildId,
+) -> Result<i64, String> {
+    log::trace!("Fetching the posting streak of user {}", author_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            WITH guild AS (
+                SELECT COALESCE(
+                    (
+                        SELECT timezone
+                        FROM guild_config
+                        WHERE guild_config.guild_id = $1
+                    ),
+                    'UTC'
+                ) AS tz
+            ), days AS (
+                SELECT DISTINCT (created_at AT TIME ZONE guild.tz)::date AS day
+                FROM post, guild
+                WHERE post.guild_id = $1 AND
+                    post.author_id = $2
+            ), streaks AS (
+                SELECT day,
+                    day - (ROW_NUMBER() OVER (ORDER BY day))::int AS streak
+                FROM days
+            )
+            SELECT COUNT(*)
+            FROM streaks
+            GROUP BY streak
+            HAVING MAX(day) >= (
+                SELECT (now() AT TIME ZONE guild.tz)::date - 1 FROM guild
+            )
+            ORDER BY MAX(day) DESC
+            LIMIT 1;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(author_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.map_or(0, |row| row.get(0)))
+}
+
+/// Gets the post of the provided guild, sent between the provided unix
+/// timestamps, with the highest difference between its upvotes and
+/// downvotes. Posts without a positive difference are not included.
+pub async fn get_top_post(
+    pool: &Pool,
+    guild_id: GuildId,
+    start: i64,
+    end: i64,
+) -> Result<Option<PostVotes>, String> {
+    log::trace!(
+        "Fetching the top post between {} and {} for guild: {}",
+        start,
+        end,
+        guild_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT post.id, post.channel_id, post.author_id,
+                COUNT(*) FILTER (WHERE vote.value > 0) AS upvotes,
+                COUNT(*) FILTER (WHERE vote.value < 0) AS downvotes
+            FROM post
+            JOIN vote ON vote.post_id = post.id
+            WHERE post.guild_id = $1 AND
+                post.created_at >= to_timestamp($2::bigint) AND
+                post.created_at < to_timestamp($3::bigint)
+            GROUP BY post.id
+            HAVING SUM(vote.value * vote.weight) > 0
+            ORDER BY SUM(vote.value * vote.weight) DESC, upvotes DESC, post.id
+            LIMIT 1;
+            "#,
+            &[&(i64::from(guild_id)), &start, &end],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(PostVotes::from_row))
+}
+
+/// Gets the post identified by the provided id, along with its votes.
+/// If there is no such post, `None` is returned.
+pub async fn get_post_votes(
+    pool: &Pool,
+    post_id: MessageId,
+) -> Result<Option<PostVotes>, String> {
+    log::trace!("Fetching votes of post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT post.id, post.channel_id, post.author_id,
+                COUNT(vote.value) FILTER (WHERE vote.value > 0) AS upvotes,
+                COUNT(vote.value) FILTER (WHERE vote.value < 0) AS downvotes
+            FROM post
+            LEFT JOIN vote ON vote.post_id = post.id
+            WHERE post.id = $1
+            GROUP BY post.id;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(PostVotes::from_row))
+}
+
+/// Gets the id of the first post of the provided kind sent in the
+/// provided guild. If there is no such post, `None` is returned.
+pub async fn get_first_post_id(
+    pool: &Pool,
+    guild_id: GuildId,
+    kind: &str,
+) -> Result<Option<MessageId>, String> {
+    log::trace!("Fetching the first {} post for guild: {}", kind, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT id
+            FROM post
+            WHERE post.guild_id = $1 AND
+                post.kind = $2
+            ORDER BY created_at, id
+            LIMIT 1;
+            "#,
+            &[&(i64::from(guild_id)), &kind],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map(|row| MessageId::from(row.get::<usize, i64>(0) as u64))
+        })
+}
+
+/// Gets up to the provided number of posts, along with their votes, whose
+/// voting window closed and that are not finalized yet, in the guilds that
+/// show the final tally of their posts.
+pub async fn get_closed_posts(
+    pool: &Pool,
+    limit: i64,
+) -> Result<Vec<PostVotes>, String> {
+    log::trace!("Fetching closed posts");
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    // NOTE: only the posts whose window closed within the last day are
+    // finalized, so that the old posts are not all edited at once when
+    // a guild starts showing the final tallies.
+    client
+        .query(
+            r#"
+            SELECT post.id, post.channel_id, post.author_id,
+                COUNT(vote.value) FILTER (WHERE vote.value > 0) AS upvotes,
+                COUNT(vote.value) FILTER (WHERE vote.value < 0) AS downvotes
+            FROM post
+            JOIN guild_config ON guild_config.guild_id = post.guild_id
+            LEFT JOIN vote ON vote.post_id = post.id
+            WHERE NOT post.finalized AND
+                guild_config.show_final_tally AND
+                post.created_at + make_interval(
+                    days => guild_config.voting_window_days
+                ) BETWEEN now() - interval '1 day' AND now()
+            GROUP BY post.id
+            ORDER BY post.created_at
+            LIMIT $1;
+            "#,
+            &[&limit],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(PostVotes::from_row).collect())
+}
+
+/// Record the post identified by the provided id as finalized, so that
+/// its final tally is only shown once.
+pub async fn set_finalized(
+    pool: &Pool,
+    post_id: MessageId,
+) -> Result<(), String> {
+    log::trace!("Finalizing post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            UPDATE post
+            SET finalized = true
+            WHERE post.id = $1;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Remove all the votes on the posts of the guild identified by the
+/// provided id, or only on the posts of the provided author. The posts are
+/// then tracked, so that removing the votes that were not recorded does
+/// not modify the scores. The scores are not modified.
+pub async fn clear_votes(
+    client: &impl GenericClient,
+    guild_id: GuildId,
+    author_id: Option<UserId>,
+) -> Result<(), String> {
+    log::trace!("Clearing votes of guild {} for {:?}", guild_id, author_id);
+    client
+        .execute(
+            r#"
+            DELETE FROM vote
+            USING post
+            WHERE post.id = vote.post_id AND
+                post.guild_id = $1 AND
+                ($2::bigint IS NULL OR post.author_id = $2);
+            "#,
+            &[&(i64::from(guild_id)), &author_id.map(i64::from)],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            UPDATE post
+            SET tracked = true
+            WHERE post.guild_id = $1 AND
+                ($2::bigint IS NULL OR post.author_id = $2);
+            "#,
+            &[&(i64::from(guild_id)), &author_id.map(i64::from)],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Void the post identified by the provided id, removing its votes and
+/// subtracting the counted ones from the score of its author. The votes
+/// on a voided post are no longer recorded. Returns the post's author, or
+/// `None` when there is no such post in the provided guild.
+pub async fn void_post(
+    client: &impl GenericClient,
+    guild_id: GuildId,
+    post_id: MessageId,
+) -> Result<Option<UserId>, String> {
+    log::trace!("Voiding post {}", post_id);
+    let post = client
+        .query_opt(
+            r#"
+            UPDATE post
+            SET voided = true,
+                tracked = true
+            WHERE post.id = $1 AND
+                post.guild_id = $2
+            RETURNING author_id;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    let author_id = match post {
+        | Some(post) => UserId::from(post.get::<usize, i64>(0) as u64),
+        | None => return Ok(None),
+    };
+    reverse_votes(client, guild_id, author_id, post_id).await?;
+    Ok(Some(author_id))
+}
+
+/// Delete the post identified by the provided id, along with its votes,
+/// subtracting the counted ones from the score of its author. The votes
+/// of the untracked posts that were not recorded are not reversed.
+pub async fn delete_post(
+    pool: &Pool,
+    guild_id: GuildId,
+    post_id: MessageId,
+) -> Result<(), String> {
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    remove_post(&*transaction, guild_id, post_id).await?;
+    transaction.commit().await.map_err(|err| err.to_string())
+}
+
+/// Remove the post identified by the provided id, along with its votes,
+/// subtracting the counted ones from the score of its author. Returns the
+/// post's author, or `None` when there is no such post in the provided
+/// guild.
+pub async fn remove_post(
+    client: &impl GenericClient,
+    guild_id: GuildId,
+    post_id: MessageId,
+) -> Result<Option<UserId>, String> {
+    log::trace!("Deleting post {}", post_id);
+    let post = client
+        .query_opt(
+            r#"
+            SELECT author_id
+            FROM post
+            WHERE post.id = $1 AND
+                post.guild_id = $2;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    let author_id = match post {
+        | Some(post) => UserId::from(post.get::<usize, i64>(0) as u64),
+        | None => return Ok(None),
+    };
+    reverse_votes(client, guild_id, author_id, post_id).await?;
+    client
+        .execute(
+            r#"
+            DELETE FROM post
+            WHERE post.id = $1;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(Some(author_id))
+}
+
+/// Remove the votes on the post identified by the provided id, and
+/// subtract them, counted by their weight, from the score of the post's
+/// author.
+async fn reverse_votes(
+    client: &impl GenericClient,
+    guild_id: GuildId,
+    author_id: UserId,
+    post_id: MessageId,
+) -> Result<(), String> {
+    let counted = client
+        .query_one(
+            r#"
+            WITH removed AS (
+                DELETE FROM vote
+                WHERE vote.post_id = $1
+                RETURNING value, weight
+            )
+            SELECT COALESCE(SUM(value * weight), 0)::double precision
+            FROM removed;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?
+        .get::<usize, f64>(0);
+    if counted != 0.0 {
+        user::add_score(client, author_id, guild_id, -counted).await?;
+    }
+    Ok(())
+}
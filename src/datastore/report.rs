@@ -0,0 +1,170 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, MessageId, UserId};
+use tokio_postgres::GenericClient;
+
+/// The review of a reported post, opened with its first report.
+pub struct Review {
+    /// The review card sent to the guild's report channel, if any.
+    pub card_id: Option<MessageId>,
+    /// Whether the post was hidden, as it reached the report threshold.
+    pub hidden: bool,
+    /// Whether the post was hidden by deleting it, so that it may not be
+    /// restored.
+    pub deleted: bool,
+    /// Whether a moderator approved the post, so that it may no longer
+    /// be reported.
+    pub approved: bool,
+    /// The number of members that reported the post.
+    pub reports: i64,
+}
+
+/// Gets the review of the post identified by the provided id. If the post
+/// was never reported, `None` is returned.
+pub async fn get_review(
+    pool: &Pool,
+    post_id: MessageId,
+) -> Result<Option<Review>, String> {
+    log::trace!("Fetching review of post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT review.card_id, review.hidden, review.approved,
+                COUNT(report.reporter_id), review.deleted
+            FROM review
+            LEFT JOIN report ON report.post_id = review.post_id
+            WHERE review.post_id = $1
+            GROUP BY review.post_id;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| {
+            row.map(|row| Review {
+                card_id: row
+                    .get::<usize, Option<i64>>(0)
+                    .map(|id| MessageId::from(id as u64)),
+                hidden: row.get(1),
+                approved: row.get(2),
+                reports: row.get(3),
+                deleted: row.get(4),
+            })
+        })
+}
+
+/// Record the report of the post identified by the provided id by the
+/// provided reporter, opening the post's review if it was not reported
+/// yet. Returns false when the reporter already reported the post, in
+/// which case nothing is recorded.
+pub async fn add_report(
+    pool: &Pool,
+    post_id: MessageId,
+    reporter_id: UserId,
+) -> Result<bool, String> {
+    log::trace!("Recording report of post {} by {}", post_id, reporter_id);
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    transaction
+        .execute(
+            r#"
+            INSERT INTO review(post_id)
+            VALUES ($1)
+            ON CONFLICT(post_id) DO NOTHING;
+            "#,
+            &[&(i64::from(post_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    let added = transaction
+        .execute(
+            r#"
+            INSERT INTO report(post_id, reporter_id)
+            VALUES ($1, $2)
+            ON CONFLICT(post_id, reporter_id) DO NOTHING;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(reporter_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    transaction.commit().await.map_err(|err| err.to_string())?;
+    Ok(added > 0)
+}
+
+/// Set the review card of the post identified by the provided id.
+pub async fn set_card(
+    pool: &Pool,
+    post_id: MessageId,
+    card_id: MessageId,
+) -> Result<(), String> {
+    log::trace!("Setting review card of post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            UPDATE review
+            SET card_id = $2
+            WHERE review.post_id = $1;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(card_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Record the post identified by the provided id as hidden, so that it is
+/// only hidden once, along with whether it was hidden by deleting it.
+pub async fn set_hidden(
+    pool: &Pool,
+    post_id: MessageId,
+    deleted: bool,
+) -> Result<(), String> {
+    log::trace!("Hiding post {}", post_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            UPDATE review
+            SET hidden = true,
+                deleted = $2
+            WHERE review.post_id = $1;
+            "#,
+            &[&(i64::from(post_id)), &deleted],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Approve the reported post identified by the provided id, so that it
+/// may no longer be reported. Returns false when there is no such reported
+/// post in the provided guild, or it is already approved.
+pub async fn approve(
+    client: &impl GenericClient,
+    guild_id: GuildId,
+    post_id: MessageId,
+) -> Result<bool, String> {
+    log::trace!("Approving post {}", post_id);
+    client
+        .execute(
+            r#"
+            UPDATE review
+            SET approved = true
+            FROM post
+            WHERE post.id = review.post_id AND
+                review.post_id = $1 AND
+                post.guild_id = $2 AND
+                NOT review.approved;
+            "#,
+            &[&(i64::from(post_id)), &(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|updated| updated > 0)
+}
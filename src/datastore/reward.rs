@@ -0,0 +1,213 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, RoleId, UserId};
+use tokio_postgres::Row;
+
+use super::user::Period;
+
+/// The rule a reward role is granted by.
+#[derive(Clone)]
+pub enum Rule {
+    /// Granted to the users whose score is at least the provided score.
+    Score(i64),
+    /// Granted to the users ranked at or above the provided rank in
+    /// the leaderboard of the provided period.
+    Rank(i64, Period),
+}
+
+/// A role rewarded to the guild's users by the provided rule.
+pub struct RewardRole {
+    pub role_id: RoleId,
+    pub rule: Rule,
+}
+
+impl RewardRole {
+    fn from_row(row: &Row) -> RewardRole {
+        let rule = match row.get::<usize, Option<i64>>(1) {
+            | Some(score) => Rule::Score(score),
+            | None => Rule::Rank(
+                row.get::<usize, Option<i64>>(2).unwrap_or_default(),
+                Period::from_id(row.get::<usize, &str>(3))
+                    .unwrap_or(Period::AllTime),
+            ),
+        };
+        RewardRole {
+            role_id: RoleId::from(row.get::<usize, i64>(0) as u64),
+            rule,
+        }
+    }
+}
+
+/// Gets the reward roles of the guild identified by the provided id.
+pub async fn get_reward_roles(
+    pool: &Pool,
+    guild_id: GuildId,
+) -> Result<Vec<RewardRole>, String> {
+    log::trace!("Fetching reward roles for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT role_id, min_score, max_rank, period
+            FROM reward_role
+            WHERE reward_role.guild_id = $1
+            ORDER BY min_score, max_rank;
+            "#,
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(RewardRole::from_row).collect())
+}
+
+/// Gets the ids of the guilds with any reward roles.
+pub async fn get_reward_guilds(pool: &Pool) -> Result<Vec<GuildId>, String> {
+    log::trace!("Fetching guilds with reward roles");
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query("SELECT DISTINCT guild_id FROM reward_role;", &[])
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| GuildId::from(row.get::<usize, i64>(0) as u64))
+                .collect()
+        })
+}
+
+/// Add the reward role identified by the provided id to the guild, or
+/// replace its rule if it already exists.
+pub async fn set_reward_role(
+    pool: &Pool,
+    guild_id: GuildId,
+    role_id: RoleId,
+    rule: &Rule,
+) -> Result<(), String> {
+    log::trace!("Setting reward role {} for guild: {}", role_id, guild_id);
+
+    let (min_score, max_rank, period) = match rule {
+        | Rule::Score(score) => (Some(*score), None, Period::AllTime),
+        | Rule::Rank(rank, period) => (None, Some(*rank), period.clone()),
+    };
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            INSERT INTO reward_role(
+                guild_id, role_id, min_score, max_rank, period
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT(guild_id, role_id)
+                DO UPDATE
+                SET min_score = $3,
+                    max_rank = $4,
+                    period = $5;
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &(i64::from(role_id)),
+                &min_score,
+                &max_rank,
+                &period.id(),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// Remove the reward role identified by the provided id, along with the
+/// records of its grants. Returns false if there was no such reward role.
+pub async fn remove_reward_role(
+    pool: &Pool,
+    guild_id: GuildId,
+    role_id: RoleId,
+) -> Result<bool, String> {
+    log::trace!("Removing reward role {} for guild: {}", role_id, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            DELETE FROM reward_role
+            WHERE reward_role.guild_id = $1 AND
+                reward_role.role_id = $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(role_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|removed| removed > 0)
+}
+
+/// Gets the ids of the users the role identified by the provided id
+/// was granted to.
+pub async fn get_grants(
+    pool: &Pool,
+    guild_id: GuildId,
+    role_id: RoleId,
+) -> Result<Vec<UserId>, String> {
+    log::trace!("Fetching grants of role {} for guild: {}", role_id, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT user_id
+            FROM reward_grant
+            WHERE reward_grant.guild_id = $1 AND
+                reward_grant.role_id = $2;
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(role_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| UserId::from(row.get::<usize, i64>(0) as u64))
+                .collect()
+        })
+}
+
+/// Record that the role identified by the provided id was granted to,
+/// or revoked from the user identified by the provided id.
+pub async fn set_granted(
+    pool: &Pool,
+    guild_id: GuildId,
+    role_id: RoleId,
+    user_id: UserId,
+    granted: bool,
+) -> Result<(), String> {
+    log::trace!(
+        "Setting role {} granted to {} for user {}",
+        role_id,
+        granted,
+        user_id
+    );
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let query = if granted {
+        r#"
+        INSERT INTO reward_grant(guild_id, role_id, user_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING;
+        "#
+    } else {
+        r#"
+        DELETE FROM reward_grant
+        WHERE reward_grant.guild_id = $1 AND
+            reward_grant.role_id = $2 AND
+            reward_grant.user_id = $3;
+        "#
+    };
+    client
+        .execute(query, &[
+            &(i64::from(guild_id)),
+            &(i64::from(role_id)),
+            &(i64::from(user_id)),
+        ])
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
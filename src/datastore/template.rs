@@ -0,0 +1,200 @@
+use deadpool_postgres::Pool;
+use serenity::model::prelude::{GuildId, UserId};
+use tokio_postgres::Row;
+
+use crate::render::Align;
+
+/// A box of a template the text is drawn in. The position and the size are
+/// percentages of the template's size.
+#[derive(Clone, Copy)]
+pub struct TemplateBox {
+    pub x: i16,
+    pub y: i16,
+    pub width: i16,
+    pub height: i16,
+    pub align: Align,
+}
+
+impl TemplateBox {
+    fn from_row(row: &Row) -> TemplateBox {
+        TemplateBox {
+            x: row.get(0),
+            y: row.get(1),
+            width: row.get(2),
+            height: row.get(3),
+            align: Align::from_name(row.get(4)).unwrap_or(Align::Center),
+        }
+    }
+}
+
+/// A meme template uploaded to a guild.
+pub struct Template {
+    pub name: String,
+    pub image: Vec<u8>,
+    /// The boxes the texts are drawn in, in the order the texts are
+    /// provided in.
+    pub boxes: Vec<TemplateBox>,
+}
+
+/// Gets the template of the guild identified by the provided id with the
+/// provided name. If there is no such template, `None` is returned.
+pub async fn get_template(
+    pool: &Pool,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<Option<Template>, String> {
+    log::trace!("Fetching template '{}' for guild: {}", name, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    let image = client
+        .query_opt(
+            r#"
+            SELECT image
+            FROM template
+            WHERE template.guild_id = $1 AND
+                template.name = $2;
+            "#,
+            &[&(i64::from(guild_id)), &name],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    let image: Vec<u8> = match image {
+        | Some(row) => row.get(0),
+        | None => return Ok(None),
+    };
+    let boxes = client
+        .query(
+            r#"
+            SELECT x, y, width, height, align
+            FROM template_box
+            WHERE template_box.guild_id = $1 AND
+                template_box.name = $2
+            ORDER BY position;
+            "#,
+            &[&(i64::from(guild_id)), &name],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(Some(Template {
+        name: name.to_string(),
+        image,
+        boxes: boxes.iter().map(TemplateBox::from_row).collect(),
+    }))
+}
+
+/// Gets up to the provided number of names of the templates of the guild
+/// identified by the provided id, that contain the provided query.
+pub async fn get_template_names(
+    pool: &Pool,
+    guild_id: GuildId,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<String>, String> {
+    log::trace!("Fetching template names for guild: {}", guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            r#"
+            SELECT name
+            FROM template
+            WHERE template.guild_id = $1 AND
+                strpos(template.name, lower($2)) > 0
+            ORDER BY name
+            LIMIT $3;
+            "#,
+            &[&(i64::from(guild_id)), &query, &limit],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Add the template with the provided name to the guild identified by the
+/// provided id, or replace it if it already exists.
+pub async fn add_template(
+    pool: &Pool,
+    guild_id: GuildId,
+    template: &Template,
+    created_by: UserId,
+) -> Result<(), String> {
+    log::trace!("Adding template '{}' to guild: {}", template.name, guild_id);
+
+    let mut client = pool.get().await.map_err(|err| err.to_string())?;
+    let transaction =
+        client.transaction().await.map_err(|err| err.to_string())?;
+    transaction
+        .execute(
+            r#"
+            DELETE FROM template
+            WHERE template.guild_id = $1 AND
+                template.name = $2;
+            "#,
+            &[&(i64::from(guild_id)), &template.name],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    transaction
+        .execute(
+            r#"
+            INSERT INTO template(guild_id, name, image, created_by)
+            VALUES ($1, $2, $3, $4);
+            "#,
+            &[
+                &(i64::from(guild_id)),
+                &template.name,
+                &template.image,
+                &(i64::from(created_by)),
+            ],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    for (position, text_box) in template.boxes.iter().enumerate() {
+        transaction
+            .execute(
+                r#"
+                INSERT INTO template_box(
+                    guild_id, name, position, x, y, width, height, align
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+                "#,
+                &[
+                    &(i64::from(guild_id)),
+                    &template.name,
+                    &(position as i16),
+                    &text_box.x,
+                    &text_box.y,
+                    &text_box.width,
+                    &text_box.height,
+                    &text_box.align.name(),
+                ],
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    transaction.commit().await.map_err(|err| err.to_string())
+}
+
+/// Remove the template with the provided name from the guild identified
+/// by the provided id. Returns false when there is no such template.
+pub async fn remove_template(
+    pool: &Pool,
+    guild_id: GuildId,
+    name: &str,
+) -> Result<bool, String> {
+    log::trace!("Removing template '{}' from guild: {}", name, guild_id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            DELETE FROM template
+            WHERE template.guild_id = $1 AND
+                template.name = $2;
+            "#,
+            &[&(i64::from(guild_id)), &name],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|removed| removed > 0)
+}
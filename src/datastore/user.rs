@@ -1,5 +1,7 @@
+use chrono::NaiveDate;
 use deadpool_postgres::Pool;
 use serenity::model::prelude::{GuildId, UserId};
+use tokio_postgres::{GenericClient, Row};
 
 /// Gets  score for the user identified by the provided id.
 /// If there is no existing record for the user, 0 will be returned.
@@ -28,87 +30,636 @@ pub async fn get_score(
     }
 }
 
-/// Gets a vector of userId, score pairs, where
-/// the resuls are descendingly sorted by the scores,
-/// and limited by the provided limit.
+/// The way the tied scores are ranked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    /// Tied scores share the rank, and a gap is left after them (1224).
+    Standard,
+    /// Tied scores share the rank, without leaving a gap (1223).
+    Dense,
+}
+
+impl Ranking {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Ranking::Standard => "standard",
+            | Ranking::Dense => "dense",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Ranking> {
+        match name {
+            | "standard" => Some(Ranking::Standard),
+            | "dense" => Some(Ranking::Dense),
+            | _ => None,
+        }
+    }
+
+    fn window_function(&self) -> &'static str {
+        match self {
+            | Ranking::Standard => "RANK()",
+            | Ranking::Dense => "DENSE_RANK()",
+        }
+    }
+}
+
+/// The order the scores are listed in. The ranks are always
+/// based on the descending order of the scores.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// The highest scores first.
+    Top,
+    /// The lowest scores first.
+    Bottom,
+}
+
+impl Order {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Order::Top => "top",
+            | Order::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Order> {
+        match name {
+            | "top" => Some(Order::Top),
+            | "bottom" => Some(Order::Bottom),
+            | _ => None,
+        }
+    }
+
+    fn direction(&self) -> &'static str {
+        match self {
+            | Order::Top => "ASC",
+            | Order::Bottom => "DESC",
+        }
+    }
+}
+
+/// Which of the scores are included in the leaderboard.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Positive,
+    NonNegative,
+    All,
+}
+
+impl Filter {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Filter::Positive => "positive",
+            | Filter::NonNegative => "non-negative",
+            | Filter::All => "all",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            | "positive" => Some(Filter::Positive),
+            | "non-negative" => Some(Filter::NonNegative),
+            | "all" => Some(Filter::All),
+            | _ => None,
+        }
+    }
+
+    fn condition(&self) -> &'static str {
+        match self {
+            | Filter::Positive => r#""user".score > 0"#,
+            | Filter::NonNegative => r#""user".score >= 0"#,
+            | Filter::All => "TRUE",
+        }
+    }
+}
+
+/// The format of the dates of the ranges.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// The period of the votes counted in the scores.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Period {
+    AllTime,
+    Today,
+    Week,
+    Month,
+    Last7Days,
+    Last30Days,
+    /// An inclusive range of dates, formatted as `YYYY-MM-DD`.
+    Range(String, String),
+}
+
+impl Period {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Period::AllTime => "all-time",
+            | Period::Today => "today",
+            | Period::Week => "week",
+            | Period::Month => "month",
+            | Period::Last7Days => "7-days",
+            | Period::Last30Days => "30-days",
+            | Period::Range(..) => "range",
+        }
+    }
+
+    /// Get the period with the provided name, the range may not be
+    /// created by its name, as it requires the dates.
+    pub fn from_name(name: &str) -> Option<Period> {
+        match name {
+            | "all-time" => Some(Period::AllTime),
+            | "today" => Some(Period::Today),
+            | "week" => Some(Period::Week),
+            | "month" => Some(Period::Month),
+            | "7-days" => Some(Period::Last7Days),
+            | "30-days" => Some(Period::Last30Days),
+            | _ => None,
+        }
+    }
+
+    /// Create a range of the provided dates. `None` is returned when the
+    /// dates are not valid dates formatted as `YYYY-MM-DD`, or the range is
+    /// empty.
+    pub fn range(from: &str, to: &str) -> Option<Period> {
+        let parse =
+            |date: &str| NaiveDate::parse_from_str(date, DATE_FORMAT).ok();
+        let (from, to) = (parse(from)?, parse(to)?);
+        if from > to {
+            return None;
+        }
+        Some(Period::Range(
+            from.format(DATE_FORMAT).to_string(),
+            to.format(DATE_FORMAT).to_string(),
+        ))
+    }
+
+    /// The period's name, along with the dates of the range, so that the
+    /// period may be recreated with `from_id`.
+    pub fn id(&self) -> String {
+        match self {
+            | Period::Range(from, to) => format!("{}..{}", from, to),
+            | _ => self.name().to_string(),
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Period> {
+        match id.split_once("..") {
+            | Some((from, to)) => Period::range(from, to),
+            | None => Period::from_name(id),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            | Period::AllTime => String::from("all time"),
+            | Period::Today => String::from("today"),
+            | Period::Week => String::from("this week"),
+            | Period::Month => String::from("this month"),
+            | Period::Last7Days => String::from("last 7 days"),
+            | Period::Last30Days => String::from("last 30 days"),
+            | Period::Range(from, to) => format!("{} to {}", from, to),
+        }
+    }
+}
+
+/// The bounds of the votes counted in the scores, as unix timestamps.
+/// The start is inclusive and the end is exclusive.
+#[derive(Clone, Copy)]
+pub struct Window {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// How the votes lose their value as they age.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DecayMode {
+    /// The votes lose half of their value every time the half-life passes.
+    Step,
+    /// The votes lose their value continuously, half of it per half-life.
+    Exponential,
+}
+
+impl DecayMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | DecayMode::Step => "step",
+            | DecayMode::Exponential => "exponential",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DecayMode> {
+        match name {
+            | "step" => Some(DecayMode::Step),
+            | "exponential" => Some(DecayMode::Exponential),
+            | _ => None,
+        }
+    }
+}
+
+/// The decay of the votes counted in the scores. The decayed scores are
+/// computed from the votes' timestamps, so they may be recomputed at any
+/// time, and are the same throughout the day.
+#[derive(Clone, Copy)]
+pub struct Decay {
+    pub mode: DecayMode,
+    pub half_life_days: i32,
+    /// The time the votes' age is measured at, as a unix timestamp.
+    pub reference: i64,
+}
+
+impl Decay {
+    /// The expression of a scored point's decayed value.
+    fn value(&self) -> String {
+        let halvings = format!(
+            "GREATEST({} - extract(epoch FROM point.created_at), 0) / {}.0",
+            self.reference,
+            i64::from(self.half_life_days) * 24 * 60 * 60
+        );
+        match self.mode {
+            | DecayMode::Step => {
+                format!("point.value * power(0.5, floor({}))", halvings)
+            }
+            | DecayMode::Exponential => {
+                format!("point.value * power(0.5, {})", halvings)
+            }
+        }
+    }
+}
+
+/// Determines which scores are listed on the leaderboard,
+/// how they are ranked and in which order they are listed.
+/// When a window or a decay is provided, the scores are computed
+/// from the votes and bonuses received within the window, rather than
+/// all-time scores, with each vote's value weighted, and each point
+/// decayed by its age.
+#[derive(Clone, Copy)]
+pub struct ScoreQuery {
+    pub ranking: Ranking,
+    pub order: Order,
+    pub filter: Filter,
+    pub window: Option<Window>,
+    pub decay: Option<Decay>,
+}
+
+impl ScoreQuery {
+    /// The subquery of the filtered scores along with their ranks.
+    fn ranked(&self) -> String {
+        let scores = match (self.window, self.decay) {
+            | (None, None) => String::from(
+                r#"
+                SELECT id, score
+                FROM "user"
+                WHERE "user".guild_id = $1
+                "#,
+            ),
+            | (window, decay) => format!(
+                r#"
+                SELECT point.id, round(SUM({}))::bigint AS score
+                FROM (
+                    SELECT post.author_id AS id,
+                        vote.value * vote.weight::double precision AS value,
+                        vote.created_at
+                    FROM vote
+                    JOIN post ON post.id = vote.post_id
+                    WHERE post.guild_id = $1
+                    UNION ALL
+                    SELECT user_id, amount, created_at
+                    FROM score_bonus
+                    WHERE score_bonus.guild_id = $1
+                ) point
+                WHERE {}
+                GROUP BY point.id
+                "#,
+                decay.map_or(String::from("point.value"), |decay| {
+                    decay.value()
+                }),
+                window.map_or(String::from("TRUE"), |window| format!(
+                    "point.created_at >= to_timestamp({}) AND \
+                     point.created_at < to_timestamp({})",
+                    window.start, window.end
+                ))
+            ),
+        };
+        format!(
+            r#"
+            SELECT id, score,
+                {} OVER (ORDER BY score DESC) AS rank
+            FROM ({}) "user"
+            WHERE {}
+            "#,
+            self.ranking.window_function(),
+            scores,
+            self.filter.condition()
+        )
+    }
+}
+
+/// A user's score along with its rank among the guild's scores.
+pub struct RankedScore {
+    pub user_id: UserId,
+    pub score: i64,
+    pub rank: i64,
+}
+
+impl RankedScore {
+    fn from_row(row: &Row) -> RankedScore {
+        RankedScore {
+            user_id: UserId::from(row.get::<usize, i64>(0) as u64),
+            score: row.get(1),
+            rank: row.get(2),
+        }
+    }
+}
+
+/// Gets a vector of ranked scores, filtered and sorted as specified by
+/// the provided query, and limited by the provided limit and offset.
 /// Returns only results for the provided guildID.
 pub async fn get_scores(
     pool: &Pool,
     guild_id: GuildId,
+    query: &ScoreQuery,
     limit: u16,
-) -> Result<Vec<(UserId, i64)>, String> {
-    log::trace!("Fetching top {} scores for guild: {}", limit, guild_id);
+    offset: i64,
+) -> Result<Vec<RankedScore>, String> {
+    log::trace!(
+        "Fetching {} scores from {} for guild: {}",
+        limit,
+        offset,
+        guild_id
+    );
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            format!(
+                r#"
+                SELECT id, score, rank FROM ({}) ranked
+                ORDER BY rank {}, id
+                LIMIT $2
+                OFFSET $3;
+                "#,
+                query.ranked(),
+                query.order.direction()
+            )
+            .as_str(),
+            &[&(i64::from(guild_id)), &(i64::from(limit)), &offset],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| rows.iter().map(RankedScore::from_row).collect())
+}
+
+/// Gets the number of the provided guild's scores that
+/// are listed by the provided query.
+pub async fn count_scores(
+    pool: &Pool,
+    guild_id: GuildId,
+    query: &ScoreQuery,
+) -> Result<i64, String> {
+    log::trace!("Counting scores for guild: {}", guild_id);
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_one(
+            format!(
+                r#"
+                SELECT COUNT(*) FROM ({}) ranked;
+                "#,
+                query.ranked()
+            )
+            .as_str(),
+            &[&(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.get(0))
+}
+
+/// Gets the ranked score of the user identified by the provided id.
+/// If the user's score does not pass the query's filter, `None`
+/// is returned.
+pub async fn get_rank(
+    pool: &Pool,
+    id: UserId,
+    guild_id: GuildId,
+    query: &ScoreQuery,
+) -> Result<Option<RankedScore>, String> {
+    log::trace!("Fetching a user {}'s rank", id);
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            format!(
+                r#"
+                SELECT id, score, rank FROM ({}) ranked
+                WHERE ranked.id = $2;
+                "#,
+                query.ranked()
+            )
+            .as_str(),
+            &[&(i64::from(guild_id)), &(i64::from(id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.as_ref().map(RankedScore::from_row))
+}
+
+/// Gets the ids of the provided guild's users ranked at the provided rank
+/// or higher by the provided query.
+pub async fn get_users_within_rank(
+    pool: &Pool,
+    guild_id: GuildId,
+    query: &ScoreQuery,
+    max_rank: i64,
+) -> Result<Vec<UserId>, String> {
+    log::trace!(
+        "Fetching users ranked up to {} for guild: {}",
+        max_rank,
+        guild_id
+    );
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query(
+            format!(
+                r#"
+                SELECT id FROM ({}) ranked
+                WHERE ranked.rank <= $2;
+                "#,
+                query.ranked()
+            )
+            .as_str(),
+            &[&(i64::from(guild_id)), &max_rank],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|rows| {
+            rows.iter()
+                .map(|row| UserId::from(row.get::<usize, i64>(0) as u64))
+                .collect()
+        })
+}
+
+/// Gets the ids of the provided guild's users whose score is at
+/// least the provided score.
+pub async fn get_users_with_score(
+    pool: &Pool,
+    guild_id: GuildId,
+    min_score: i64,
+) -> Result<Vec<UserId>, String> {
+    log::trace!(
+        "Fetching users with score of at least {} for guild: {}",
+        min_score,
+        guild_id
+    );
     let client = pool.get().await.map_err(|err| err.to_string())?;
     client
         .query(
             r#"
-            SELECT id, score FROM "user"
+            SELECT id FROM "user"
             WHERE "user".guild_id = $1 AND
-                "user".score > 0
-            ORDER BY "user".score DESC
-            LIMIT $2;
+                "user".score >= $2;
             "#,
-            &[&(i64::from(guild_id)), &(i64::from(limit))],
+            &[&(i64::from(guild_id)), &min_score],
         )
         .await
         .map_err(|err| err.to_string())
         .map(|rows| {
-            // NOTE: map the rows into a vector of userId, score tuples
             rows.iter()
-                .map(|row| {
-                    (
-                        UserId::from(row.get::<usize, i64>(0) as u64),
-                        i64::from(row.get::<usize, i64>(1)),
-                    )
-                })
-                .collect::<Vec<(UserId, i64)>>()
+                .map(|row| UserId::from(row.get::<usize, i64>(0) as u64))
+                .collect()
         })
 }
 
-/// Increment the score of the user identified by the provided
-/// id by 1.
-pub async fn increment_score(
-    pool: &Pool,
+/// Add the provided score to the score of the user identified
+/// by the provided id. If no such user exists, a new record
+/// is added. The provided client may be a transaction, so that
+/// the score is updated along with the records it is derived from.
+/// The score may be fractional, as the votes are counted by their
+/// weight, and the rounded sum is kept as the user's score.
+pub async fn add_score(
+    client: &impl GenericClient,
     id: UserId,
     guild_id: GuildId,
+    score: f64,
 ) -> Result<(), String> {
-    log::trace!("Incrementing a user score by 1");
-    add_score(pool, id, guild_id, 1).await
+    log::trace!("Adding {} to user {}'s score", score, id);
+    client
+        .execute(
+            r#"
+            INSERT INTO "user"(id, guild_id, score, weighted_score)
+            VALUES ($1, $2, round($3::double precision)::bigint, $3)
+            ON CONFLICT(id, guild_id)
+                DO UPDATE
+                SET weighted_score = "user".weighted_score + $3,
+                    score = round("user".weighted_score + $3)::bigint;
+            "#,
+            &[&(i64::from(id)), &(i64::from(guild_id)), &score],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
 }
 
-/// Decrement the score of the user identified by the provided
-/// id by 1.
-pub async fn decrement_score(
-    pool: &Pool,
+/// Add the provided bonus to the score of the user identified by the
+/// provided id, and record it with its time, so that the bonuses are
+/// counted in the windowed and decayed scores along with the votes.
+pub async fn add_bonus(
+    client: &impl GenericClient,
     id: UserId,
     guild_id: GuildId,
+    amount: f64,
+) -> Result<(), String> {
+    log::trace!("Adding a bonus of {} to user {}'s score", amount, id);
+    client
+        .execute(
+            r#"
+            INSERT INTO score_bonus(guild_id, user_id, amount)
+            VALUES ($1, $2, $3);
+            "#,
+            &[&(i64::from(guild_id)), &(i64::from(id)), &amount],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    add_score(client, id, guild_id, amount).await
+}
+
+/// Reset the score of the user identified by the provided id to 0, or the
+/// scores of all the guild's users when no user is provided, along with
+/// their recorded bonuses.
+pub async fn reset_score(
+    client: &impl GenericClient,
+    id: Option<UserId>,
+    guild_id: GuildId,
 ) -> Result<(), String> {
-    log::trace!("Decrementing a user score by 1");
-    add_score(pool, id, guild_id, -1).await
+    log::trace!("Resetting scores of guild {} for user {:?}", guild_id, id);
+    client
+        .execute(
+            r#"
+            UPDATE "user"
+            SET score = 0,
+                weighted_score = 0
+            WHERE "user".guild_id = $1 AND
+                ($2::bigint IS NULL OR "user".id = $2);
+            "#,
+            &[&(i64::from(guild_id)), &id.map(i64::from)],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    client
+        .execute(
+            r#"
+            DELETE FROM score_bonus
+            WHERE score_bonus.guild_id = $1 AND
+                ($2::bigint IS NULL OR score_bonus.user_id = $2);
+            "#,
+            &[&(i64::from(guild_id)), &id.map(i64::from)],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
 }
 
-/// Add the provided score to the score of the user identified
-/// by the provided id. If no such user exists, a new record
-/// is added.
-async fn add_score(
-    pool: &Pool,
+/// Ban or unban the user identified by the provided id from voting in
+/// the guild identified by the provided id.
+pub async fn set_voting_banned(
+    client: &impl GenericClient,
     id: UserId,
     guild_id: GuildId,
-    score: i8,
+    banned: bool,
 ) -> Result<(), String> {
-    let client = pool.get().await.map_err(|err| err.to_string())?;
+    log::trace!("Setting user {} banned from voting to {}", id, banned);
     client
         .execute(
             r#"
-            INSERT INTO "user"(id, guild_id, score)
+            INSERT INTO "user"(id, guild_id, voting_banned)
             VALUES ($1, $2, $3)
             ON CONFLICT(id, guild_id)
                 DO UPDATE
-                SET score = "user".score + $3;
+                SET voting_banned = $3;
             "#,
-            &[&(i64::from(id)), &(i64::from(guild_id)), &(score as i64)],
+            &[&(i64::from(id)), &(i64::from(guild_id)), &banned],
         )
         .await
         .map_err(|err| err.to_string())?;
     Ok(())
 }
+
+/// Whether the user identified by the provided id is banned from voting
+/// in the guild identified by the provided id.
+pub async fn is_voting_banned(
+    pool: &Pool,
+    id: UserId,
+    guild_id: GuildId,
+) -> Result<bool, String> {
+    log::trace!("Checking whether user {} is banned from voting", id);
+
+    let client = pool.get().await.map_err(|err| err.to_string())?;
+    client
+        .query_opt(
+            r#"
+            SELECT voting_banned
+            FROM "user"
+            WHERE "user".id = $1 AND
+                "user".guild_id = $2;
+            "#,
+            &[&(i64::from(id)), &(i64::from(guild_id))],
+        )
+        .await
+        .map_err(|err| err.to_string())
+        .map(|row| row.is_some_and(|row| row.get(0)))
+}
@@ -1,21 +1,31 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use serenity::{
     async_trait,
     model::{
         gateway::Ready,
-        prelude::{interaction::Interaction, Activity, Reaction, UserId},
+        prelude::{interaction::Interaction, Activity, Guild, Reaction},
     },
     prelude::{Context, EventHandler},
 };
 
+use self::{
+    application_command::Registry,
+    state::{PendingEvent, State},
+};
 use crate::datastore::Datastore;
+mod achievement;
 mod application_command;
+mod member;
+mod post;
 mod reaction;
+mod reward;
+mod scheduler;
+mod state;
+mod template;
 
 pub struct Handler {
-    giphy_key: String,
-    datastore: Datastore,
+    state: Arc<State>,
 }
 
 impl Handler {
@@ -27,29 +37,106 @@ impl Handler {
         datastore.migrate().await;
 
         Handler {
-            giphy_key,
-            datastore,
+            state: Arc::new(State::new(giphy_key, datastore, Registry::new())),
         }
     }
-}
 
-pub static mut BOT_USER_ID: Option<UserId> = None;
+    /// Handle the provided event, or queue it when the shard
+    /// that received it is not yet ready.
+    async fn handle_event(&self, event: PendingEvent) {
+        if let Some(event) = self.state.defer(event) {
+            self.handle_ready_event(event).await;
+        }
+    }
+
+    async fn handle_ready_event(&self, event: PendingEvent) {
+        let state = &self.state;
+        match event {
+            | PendingEvent::Interaction(ctx, interaction) => match *interaction
+            {
+                | Interaction::ApplicationCommand(command) => {
+                    state.commands.dispatch(ctx, command, state).await
+                }
+                | Interaction::MessageComponent(component) => {
+                    state
+                        .commands
+                        .dispatch_component(ctx, component, state)
+                        .await
+                }
+                | Interaction::Autocomplete(autocomplete) => {
+                    state
+                        .commands
+                        .dispatch_autocomplete(ctx, autocomplete, state)
+                        .await
+                }
+                | _ => (),
+            },
+            | PendingEvent::ReactionAdd(ctx, reaction) => {
+                reaction::handle_reaction_add(ctx, reaction, state).await;
+            }
+            // Handle the reaction removed event. This is where we handle the
+            // reactions removed from the meme message, and the logic behind
+            // decreasing the user's score.
+            | PendingEvent::ReactionRemove(ctx, reaction) => {
+                reaction::handle_reaction_remove(ctx, reaction, state).await;
+            }
+        }
+    }
+}
 
 #[async_trait]
 impl EventHandler for Handler {
+    /// Mark the shard as ready and handle the events it received
+    /// before it was ready, start the scheduler with the first ready shard,
+    /// then register the commands from the first shard.
     async fn ready(&self, ctx: Context, ready: Ready) {
-        if let Err(why) = application_command::register(&ctx).await {
-            log::error!("Failed to register global commands: {}", why);
+        log::info!(
+            "Shard {} ready with username '{}'",
+            ctx.shard_id,
+            ready.user.name
+        );
+
+        let pending = self.state.mark_ready(ctx.shard_id, ready.user.id);
+        if !pending.is_empty() {
+            log::debug!("Handling {} queued event/s", pending.len());
+        }
+        for event in pending {
+            self.handle_ready_event(event).await;
         }
 
         ctx.set_activity(Activity::competing("Rust, I'm in Rust btw.")).await;
 
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        if self.state.start_scheduler() {
+            scheduler::start(ctx.clone(), self.state.clone());
+        }
 
-        log::info!("Bot ready with username '{}'", ready.user.name);
+        // NOTE: the commands are the same for all the shards,
+        // so they are registered only once.
+        if ctx.shard_id != 0 {
+            return;
+        }
+        if let Err(why) = self.state.commands.register(&ctx, &self.state).await
+        {
+            log::error!("Failed to register commands: {}", why);
+        }
+    }
 
-        unsafe {
-            BOT_USER_ID = Some(ready.user.id);
+    /// Register the commands enabled in the guild, when the
+    /// commands are registered per guild.
+    async fn guild_create(&self, ctx: Context, guild: Guild, _is_new: bool) {
+        log::trace!("Received guild create event: {}", guild.id);
+
+        if let Err(why) = self
+            .state
+            .commands
+            .register_guild(&ctx, guild.id, &self.state)
+            .await
+        {
+            log::error!(
+                "Failed to register guild {} commands: {}",
+                guild.id,
+                why
+            );
         }
     }
 
@@ -58,52 +145,22 @@ impl EventHandler for Handler {
             "Received interaction created event: {:?}",
             interaction.id(),
         );
-        unsafe {
-            if let None = BOT_USER_ID {
-                log::trace!("Bot not yet ready, cannot handle event");
-                return;
-            }
-        }
-        if let Interaction::ApplicationCommand(command) = interaction {
-            let pool = &self.datastore.pool;
-            application_command::handle_appliaction_command(
-                ctx,
-                command,
-                pool,
-                &self.giphy_key,
-            )
-            .await;
-        }
+        self.handle_event(PendingEvent::Interaction(
+            ctx,
+            Box::new(interaction),
+        ))
+        .await;
     }
 
     async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
         log::trace!("Received reaction add event");
 
-        unsafe {
-            if let None = BOT_USER_ID {
-                log::trace!("Bot not yet ready, cannot handle event");
-                return;
-            }
-        }
-
-        let pool = &self.datastore.pool;
-        reaction::handle_reaction_add(ctx, reaction, pool).await;
+        self.handle_event(PendingEvent::ReactionAdd(ctx, reaction)).await;
     }
 
-    // Handle the reaction removed event. This is where we handle the
-    // reactions removed from the meme message, and the logic behind
-    // decreasing the user's score.
     async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
         log::trace!("Received reaction remove event");
 
-        unsafe {
-            if let None = BOT_USER_ID {
-                log::trace!("Bot not yet ready, cannot handle event");
-                return;
-            }
-        }
-
-        let pool = &self.datastore.pool;
-        reaction::handle_reaction_remove(ctx, reaction, pool).await;
+        self.handle_event(PendingEvent::ReactionRemove(ctx, reaction)).await;
     }
 }
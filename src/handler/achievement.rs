@@ -0,0 +1,217 @@
+use serenity::{
+    model::prelude::{ChannelId, GuildId, MessageId, UserId},
+    prelude::Context,
+};
+
+use super::state::State;
+use crate::datastore;
+
+/// An achievement the users may unlock, by meeting its rule.
+pub struct Achievement {
+    /// The achievement's id, under which it is recorded once unlocked.
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub rule: Rule,
+}
+
+/// The rule an achievement is unlocked by.
+pub enum Rule {
+    /// Send at least the provided number of posts.
+    Posts(i64),
+    /// Send at least the provided number of posts of the provided kind.
+    PostsOfKind(&'static str, i64),
+    /// Receive at least the provided number of upvotes on a single post.
+    Upvotes(i64),
+    /// Post on at least the provided number of consecutive days.
+    Streak(i64),
+    /// Send the first post of the provided kind in the guild.
+    FirstOfKind(&'static str),
+}
+
+/// The event the achievements are checked on.
+pub enum Event<'a> {
+    /// A post of the provided kind was sent.
+    Post(MessageId, &'a str),
+    /// A post received an upvote.
+    Upvote(MessageId),
+}
+
+/// All the achievements that may be unlocked, in the order they are listed.
+pub const ACHIEVEMENTS: [Achievement; 6] = [
+    Achievement {
+        id: "first-meme",
+        name: "First meme",
+        description: "Post your first meme",
+        rule: Rule::PostsOfKind("meme", 1),
+    },
+    Achievement {
+        id: "crowd-pleaser",
+        name: "Crowd pleaser",
+        description: "Receive 10 upvotes on a single post",
+        rule: Rule::Upvotes(10),
+    },
+    Achievement {
+        id: "centurion",
+        name: "Centurion",
+        description: "Send 100 posts",
+        rule: Rule::Posts(100),
+    },
+    Achievement {
+        id: "on-a-roll",
+        name: "On a roll",
+        description: "Post on 7 consecutive days",
+        rule: Rule::Streak(7),
+    },
+    Achievement {
+        id: "gif-pioneer",
+        name: "Gif pioneer",
+        description: "Send the first gif in the server",
+        rule: Rule::FirstOfKind("gif"),
+    },
+    Achievement {
+        id: "viral",
+        name: "Viral",
+        description: "Receive 50 upvotes on a single post",
+        rule: Rule::Upvotes(50),
+    },
+];
+
+impl Rule {
+    /// Whether the rule may be met by the provided event.
+    fn is_checked_on(&self, event: &Event<'_>) -> bool {
+        match (self, event) {
+            | (Rule::Upvotes(_), Event::Upvote(_)) => true,
+            | (Rule::Upvotes(_), Event::Post(..)) => false,
+            | (_, Event::Post(..)) => true,
+            | (_, Event::Upvote(_)) => false,
+        }
+    }
+
+    async fn is_met(
+        &self,
+        state: &State,
+        guild_id: GuildId,
+        user_id: UserId,
+        event: &Event<'_>,
+    ) -> Result<bool, String> {
+        let pool = &state.datastore.pool;
+        match (self, event) {
+            | (Rule::Posts(posts), _) => {
+                let counts = datastore::post::count_posts_by_kind(
+                    pool, user_id, guild_id,
+                )
+                .await?;
+                Ok(counts.iter().map(|(_, count)| count).sum::<i64>() >= *posts)
+            }
+            | (Rule::PostsOfKind(kind, posts), _) => {
+                let counts = datastore::post::count_posts_by_kind(
+                    pool, user_id, guild_id,
+                )
+                .await?;
+                Ok(counts
+                    .iter()
+                    .any(|(name, count)| name == kind && count >= posts))
+            }
+            | (Rule::Upvotes(upvotes), Event::Upvote(post_id)) => {
+                Ok(datastore::post::get_post_votes(pool, *post_id)
+                    .await?
+                    .is_some_and(|post| post.upvotes >= *upvotes))
+            }
+            | (Rule::Streak(days), _) => {
+                Ok(datastore::post::get_streak(pool, user_id, guild_id).await?
+                    >= *days)
+            }
+            | (Rule::FirstOfKind(kind), Event::Post(post_id, post_kind)) => {
+                if kind != post_kind {
+                    return Ok(false);
+                }
+                Ok(datastore::post::get_first_post_id(pool, guild_id, kind)
+                    .await?
+                    == Some(*post_id))
+            }
+            | _ => Ok(false),
+        }
+    }
+}
+
+/// Check the achievements the provided event may unlock for the provided
+/// user, and announce the unlocked ones in the provided channel.
+pub async fn check(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: ChannelId,
+    event: Event<'_>,
+) {
+    let unlocked = match datastore::achievement::get_unlocked(
+        &state.datastore.pool,
+        guild_id,
+        user_id,
+    )
+    .await
+    {
+        | Ok(unlocked) => unlocked,
+        | Err(why) => {
+            log::error!("Failed to fetch achievements: {}", why);
+            return;
+        }
+    };
+    for achievement in ACHIEVEMENTS.iter() {
+        if !achievement.rule.is_checked_on(&event)
+            || unlocked.iter().any(|(id, _)| id == achievement.id)
+        {
+            continue;
+        }
+        match achievement.rule.is_met(state, guild_id, user_id, &event).await {
+            | Ok(true) => {
+                unlock(ctx, state, guild_id, user_id, channel_id, achievement)
+                    .await
+            }
+            | Ok(false) => (),
+            | Err(why) => log::error!(
+                "Failed to check achievement '{}': {}",
+                achievement.id,
+                why
+            ),
+        }
+    }
+}
+
+async fn unlock(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+    channel_id: ChannelId,
+    achievement: &Achievement,
+) {
+    match datastore::achievement::unlock(
+        &state.datastore.pool,
+        guild_id,
+        user_id,
+        achievement.id,
+    )
+    .await
+    {
+        | Ok(true) => (),
+        | Ok(false) => return,
+        | Err(why) => {
+            log::error!("Failed to unlock achievement: {}", why);
+            return;
+        }
+    }
+    log::debug!("User {} unlocked '{}'", user_id, achievement.id);
+    if let Err(why) = channel_id
+        .send_message(ctx, |message| {
+            message.content(format!(
+                "🏆 <@{}> unlocked **_{}_**: {}",
+                user_id, achievement.name, achievement.description
+            ))
+        })
+        .await
+    {
+        log::warn!("Failed to announce an achievement: {}", why);
+    }
+}
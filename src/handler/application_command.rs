@@ -1,92 +1,776 @@
-use deadpool_postgres::Pool;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
 use serenity::{
-    model::{
-        application::command::Command,
-        prelude::interaction::application_command::ApplicationCommandInteraction,
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::CommandType,
+        interaction::{
+            application_command::{
+                ApplicationCommandInteraction, CommandDataOption,
+                CommandDataOptionValue,
+            },
+            autocomplete::AutocompleteInteraction,
+            message_component::MessageComponentInteraction,
+            InteractionResponseType, MessageFlags,
+        },
+        GuildId, MessageId, User,
     },
     prelude::Context,
 };
 
+use self::sync::{Scope, SyncOptions};
+use super::{post, state::State};
+use crate::datastore::{self, post::Marking};
+
+pub mod abuse;
+pub mod admin;
+pub mod badges;
+pub mod caption;
+pub mod config;
 pub mod gif;
 pub mod leaderboard;
 pub mod link;
 pub mod meme;
+pub mod mypost;
+pub mod report;
 pub mod score;
+pub mod shame;
+mod sync;
+pub mod template;
+
+/// A slash command that may be registered with discord and
+/// dispatched by the `Registry`.
+#[async_trait]
+pub trait SlashCommand: Send + Sync {
+    /// The name of the command, this is also used to
+    /// route the received interactions to the command.
+    fn name(&self) -> String;
+
+    fn description(&self) -> String;
+
+    /// The type of the command, a slash command by default. The message and
+    /// user commands are listed in the context menus, and have no
+    /// description.
+    fn kind(&self) -> CommandType { CommandType::ChatInput }
+
+    /// Add the command's options to the provided builder. The name and
+    /// the description are already set by the registry.
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+    }
+
+    /// Handle the application command interaction. The registry only
+    /// calls this with interactions whose name matches `name()`.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    );
+
+    /// Handle a message component interaction, such as a button press, on a
+    /// message sent by this command. The registry only calls this with
+    /// components whose custom id is prefixed by the command's name and `:`.
+    async fn handle_component(
+        &self,
+        _ctx: Context,
+        component: MessageComponentInteraction,
+        _state: &State,
+    ) {
+        log::warn!(
+            "Received unexpected component '{}'",
+            component.data.custom_id
+        );
+    }
+
+    /// Handle an autocomplete interaction, sent while one of the command's
+    /// options set to be autocompleted is typed. The registry only calls
+    /// this with interactions whose name matches `name()`.
+    async fn handle_autocomplete(
+        &self,
+        _ctx: Context,
+        autocomplete: AutocompleteInteraction,
+        _state: &State,
+    ) {
+        log::warn!(
+            "Received unexpected autocomplete for '{}'",
+            autocomplete.data.name
+        );
+    }
+}
+
+/// A hook run around every dispatched command, used for concerns
+/// shared by all the commands, such as permission checks, cooldowns
+/// or metrics.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the command is handled. When this returns false,
+    /// the command is not handled and the remaining middleware is skipped.
+    async fn before(
+        &self,
+        _ctx: &Context,
+        _command: &ApplicationCommandInteraction,
+        _state: &State,
+    ) -> bool {
+        true
+    }
 
-/// Fetch all global commands. Delete those that are no longer required,
-/// and register those that are not yet registered.
-pub async fn register(ctx: &Context) -> Result<(), String> {
-    let commands = Command::get_global_application_commands(&ctx.http)
+    /// Called after the command has been handled.
+    async fn after(
+        &self,
+        _ctx: &Context,
+        _command: &ApplicationCommandInteraction,
+        _state: &State,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// Logs the time it took to handle each command.
+pub struct Timing;
+
+#[async_trait]
+impl Middleware for Timing {
+    async fn after(
+        &self,
+        _ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        _state: &State,
+        elapsed: Duration,
+    ) {
+        log::debug!(
+            "Handled '{}' command in {} ms",
+            command.data.name,
+            elapsed.as_millis()
+        );
+    }
+}
+
+/// Stops the commands disabled in the guild's config from being handled.
+/// This is required when the commands are registered globally, as
+/// all of them are then visible in every guild.
+pub struct DisabledCommands;
+
+#[async_trait]
+impl Middleware for DisabledCommands {
+    async fn before(
+        &self,
+        ctx: &Context,
+        command: &ApplicationCommandInteraction,
+        state: &State,
+    ) -> bool {
+        let guild_id = match command.guild_id {
+            | Some(guild_id) => guild_id,
+            | None => return true,
+        };
+        let disabled = match datastore::guild::get_disabled_commands(
+            &state.datastore.pool,
+            guild_id,
+        )
         .await
-        .map_err(|err| format!("Failed to fetch global commands: {:?}", err))?;
-
-    let to_register = vec![
-        meme::name(),
-        score::name(),
-        link::name(),
-        gif::name(),
-        leaderboard::name(),
-    ];
-
-    log::debug!("Registering slash commands ...");
-    for command in commands.iter() {
-        if to_register.iter().find(|&name| command.name == *name).is_none() {
-            log::debug!("Deleting '{}' app. command", command.name,);
-            Command::delete_global_application_command(&ctx.http, command.id)
-                .await
-                .map_err(|err| {
-                    format!(
-                        "Failed to delete '{}' app. command: {:?}",
-                        command.name, err
-                    )
-                })?;
-        }
-    }
-    // NOTE: register only slash commands that are not yet present in
-    // the already registered commands.
-    // This is a safety to avoid being blocked from discord for registering
-    // commands too often.
-    if commands.iter().find(|command| command.name == meme::name()).is_none() {
-        meme::register(ctx).await;
-    };
-    if commands.iter().find(|command| command.name == score::name()).is_none() {
-        score::register(ctx).await;
-    };
-    if commands.iter().find(|command| command.name == link::name()).is_none() {
-        link::register(ctx).await;
-    };
-    if commands.iter().find(|command| command.name == gif::name()).is_none() {
-        gif::register(ctx).await;
-    };
-    if commands
+        {
+            | Ok(disabled) => disabled,
+            | Err(why) => {
+                log::warn!("Failed to fetch disabled commands: {}", why);
+                return true;
+            }
+        };
+        if !disabled.contains(&command.data.name) {
+            return true;
+        }
+        log::trace!(
+            "Command '{}' is disabled in guild {}",
+            command.data.name,
+            guild_id
+        );
+        if let Err(why) = command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::ChannelMessageWithSource)
+                    .interaction_response_data(|message| {
+                        message
+                            .content("This command is disabled in this server")
+                            .flags(MessageFlags::EPHEMERAL)
+                    })
+            })
+            .await
+        {
+            log::warn!("Failed to respond to a disabled command: {}", why);
+        }
+        false
+    }
+}
+
+/// Holds all the slash commands and middleware, and drives both
+/// registering the commands with discord and dispatching the
+/// received interactions.
+pub struct Registry {
+    commands: Vec<Box<dyn SlashCommand>>,
+    middleware: Vec<Box<dyn Middleware>>,
+    sync: SyncOptions,
+}
+
+impl Registry {
+    /// Create a registry containing all of the bot's commands.
+    pub fn new() -> Registry {
+        let mut commands: Vec<Box<dyn SlashCommand>> = vec![
+            Box::new(meme::Meme),
+            Box::new(caption::Caption),
+            Box::new(template::Template),
+            Box::new(score::Score),
+            Box::new(link::Link),
+            Box::new(gif::Gif),
+            Box::new(leaderboard::Leaderboard),
+            Box::new(shame::Shame),
+            Box::new(badges::Badges),
+            Box::new(mypost::MyPost),
+            Box::new(abuse::Abuse),
+            Box::new(report::ReportMeme),
+        ];
+        // NOTE: the config and admin commands may not be disabled,
+        // so that it is always possible to enable the other ones again,
+        // and to moderate the scores.
+        let toggleable =
+            commands.iter().map(|command| command.name()).collect();
+        commands.push(Box::new(config::Config::new(toggleable)));
+        commands.push(Box::new(admin::Admin));
+        Registry {
+            commands,
+            middleware: vec![Box::new(Timing), Box::new(DisabledCommands)],
+            sync: SyncOptions::from_env(),
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&dyn SlashCommand> {
+        self.commands
+            .iter()
+            .find(|command| command.name() == name)
+            .map(|command| command.as_ref())
+    }
+
+    /// The definitions of all the commands, except the disabled ones,
+    /// as they should be registered with discord.
+    pub fn definitions(
+        &self,
+        disabled: &[String],
+    ) -> Vec<CreateApplicationCommand> {
+        self.commands
+            .iter()
+            .filter(|slash_command| !disabled.contains(&slash_command.name()))
+            .map(|slash_command| {
+                let mut command = CreateApplicationCommand::default();
+                command.name(slash_command.name());
+                match slash_command.kind() {
+                    | CommandType::ChatInput => {
+                        command.description(slash_command.description())
+                    }
+                    | kind => command.kind(kind),
+                };
+                slash_command.options(&mut command);
+                command
+            })
+            .collect()
+    }
+
+    /// Register the commands once the bot is ready. When the commands are
+    /// registered per guild, the global commands are removed and the commands
+    /// are registered to the dev guilds, the other guilds are registered
+    /// once they are received in `register_guild`. Otherwise all the commands
+    /// are registered globally.
+    pub async fn register(
+        &self,
+        ctx: &Context,
+        state: &State,
+    ) -> Result<(), String> {
+        if !self.sync.per_guild() {
+            return self
+                .sync_scope(ctx, Scope::Global, self.definitions(&[]))
+                .await;
+        }
+        self.sync_scope(ctx, Scope::Global, Vec::new()).await?;
+        for guild_id in self.sync.dev_guilds.iter() {
+            self.register_guild(ctx, *guild_id, state).await?;
+        }
+        Ok(())
+    }
+
+    /// Register the commands enabled in the provided guild, when the
+    /// commands are registered per guild. This is called whenever a guild
+    /// is received and whenever its config changes.
+    pub async fn register_guild(
+        &self,
+        ctx: &Context,
+        guild_id: GuildId,
+        state: &State,
+    ) -> Result<(), String> {
+        if !self.sync.includes_guild(guild_id) {
+            return Ok(());
+        }
+        let disabled = datastore::guild::get_disabled_commands(
+            &state.datastore.pool,
+            guild_id,
+        )
+        .await?;
+        self.sync_scope(
+            ctx,
+            Scope::Guild(guild_id),
+            self.definitions(&disabled),
+        )
+        .await
+    }
+
+    /// Fetch the commands registered in the provided scope and compare them
+    /// with the desired definitions. Delete those that are no longer
+    /// required, register those that are not yet registered and update
+    /// those whose definition has changed.
+    async fn sync_scope(
+        &self,
+        ctx: &Context,
+        scope: Scope,
+        definitions: Vec<CreateApplicationCommand>,
+    ) -> Result<(), String> {
+        let commands = scope.fetch(&ctx.http).await.map_err(|err| {
+            format!("Failed to fetch {} commands: {:?}", scope.describe(), err)
+        })?;
+
+        log::debug!("Registering {} slash commands ...", scope.describe());
+        // NOTE: only the commands that differ from the already registered
+        // ones are sent to discord.
+        // This is a safety to avoid being blocked from discord for
+        // registering commands too often.
+        let changes = sync::plan(&definitions, &commands);
+        if changes.is_empty() {
+            log::info!("{} slash commands are up to date", scope.describe());
+            return Ok(());
+        }
+        for change in changes.iter() {
+            if self.sync.dry_run {
+                log::info!(
+                    "[dry-run] Would {} {} command",
+                    change.describe(),
+                    scope.describe()
+                );
+            } else {
+                log::debug!(
+                    "Will {} {} command",
+                    change.describe(),
+                    scope.describe()
+                );
+            }
+        }
+        if self.sync.dry_run {
+            return Ok(());
+        }
+        if self.sync.overwrite {
+            log::debug!("Overwriting all {} app. commands", scope.describe());
+            scope.overwrite(&ctx.http, definitions).await.map_err(|err| {
+                format!(
+                    "Failed to overwrite {} commands: {:?}",
+                    scope.describe(),
+                    err
+                )
+            })?;
+            log::info!("{} slash commands overwritten", scope.describe());
+            return Ok(());
+        }
+        for change in changes.iter() {
+            let result = match change {
+                | sync::Change::Create(name) => {
+                    scope
+                        .create(&ctx.http, find_definition(&definitions, name))
+                        .await
+                }
+                | sync::Change::Update(name, id, _) => {
+                    scope
+                        .edit(
+                            &ctx.http,
+                            *id,
+                            find_definition(&definitions, name),
+                        )
+                        .await
+                }
+                | sync::Change::Delete(_, id) => {
+                    scope.delete(&ctx.http, *id).await
+                }
+            };
+            match result {
+                | Ok(_) => log::info!(
+                    "Slash commands: {} {} command",
+                    change.describe(),
+                    scope.describe()
+                ),
+                | Err(why) => log::warn!(
+                    "Failed to {} {} slash command: {}",
+                    change.describe(),
+                    scope.describe(),
+                    why
+                ),
+            }
+        }
+
+        log::info!("{} slash commands registered", scope.describe());
+        Ok(())
+    }
+
+    /// Find the command matching the interaction's name and handle
+    /// the interaction with it, running the middleware around it.
+    pub async fn dispatch(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        log::trace!("Handling command interaction: {:?}", command.data.name);
+
+        let slash_command = match self.find(command.data.name.as_str()) {
+            | Some(slash_command) => slash_command,
+            | None => {
+                log::warn!(
+                    "Received interaction for unknown command '{}'",
+                    command.data.name
+                );
+                return;
+            }
+        };
+        for middleware in self.middleware.iter() {
+            if !middleware.before(&ctx, &command, state).await {
+                log::trace!(
+                    "Middleware stopped handling '{}' command",
+                    command.data.name
+                );
+                return;
+            }
+        }
+        let start = Instant::now();
+        slash_command.handle(ctx.clone(), command.clone(), state).await;
+        let elapsed = start.elapsed();
+        for middleware in self.middleware.iter() {
+            middleware.after(&ctx, &command, state, elapsed).await;
+        }
+    }
+
+    /// Find the command that sent the message the component belongs to,
+    /// based on the component's custom id, and handle the interaction
+    /// with it.
+    pub async fn dispatch_component(
+        &self,
+        ctx: Context,
+        component: MessageComponentInteraction,
+        state: &State,
+    ) {
+        log::trace!(
+            "Handling component interaction: {:?}",
+            component.data.custom_id
+        );
+
+        let name = component
+            .data
+            .custom_id
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        match self.find(name.as_str()) {
+            | Some(slash_command) => {
+                slash_command.handle_component(ctx, component, state).await
+            }
+            | None => log::warn!(
+                "Received component interaction for unknown command '{}'",
+                name
+            ),
+        }
+    }
+
+    /// Find the command matching the interaction's name and suggest the
+    /// values of the option being typed with it.
+    pub async fn dispatch_autocomplete(
+        &self,
+        ctx: Context,
+        autocomplete: AutocompleteInteraction,
+        state: &State,
+    ) {
+        log::trace!(
+            "Handling autocomplete interaction: {:?}",
+            autocomplete.data.name
+        );
+
+        match self.find(autocomplete.data.name.as_str()) {
+            | Some(slash_command) => {
+                slash_command
+                    .handle_autocomplete(ctx, autocomplete, state)
+                    .await
+            }
+            | None => log::warn!(
+                "Received autocomplete interaction for unknown command '{}'",
+                autocomplete.data.name
+            ),
+        }
+    }
+}
+
+/// Get the user provided as the command's `user` option, or the command's
+/// user when no user is provided.
+fn get_user_or_caller(command: &ApplicationCommandInteraction) -> &User {
+    command
+        .data
+        .options
         .iter()
-        .find(|command| command.name == leaderboard::name())
-        .is_none()
+        .filter(|option| option.name == "user")
+        .find_map(|option| match &option.resolved {
+            | Some(CommandDataOptionValue::User(user, _)) => Some(user),
+            | _ => None,
+        })
+        .unwrap_or(&command.user)
+}
+
+/// Get the value of the command's option with the provided name.
+fn get_option<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> Option<&'a Value> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_ref())
+}
+
+/// Get the value of the provided subcommand's option with the
+/// provided name.
+fn get_value<'a>(
+    subcommand: &'a CommandDataOption,
+    name: &str,
+) -> Option<&'a Value> {
+    subcommand
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| option.value.as_ref())
+}
+
+/// Suggest the names of the templates containing the value of the option
+/// being typed, in response to the provided autocomplete interaction.
+async fn suggest_templates(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+    state: &State,
+) {
+    let query = get_focused(&autocomplete.data.options)
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+    let names = match crate::handler::template::suggest(
+        state,
+        autocomplete.guild_id,
+        query,
+    )
+    .await
     {
-        leaderboard::register(ctx).await;
+        | Ok(names) => names,
+        | Err(why) => {
+            log::warn!("Failed to suggest templates: {}", why);
+            Vec::new()
+        }
     };
+    if let Err(why) = autocomplete
+        .create_autocomplete_response(&ctx.http, |response| {
+            for name in names.iter() {
+                response.add_string_choice(name, name);
+            }
+            response
+        })
+        .await
+    {
+        log::warn!("Failed to suggest templates: {}", why);
+    }
+}
+
+/// Get the option being typed among the provided options and their
+/// subcommands' options.
+fn get_focused(options: &[CommandDataOption]) -> Option<&CommandDataOption> {
+    options.iter().find_map(|option| {
+        if option.focused {
+            Some(option)
+        } else {
+            get_focused(&option.options)
+        }
+    })
+}
+
+/// Defer the response to the provided command, so the interaction does not
+/// timeout before the response is sent. This is neccessary as it may take
+/// a long time to upload videos or such attachments, or to render images.
+async fn defer_response(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+) {
+    log::trace!("Deffering '{}' slash command", command.data.name);
 
-    log::info!("Slash commands registered");
-    return Ok(());
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+    {
+        log::warn!("Failed to defer an interaction: {:?}", why);
+    };
 }
 
-pub async fn handle_appliaction_command(
-    ctx: Context,
-    command: ApplicationCommandInteraction,
-    pool: &Pool,
-    giphy_key: &String,
+/// Remove the deferred response to the provided command, and explain why
+/// nothing was sent with a follow-up only visible to the command's user.
+async fn reject_deferred(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    explanation: String,
 ) {
-    log::trace!("Handling command interaction: {:?}", command.data.name,);
-    let name = command.data.name.to_string();
-    if name == meme::name() {
-        meme::handle_command(ctx, command).await
-    } else if name == score::name() {
-        score::handle_command(ctx, command, pool).await
-    } else if name == link::name() {
-        link::handle_command(ctx, command).await
-    } else if name == gif::name() {
-        gif::handle_command(ctx, command, giphy_key.as_str()).await
-    } else if name == leaderboard::name() {
-        leaderboard::handle_command(ctx, command, pool).await
+    log::trace!("Removing origin response to '{}' command", command.data.name);
+
+    if let Err(why) =
+        command.delete_original_interaction_response(&ctx.http).await
+    {
+        log::warn!(
+            "Error when deleting original interaction response: {:?}",
+            why
+        );
     };
+    // NOTE: the follow-up is only sent privately once the deferred response
+    // is deleted, otherwise it would replace the public deferred response.
+    if let Err(why) = command
+        .create_followup_message(&ctx.http, |message| {
+            message.content(explanation).flags(MessageFlags::EPHEMERAL)
+        })
+        .await
+    {
+        log::warn!("Failed to explain why a command was rejected: {:?}", why);
+    }
+}
+
+/// Render the meme prepared from the provided command, and send it in
+/// response to the deferred command as a post that may be voted on, within
+/// the guild's upload limit. When the meme may not be prepared or rendered,
+/// the response explaining why is sent privately instead.
+async fn send_rendered<R>(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    state: &State,
+    prepared: Result<Result<R, String>, String>,
+) where
+    R: FnOnce() -> Result<Vec<u8>, String> + Send + 'static,
+{
+    let name = command.data.name.as_str();
+    let render = match prepared {
+        | Ok(Ok(render)) => render,
+        | Ok(Err(explanation)) => {
+            reject_deferred(ctx, command, explanation).await;
+            return;
+        }
+        | Err(why) => {
+            log::warn!("Failed to prepare a '{}' meme: {}", name, why);
+            reject_deferred(
+                ctx,
+                command,
+                String::from("The meme could not be rendered"),
+            )
+            .await;
+            return;
+        }
+    };
+    // NOTE: the image is rendered on a blocking thread, as it may take
+    // a while and would otherwise stall the other events.
+    let image = match tokio::task::spawn_blocking(render).await {
+        | Ok(Ok(image)) => image,
+        | Ok(Err(why)) => {
+            log::debug!("Failed to render a '{}' meme: {}", name, why);
+            reject_deferred(
+                ctx,
+                command,
+                String::from(
+                    "The image could not be read, it may be of an unsupported \
+                     format",
+                ),
+            )
+            .await;
+            return;
+        }
+        | Err(why) => {
+            log::warn!("Failed to render a '{}' meme: {}", name, why);
+            reject_deferred(
+                ctx,
+                command,
+                String::from("The meme could not be rendered"),
+            )
+            .await;
+            return;
+        }
+    };
+    let spoiler = post::is_spoiler_channel(state, command).await;
+    let rules = post::attachment_rules(state, command.guild_id).await;
+    let limit = post::upload_limit(ctx, command.guild_id).await;
+    let file = post::prepare_file(
+        image,
+        format!("{}.png", name).as_str(),
+        Some("image/png"),
+        limit,
+        spoiler,
+        rules.strip_metadata,
+    )
+    .await;
+    let sent = match file {
+        | Ok((file, _)) => command
+            .create_followup_message(&ctx.http, |message| {
+                message.add_file(file)
+            })
+            .await
+            .map_err(|err| err.to_string()),
+        | Err(why) => Err(why),
+    };
+    match sent {
+        | Ok(message) => {
+            post::publish(ctx, state, command, &message, Marking {
+                spoiler,
+                cw: None,
+            })
+            .await
+        }
+        | Err(why) => {
+            log::info!("Err when responding with '{}' meme: {:?}", name, why);
+            reject_deferred(
+                ctx,
+                command,
+                format!("The meme could not be sent: {}", why),
+            )
+            .await;
+        }
+    }
+}
+
+/// Parse the id of the post from the provided message link, or id.
+fn parse_post_id(post: &str) -> Option<MessageId> {
+    post.trim()
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(MessageId)
+}
+
+fn find_definition(
+    definitions: &[CreateApplicationCommand],
+    name: &str,
+) -> CreateApplicationCommand {
+    definitions
+        .iter()
+        .find(|definition| {
+            definition.0.get("name").and_then(|value| value.as_str())
+                == Some(name)
+        })
+        .cloned()
+        .unwrap_or_default()
 }
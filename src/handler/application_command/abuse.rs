@@ -0,0 +1,172 @@
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::{
+        prelude::{
+            interaction::{
+                application_command::ApplicationCommandInteraction,
+                MessageFlags,
+            },
+            GuildId,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use super::SlashCommand;
+use crate::{
+    datastore::{self, abuse},
+    handler::state::State,
+};
+
+fn name() -> String { String::from("abuse") }
+fn description() -> String {
+    String::from("Report the server's suspicious voting patterns")
+}
+
+/// The number of suspects listed in each section of the report.
+const REPORT_SIZE: i64 = 10;
+
+/// The abuse slash command, it is only available to the members that may
+/// moderate the server. It privately reports the reciprocal voting pairs,
+/// the voters that only ever upvote a single author, and the new members
+/// that mass voted right after joining.
+pub struct Abuse;
+
+#[async_trait]
+impl SlashCommand for Abuse {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .default_member_permissions(Permissions::MODERATE_MEMBERS)
+            .dm_permission(false)
+    }
+
+    /// Respond to the provided command with the server's abuse report,
+    /// only visible to the command's user.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+    let guild_id = match command.guild_id {
+        | Some(id) => id,
+        | None => return,
+    };
+
+    let embed = match get_embed(state, guild_id).await {
+        | Ok(embed) => embed,
+        | Err(why) => {
+            log::warn!("Failed to build the abuse report: {}", why);
+            let mut embed = CreateEmbed::default();
+            embed.description("Failed to build the abuse report");
+            embed
+        }
+    };
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response.interaction_response_data(|message| {
+                message.set_embed(embed).flags(MessageFlags::EPHEMERAL)
+            })
+        })
+        .await
+    {
+        log::warn!("Failed to respond with the abuse report: {}", why);
+    }
+}
+
+async fn get_embed(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<CreateEmbed, String> {
+    let pool = &state.datastore.pool;
+    let pairs =
+        abuse::get_reciprocal_pairs(pool, guild_id, REPORT_SIZE).await?;
+    let single_target =
+        abuse::get_single_target_voters(pool, guild_id, REPORT_SIZE).await?;
+    let new_members =
+        abuse::get_new_member_voters(pool, guild_id, REPORT_SIZE).await?;
+    let config = datastore::guild::get_voting_config(pool, guild_id).await?;
+
+    let pairs: Vec<String> = pairs
+        .iter()
+        .map(|pair| {
+            format!(
+                "<@{}> ⇄ <@{}>: {} / {} 👍",
+                pair.first_id,
+                pair.second_id,
+                pair.first_votes,
+                pair.second_votes
+            )
+        })
+        .collect();
+    let single_target: Vec<String> = single_target
+        .iter()
+        .map(|voter| {
+            format!(
+                "<@{}> → <@{}>: {} 👍",
+                voter.voter_id, voter.author_id, voter.votes
+            )
+        })
+        .collect();
+    let new_members: Vec<String> = new_members
+        .iter()
+        .map(|voter| {
+            format!(
+                "<@{}> joined <t:{}:R>: {} vote/s",
+                voter.voter_id, voter.joined_at, voter.votes
+            )
+        })
+        .collect();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Voting abuse report")
+        .field("Reciprocal voting pairs", describe_section(&pairs), false)
+        .field("Single target voters", describe_section(&single_target), false)
+        .field(
+            format!(
+                "New members voting in their first {} day/s",
+                abuse::NEW_MEMBER_DAYS
+            ),
+            describe_section(&new_members),
+            false,
+        )
+        .footer(|footer| {
+            footer.text(match config.flagged_vote_weight {
+                | Some(weight) => {
+                    format!("Flagged votes count {:.2}", weight)
+                }
+                | None => String::from(
+                    "Flagged votes count fully, see /config voting",
+                ),
+            })
+        });
+    Ok(embed)
+}
+
+fn describe_section(lines: &[String]) -> String {
+    if lines.is_empty() {
+        String::from("Nothing suspicious")
+    } else {
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,432 @@
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::{
+        prelude::{
+            command::CommandOptionType,
+            interaction::{
+                application_command::{
+                    ApplicationCommandInteraction, CommandDataOption,
+                },
+                MessageFlags,
+            },
+            GuildId, UserId,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use super::{get_value, parse_post_id, SlashCommand};
+use crate::{
+    datastore::audit::{self, Action, Entry},
+    handler::state::State,
+};
+
+fn name() -> String { String::from("admin") }
+fn description() -> String {
+    String::from("Moderate the scores and votes of this server")
+}
+
+/// The largest amount a score may be adjusted by at once.
+const MAX_ADJUSTMENT: i64 = 1_000_000;
+
+/// The number of entries listed by the log subcommand.
+const LOG_SIZE: i64 = 15;
+
+/// The admin slash command, it is only available to the members that may
+/// moderate the server. It has subcommands used to adjust a user's score,
+/// reset the scores of a user or of the whole server, void a post and ban
+/// a user from voting. Every action is recorded in the server's audit log,
+/// listed with the `log` subcommand.
+pub struct Admin;
+
+#[async_trait]
+impl SlashCommand for Admin {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .default_member_permissions(Permissions::MODERATE_MEMBERS)
+            .dm_permission(false)
+            .create_option(|option| {
+                option
+                    .name("adjust")
+                    .description("Add points to, or remove points from a score")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description("The user whose score is adjusted")
+                            .kind(CommandOptionType::User)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("amount")
+                            .description("The points added to the score")
+                            .kind(CommandOptionType::Integer)
+                            .min_int_value(-MAX_ADJUSTMENT)
+                            .max_int_value(MAX_ADJUSTMENT)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("reason")
+                            .description("Why the score is adjusted")
+                            .kind(CommandOptionType::String)
+                            .required(true)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("reset")
+                    .description("Reset the scores, removing the votes")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description(
+                                "Reset a user's score and remove the votes on \
+                                 their posts",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("user")
+                                    .description("The user to reset")
+                                    .kind(CommandOptionType::User)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("reason")
+                                    .description("Why the score is reset")
+                                    .kind(CommandOptionType::String)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("guild")
+                            .description(
+                                "Reset all the scores of the server and \
+                                 remove all the votes",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("confirm")
+                                    .description(
+                                        "Confirm that all the scores are \
+                                         reset, this can not be undone",
+                                    )
+                                    .kind(CommandOptionType::Boolean)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("reason")
+                                    .description("Why the scores are reset")
+                                    .kind(CommandOptionType::String)
+                                    .required(false)
+                            })
+                    })
+            })
+            .create_option(|option| {
+                option
+                    .name("void")
+                    .description(
+                        "Remove the votes on a post and ignore the future ones",
+                    )
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("post")
+                            .description("The link to the post, or its id")
+                            .kind(CommandOptionType::String)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("reason")
+                            .description("Why the post is voided")
+                            .kind(CommandOptionType::String)
+                            .required(false)
+                    })
+            })
+            .create_option(|option| {
+                option
+                    .name("ban-from-voting")
+                    .description("Ignore the votes of a user")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description("The user to ban from voting")
+                            .kind(CommandOptionType::User)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("banned")
+                            .description(
+                                "Whether the user is banned, true by default",
+                            )
+                            .kind(CommandOptionType::Boolean)
+                            .required(false)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("reason")
+                            .description("Why the user is banned")
+                            .kind(CommandOptionType::String)
+                            .required(false)
+                    })
+            })
+            .create_option(|option| {
+                option
+                    .name("log")
+                    .description("List the latest moderation actions")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("user")
+                            .description("Only list the actions on this user")
+                            .kind(CommandOptionType::User)
+                            .required(false)
+                    })
+            })
+    }
+
+    /// Take the moderation action of the provided subcommand, or list the
+    /// audit log, only visible to the command's user.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    let subcommand = match get_subcommand(&command) {
+        | Some(subcommand) => subcommand,
+        | None => {
+            log::warn!("Received admin command with no subcommand");
+            return;
+        }
+    };
+    if subcommand.name == "log" {
+        let embed = get_log_embed(state, guild_id, subcommand)
+            .await
+            .unwrap_or_else(|why| {
+                log::warn!("Failed to fetch the audit log: {}", why);
+                let mut embed = CreateEmbed::default();
+                embed.description("Failed to fetch the audit log");
+                embed
+            });
+        if let Err(why) = command
+            .create_interaction_response(&ctx.http, |response| {
+                response.interaction_response_data(|message| {
+                    message.set_embed(embed).flags(MessageFlags::EPHEMERAL)
+                })
+            })
+            .await
+        {
+            log::warn!("Failed to respond with the audit log: {}", why);
+        }
+        return;
+    }
+
+    let content = match take_action(state, &command, guild_id, subcommand).await
+    {
+        | Ok(content) => content,
+        | Err(why) => {
+            log::warn!("Failed to take moderation action: {}", why);
+            String::from("Failed to take the moderation action")
+        }
+    };
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response.interaction_response_data(|message| {
+                message.content(content).flags(MessageFlags::EPHEMERAL)
+            })
+        })
+        .await
+    {
+        log::warn!("Failed to respond to admin command: {}", why);
+    }
+}
+
+/// Take the action of the provided subcommand and queue the sync of the
+/// guild's reward roles, as the scores may have changed. Returns the
+/// content of the response.
+async fn take_action(
+    state: &State,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let reason =
+        get_value(subcommand, "reason").and_then(|value| value.as_str());
+    let action = match subcommand.name.as_str() {
+        | "adjust" => Action::Adjust(
+            get_user_id(subcommand)?,
+            get_value(subcommand, "amount")
+                .and_then(|value| value.as_i64())
+                .ok_or_else(|| String::from("No amount provided"))?,
+        ),
+        | "user" => Action::ResetUser(get_user_id(subcommand)?),
+        | "guild" => {
+            if !get_value(subcommand, "confirm")
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false)
+            {
+                return Ok(String::from("The scores were not reset"));
+            }
+            Action::ResetGuild
+        }
+        | "void" => {
+            let post = get_value(subcommand, "post")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            match parse_post_id(post) {
+                | Some(post_id) => Action::VoidPost(post_id),
+                | None => return Ok(format!("_{}_ is not a valid post", post)),
+            }
+        }
+        | "ban-from-voting" => Action::BanFromVoting(
+            get_user_id(subcommand)?,
+            get_value(subcommand, "banned")
+                .and_then(|value| value.as_bool())
+                .unwrap_or(true),
+        ),
+        | _ => return Err(String::from("Unknown subcommand")),
+    };
+    if !audit::take_action(
+        &state.datastore.pool,
+        guild_id,
+        command.user.id,
+        action,
+        reason,
+    )
+    .await?
+    {
+        return Ok(describe_failure(&action));
+    }
+    log::info!(
+        "Moderator {} took action '{}' in guild {}",
+        command.user.id,
+        action.name(),
+        guild_id
+    );
+    state.queue_rank_sync(guild_id);
+    Ok(format!("Done, {}", describe_action(&action)))
+}
+
+async fn get_log_embed(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<CreateEmbed, String> {
+    let user_id = get_user_id(subcommand).ok();
+    let entries =
+        audit::get_entries(&state.datastore.pool, guild_id, user_id, LOG_SIZE)
+            .await?;
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Audit log");
+    if entries.is_empty() {
+        embed.description("No moderation actions were taken");
+    } else {
+        embed.description(
+            entries.iter().map(describe_entry).collect::<Vec<_>>().join("\n"),
+        );
+    }
+    Ok(embed)
+}
+
+fn describe_entry(entry: &Entry) -> String {
+    let mut description = format!(
+        "<t:{}:f> <@{}> {}",
+        entry.created_at,
+        entry.moderator_id,
+        describe_action(&entry.action)
+    );
+    if let Some(reason) = &entry.reason {
+        description.push_str(format!(": _{}_", reason).as_str());
+    }
+    description
+}
+
+/// Explain why the provided action was not taken, such as the voided post
+/// not being found.
+fn describe_failure(action: &Action) -> String {
+    match action {
+        | Action::VoidPost(post_id) => {
+            format!("Post `{}` was not found in this server", post_id)
+        }
+        | _ => String::from("Nothing was changed"),
+    }
+}
+
+fn describe_action(action: &Action) -> String {
+    match action {
+        | Action::Adjust(user_id, amount) => {
+            format!("adjusted <@{}>'s score by {:+}", user_id, amount)
+        }
+        | Action::ResetUser(user_id) => format!("reset <@{}>'s score", user_id),
+        | Action::ResetGuild => String::from("reset all the scores"),
+        | Action::VoidPost(post_id) => format!("voided post `{}`", post_id),
+        | Action::BanFromVoting(user_id, true) => {
+            format!("banned <@{}> from voting", user_id)
+        }
+        | Action::BanFromVoting(user_id, false) => {
+            format!("unbanned <@{}> from voting", user_id)
+        }
+        | Action::ApprovePost(post_id) => {
+            format!("approved reported post `{}`", post_id)
+        }
+        | Action::RemovePost(post_id) => {
+            format!("removed reported post `{}`", post_id)
+        }
+    }
+}
+
+fn get_user_id(subcommand: &CommandDataOption) -> Result<UserId, String> {
+    get_value(subcommand, "user")
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(UserId)
+        .ok_or_else(|| String::from("No user provided"))
+}
+
+/// Get the subcommand of the provided interaction, nested in its
+/// subcommand group when it has one.
+fn get_subcommand(
+    command: &ApplicationCommandInteraction,
+) -> Option<&CommandDataOption> {
+    let option = command.data.options.first()?;
+    match option.kind {
+        | CommandOptionType::SubCommandGroup => option.options.first(),
+        | _ => Some(option),
+    }
+}
@@ -0,0 +1,129 @@
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::prelude::{
+        command::CommandOptionType,
+        interaction::application_command::ApplicationCommandInteraction,
+        GuildId, User,
+    },
+    prelude::Context,
+};
+
+use super::{get_user_or_caller, SlashCommand};
+use crate::{
+    datastore,
+    handler::{achievement::ACHIEVEMENTS, member, state::State},
+};
+
+fn name() -> String { String::from("badges") }
+fn description() -> String { String::from("List a user's achievements") }
+
+/// The badges slash command. It has an optional `user` option, defaulting
+/// to the user of the command, and lists the achievements the user has
+/// unlocked, along with the locked ones.
+pub struct Badges;
+
+#[async_trait]
+impl SlashCommand for Badges {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command.create_option(|option| {
+            option
+                .name("user")
+                .description("The user to list the achievements of")
+                .kind(CommandOptionType::User)
+                .required(false)
+        })
+    }
+
+    /// Respond to the provided command with the achievements of the user
+    /// provided as the command's option, or the command's user.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Handling '{}' command ...", name());
+
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    let user = get_user_or_caller(&command);
+    let embed = match get_embed(&ctx, state, guild_id, user).await {
+        | Ok(embed) => embed,
+        | Err(why) => {
+            log::warn!("Failed to get user achievements: {}", why);
+            return;
+        }
+    };
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .interaction_response_data(|message| message.set_embed(embed))
+        })
+        .await
+    {
+        log::warn!("Failed to respond to badges command: {}", why);
+    }
+}
+
+async fn get_embed(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user: &User,
+) -> Result<CreateEmbed, String> {
+    let unlocked = datastore::achievement::get_unlocked(
+        &state.datastore.pool,
+        guild_id,
+        user.id,
+    )
+    .await?;
+    let name = member::display_name(ctx, state, guild_id, user.id)
+        .await
+        .unwrap_or_else(|_| user.name.clone());
+
+    let mut earned = Vec::new();
+    let mut locked = Vec::new();
+    for achievement in ACHIEVEMENTS.iter() {
+        match unlocked.iter().find(|(id, _)| id == achievement.id) {
+            | Some((_, unlocked_at)) => earned.push(format!(
+                "🏆 **{}**: {} (<t:{}:D>)",
+                achievement.name, achievement.description, unlocked_at
+            )),
+            | None => locked.push(format!(
+                "🔒 **{}**: {}",
+                achievement.name, achievement.description
+            )),
+        }
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("{}'s badges", name)).thumbnail(user.face()).footer(
+        |f| f.text(format!("{}/{} unlocked", earned.len(), ACHIEVEMENTS.len())),
+    );
+    if !earned.is_empty() {
+        embed.field("Earned", earned.join("\n"), false);
+    }
+    if !locked.is_empty() {
+        embed.field("Locked", locked.join("\n"), false);
+    }
+    Ok(embed)
+}
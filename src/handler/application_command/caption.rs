@@ -0,0 +1,197 @@
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::{
+        channel::Attachment,
+        prelude::{
+            command::CommandOptionType,
+            interaction::{
+                application_command::{
+                    ApplicationCommandInteraction, CommandDataOptionValue,
+                },
+                autocomplete::AutocompleteInteraction,
+            },
+        },
+    },
+    prelude::Context,
+};
+
+use super::{
+    defer_response, get_option, send_rendered, suggest_templates, SlashCommand,
+};
+use crate::{
+    handler::{state::State, template},
+    render,
+};
+
+fn name() -> String { String::from("caption") }
+fn description() -> String {
+    String::from("Caption an image with top and bottom text")
+}
+
+/// The longest top or bottom text.
+const MAX_TEXT_LENGTH: u16 = 200;
+
+/// The largest image that may be captioned, in bytes.
+const MAX_IMAGE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// The caption slash command. It renders the classic meme, with the top
+/// and bottom text drawn over an image, either the provided attachment or a
+/// template of the server or of the templates directory. The rendered meme
+/// is sent as a post that may be voted on, same as the memes sent with the
+/// meme command.
+pub struct Caption;
+
+#[async_trait]
+impl SlashCommand for Caption {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .create_option(|option| {
+                option
+                    .name("top")
+                    .description("The text at the top of the image")
+                    .kind(CommandOptionType::String)
+                    .max_length(MAX_TEXT_LENGTH)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("bottom")
+                    .description("The text at the bottom of the image")
+                    .kind(CommandOptionType::String)
+                    .max_length(MAX_TEXT_LENGTH)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("image")
+                    .description("The image to caption")
+                    .kind(CommandOptionType::Attachment)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("template")
+                    .description("The name of the template to caption")
+                    .kind(CommandOptionType::String)
+                    .set_autocomplete(true)
+                    .required(false)
+            })
+    }
+
+    /// Respond to the provided command with the captioned image, and
+    /// publish it as a post.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+
+    /// Suggest the names of the templates while the template is typed.
+    async fn handle_autocomplete(
+        &self,
+        ctx: Context,
+        autocomplete: AutocompleteInteraction,
+        state: &State,
+    ) {
+        suggest_templates(&ctx, &autocomplete, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+
+    defer_response(&ctx, &command).await;
+
+    let prepared = prepare_meme(&command, state).await;
+    send_rendered(&ctx, &command, state, prepared).await;
+}
+
+/// Prepare the rendering of the command's text over the provided image or
+/// template. When the image may not be rendered, the response explaining
+/// why is returned instead.
+async fn prepare_meme(
+    command: &ApplicationCommandInteraction,
+    state: &State,
+) -> Result<
+    Result<impl FnOnce() -> Result<Vec<u8>, String> + Send + 'static, String>,
+    String,
+> {
+    let top = get_option(command, "top")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let bottom = get_option(command, "bottom")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default()
+        .to_string();
+    if top.trim().is_empty() && bottom.trim().is_empty() {
+        return Ok(Err(String::from("Provide the top or the bottom text")));
+    }
+    let template =
+        get_option(command, "template").and_then(|value| value.as_str());
+    let image =
+        match (get_attachment(command), template) {
+            | (Some(attachment), None) => {
+                if !attachment.content_type.as_ref().is_some_and(
+                    |content_type| content_type.starts_with("image/"),
+                ) {
+                    return Ok(Err(format!(
+                        "_{}_ is not an image",
+                        attachment.filename
+                    )));
+                }
+                if attachment.size > MAX_IMAGE_SIZE {
+                    return Ok(Err(format!(
+                        "_{}_ is too large, the captioned images may be at \
+                         most {} MB",
+                        attachment.filename,
+                        MAX_IMAGE_SIZE / (1024 * 1024)
+                    )));
+                }
+                attachment.download().await.map_err(|err| err.to_string())?
+            }
+            | (None, Some(name)) => {
+                match template::find(state, command.guild_id, name).await? {
+                    | Some((image, _)) => image,
+                    | None => {
+                        return Ok(Err(format!("_{}_ is not a template", name)))
+                    }
+                }
+            }
+            | _ => {
+                return Ok(Err(String::from(
+                    "Provide either an image or a template to caption",
+                )))
+            }
+        };
+    Ok(Ok(move || render::caption(&image, &top, &bottom)))
+}
+
+/// Get the image provided in the command's options.
+fn get_attachment(
+    command: &ApplicationCommandInteraction,
+) -> Option<&Attachment> {
+    command.data.options.iter().find(|option| option.name == "image").and_then(
+        |option| match &option.resolved {
+            | Some(CommandDataOptionValue::Attachment(attachment)) => {
+                Some(attachment)
+            }
+            | _ => None,
+        },
+    )
+}
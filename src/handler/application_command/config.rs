@@ -0,0 +1,1799 @@
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::{
+        prelude::{
+            command::CommandOptionType,
+            interaction::{
+                application_command::{
+                    ApplicationCommandInteraction, CommandDataOption,
+                    CommandDataOptionValue,
+                },
+                autocomplete::AutocompleteInteraction,
+                InteractionResponseType, MessageFlags,
+            },
+            ChannelId, ChannelType, GuildId, RoleId,
+        },
+        Permissions,
+    },
+    prelude::Context,
+};
+
+use super::{get_value, suggest_templates, SlashCommand};
+use crate::{
+    datastore::{
+        self,
+        guild::{AttachmentRules, HideMode, ReportConfig, VotingConfig},
+        reward::Rule,
+        template::Template,
+        user::{DecayMode, Period},
+    },
+    handler::{reward, scheduler, state::State, template},
+    render,
+};
+
+fn name() -> String { String::from("config") }
+fn description() -> String { String::from("Configure the bot for this server") }
+
+/// The lowest leaderboard position a role may be rewarded for.
+const MAX_REWARD_RANK: u64 = 25;
+
+/// The longest half-life of the decayed votes, about 10 years.
+const MAX_HALF_LIFE_DAYS: u64 = 3650;
+
+/// The longest time new members may be stopped from voting for.
+const MAX_MEMBER_DAYS: u64 = 365;
+
+/// The highest daily vote budget, about the votes a member may give by
+/// voting every minute.
+const MAX_DAILY_VOTES: u64 = 1440;
+
+/// The longest voting window of the posts, about a year.
+const MAX_WINDOW_DAYS: u64 = 365;
+
+/// The most reports a post may need to be hidden.
+const MAX_REPORT_THRESHOLD: u64 = 100;
+
+/// The largest size of the posted files, in megabytes.
+const MAX_ATTACHMENT_MB: u64 = 500;
+
+/// The largest width and height of the posted images and videos.
+const MAX_ATTACHMENT_DIMENSION: u64 = 16384;
+
+/// The files that may be posted at once, as the meme command has four
+/// attachment options.
+const MAX_ATTACHMENTS: u64 = 4;
+
+/// The longest name of an uploaded template.
+const MAX_TEMPLATE_NAME_LENGTH: u16 = template::MAX_NAME_LENGTH as u16;
+
+/// The largest image of an uploaded template, in bytes.
+const MAX_TEMPLATE_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The most names of the server's templates listed.
+const MAX_LISTED_TEMPLATES: i64 = 50;
+
+/// The leaderboard periods a role may be rewarded for.
+const REWARD_PERIODS: [Period; 6] = [
+    Period::AllTime,
+    Period::Today,
+    Period::Week,
+    Period::Month,
+    Period::Last7Days,
+    Period::Last30Days,
+];
+
+/// The config slash command, it is only available to the members that
+/// may manage the server. It has a `commands` subcommand group used to
+/// enable, disable and list the commands available in the server,
+/// a `timezone` subcommand group used to set the server's time zone,
+/// an `announcements` subcommand group used to manage the scheduled
+/// announcements of the server's top posts, a `roles` subcommand group
+/// used to manage the roles rewarded for the scores, a `decay`
+/// subcommand group used to configure how the votes lose their value,
+/// a `voting` subcommand group used to configure how the votes count,
+/// a `reports` subcommand group used to configure how the reported posts
+/// are handled, an `attachments` subcommand group used to configure the
+/// files that may be posted, a `spoilers` subcommand group used to manage
+/// the channels where the memes are always sent as spoilers, and a
+/// `templates` subcommand group used to manage the server's meme templates.
+pub struct Config {
+    toggleable: Vec<String>,
+}
+
+impl Config {
+    /// Create the config command, the provided commands may be
+    /// enabled or disabled with it.
+    pub fn new(toggleable: Vec<String>) -> Config {
+        Config {
+            toggleable,
+        }
+    }
+}
+
+#[async_trait]
+impl SlashCommand for Config {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .default_member_permissions(Permissions::MANAGE_GUILD)
+            .dm_permission(false)
+            .create_option(|group| {
+                group
+                    .name("commands")
+                    .description("Manage the commands available in the server")
+                    .kind(CommandOptionType::SubCommandGroup);
+                for (subcommand, description) in [
+                    ("enable", "Enable a command in this server"),
+                    ("disable", "Disable a command in this server"),
+                ] {
+                    group.create_sub_option(|option| {
+                        option
+                            .name(subcommand)
+                            .description(description)
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("command")
+                                    .description("The command's name")
+                                    .kind(CommandOptionType::String)
+                                    .required(true);
+                                for name in self.toggleable.iter() {
+                                    option.add_string_choice(name, name);
+                                }
+                                option
+                            })
+                    });
+                }
+                group.create_sub_option(|option| {
+                    option
+                        .name("list")
+                        .description(
+                            "List the commands disabled in this server",
+                        )
+                        .kind(CommandOptionType::SubCommand)
+                })
+            })
+            .create_option(|group| {
+                group
+                    .name("timezone")
+                    .description("Manage the time zone of the server")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("set")
+                            .description(
+                                "Set the time zone the leaderboard periods \
+                                 start in",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("zone")
+                                    .description(
+                                        "The time zone's name, such as \
+                                         Europe/Ljubljana",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("show")
+                            .description("Show the time zone of the server")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("announcements")
+                    .description("Manage the announcements of the top posts")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("add")
+                            .description(
+                                "Announce the top post on a schedule, such as \
+                                 the meme of the day",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("name")
+                                    .description(
+                                        "The announcement's title, such as \
+                                         Meme of the Day",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .max_length(100)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("schedule")
+                                    .description(
+                                        "A cron expression, such as 0 12 * * \
+                                         * for every day at noon",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("channel")
+                                    .description(
+                                        "The channel the announcement is sent \
+                                         to",
+                                    )
+                                    .kind(CommandOptionType::Channel)
+                                    .channel_types(&[ChannelType::Text])
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("bonus")
+                                    .description(
+                                        "Points added to the winner's score",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(0)
+                                    .required(false)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("role")
+                                    .description(
+                                        "A role moved to the latest winner",
+                                    )
+                                    .kind(CommandOptionType::Role)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("remove")
+                            .description("Remove an announcement")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("name")
+                                    .description("The announcement's title")
+                                    .kind(CommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("list")
+                            .description("List the server's announcements")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("roles")
+                    .description("Manage the roles rewarded for the scores")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("score")
+                            .description("Reward a role for reaching a score")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("role")
+                                    .description("The rewarded role")
+                                    .kind(CommandOptionType::Role)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("score")
+                                    .description("The score required")
+                                    .kind(CommandOptionType::Integer)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("rank")
+                            .description(
+                                "Reward a role for a position on the \
+                                 leaderboard",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("role")
+                                    .description("The rewarded role")
+                                    .kind(CommandOptionType::Role)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("rank")
+                                    .description(
+                                        "The lowest rank rewarded, such as 3 \
+                                         for the top 3",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_REWARD_RANK)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("period")
+                                    .description(
+                                        "The period of the leaderboard",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .required(false);
+                                for period in REWARD_PERIODS {
+                                    option.add_string_choice(
+                                        period.describe(),
+                                        period.name(),
+                                    );
+                                }
+                                option
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("remove")
+                            .description("Stop rewarding a role, and revoke it")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("role")
+                                    .description("The rewarded role")
+                                    .kind(CommandOptionType::Role)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("list")
+                            .description("List the server's reward roles")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("decay")
+                    .description(
+                        "Manage how the votes lose their value over time",
+                    )
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("set")
+                            .description(
+                                "Decay the votes counted on the leaderboard",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("mode")
+                                    .description("How the votes decay")
+                                    .kind(CommandOptionType::String)
+                                    .add_string_choice(
+                                        "halved every half-life",
+                                        DecayMode::Step.name(),
+                                    )
+                                    .add_string_choice(
+                                        "exponentially",
+                                        DecayMode::Exponential.name(),
+                                    )
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("half-life")
+                                    .description(
+                                        "The days it takes a vote to lose \
+                                         half of its value",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_HALF_LIFE_DAYS)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("disable")
+                            .description("Stop decaying the votes")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("show")
+                            .description("Show how the votes decay")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("voting")
+                    .description("Manage how the votes are counted")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("min-member-age")
+                            .description(
+                                "Ignore the votes of the members that joined \
+                                 recently",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("days")
+                                    .description(
+                                        "The days a member must have been in \
+                                         the server for, leave empty to count \
+                                         all the votes",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_MEMBER_DAYS)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("flagged-weight")
+                            .description(
+                                "Down-weight the votes of the flagged voters",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("weight")
+                                    .description(
+                                        "The weight of the flagged votes, \
+                                         leave empty to count them fully",
+                                    )
+                                    .kind(CommandOptionType::Number)
+                                    .min_number_value(0.0)
+                                    .max_number_value(1.0)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("min-account-age")
+                            .description(
+                                "Ignore the votes of the recently created \
+                                 accounts",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("days")
+                                    .description(
+                                        "The days an account must have \
+                                         existed for, leave empty to count \
+                                         all the votes",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_MEMBER_DAYS)
+                                    .required(false)
+                            })
+                    });
+                for (subcommand, description) in [
+                    (
+                        "max-downvotes",
+                        "Limit the downvotes a member may give each day",
+                    ),
+                    (
+                        "max-author-votes",
+                        "Limit the votes a member may give each author each \
+                         day",
+                    ),
+                ] {
+                    group.create_sub_option(|option| {
+                        option
+                            .name(subcommand)
+                            .description(description)
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("count")
+                                    .description(
+                                        "The votes counted each day, leave \
+                                         empty to count all the votes",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_DAILY_VOTES)
+                                    .required(false)
+                            })
+                    });
+                }
+                group
+                    .create_sub_option(|option| {
+                        option
+                            .name("budget-notice")
+                            .description(
+                                "Notify the members once a day when their \
+                                 votes are no longer counted",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("enabled")
+                                    .description(
+                                        "Whether the members are notified",
+                                    )
+                                    .kind(CommandOptionType::Boolean)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("window")
+                            .description(
+                                "Stop counting the votes on the posts once \
+                                 they are old enough",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("days")
+                                    .description(
+                                        "The days the posts may be voted on \
+                                         for, leave empty to never stop",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_WINDOW_DAYS)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("final-tally")
+                            .description(
+                                "Show the final tally on the posts once their \
+                                 voting window closes",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("enabled")
+                                    .description(
+                                        "Whether the final tally is shown",
+                                    )
+                                    .kind(CommandOptionType::Boolean)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("show")
+                            .description("Show how the votes are counted")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("reports")
+                    .description("Manage how the reported posts are handled")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("channel")
+                            .description(
+                                "Set the channel the reported posts are \
+                                 reviewed in",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("channel")
+                                    .description(
+                                        "The channel the review cards are \
+                                         sent to, leave empty to stop sending \
+                                         them",
+                                    )
+                                    .kind(CommandOptionType::Channel)
+                                    .channel_types(&[ChannelType::Text])
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("threshold")
+                            .description(
+                                "Hide the posts reported by enough members",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("count")
+                                    .description(
+                                        "The reports after which a post is \
+                                         hidden, leave empty to never hide \
+                                         the posts",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_REPORT_THRESHOLD)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("hide-mode")
+                            .description(
+                                "Set how the reported posts are hidden",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("mode")
+                                    .description("How the posts are hidden")
+                                    .kind(CommandOptionType::String)
+                                    .add_string_choice(
+                                        "marked as spoilers",
+                                        HideMode::Spoiler.name(),
+                                    )
+                                    .add_string_choice(
+                                        "deleted",
+                                        HideMode::Delete.name(),
+                                    )
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("show")
+                            .description(
+                                "Show how the reported posts are handled",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("attachments")
+                    .description("Manage the files that may be posted")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("types")
+                            .description(
+                                "Set the types of files that may be posted",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("types")
+                                    .description(
+                                        "The comma separated types, such as \
+                                         image, video/mp4",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("max-size")
+                            .description("Limit the size of the posted files")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("megabytes")
+                                    .description(
+                                        "The size of the largest file, leave \
+                                         empty to allow any size",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_ATTACHMENT_MB)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("max-dimension")
+                            .description(
+                                "Limit the width and height of the posted \
+                                 images and videos",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("pixels")
+                                    .description(
+                                        "The largest width and height, leave \
+                                         empty to allow any dimensions",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_ATTACHMENT_DIMENSION)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("max-count")
+                            .description(
+                                "Limit the files that may be posted at once",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("count")
+                                    .description(
+                                        "The files posted at once, leave \
+                                         empty to allow all of them",
+                                    )
+                                    .kind(CommandOptionType::Integer)
+                                    .min_int_value(1)
+                                    .max_int_value(MAX_ATTACHMENTS)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("strip-metadata")
+                            .description(
+                                "Strip the metadata, such as the location, \
+                                 from the posted images",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("enabled")
+                                    .description(
+                                        "Whether the metadata is stripped",
+                                    )
+                                    .kind(CommandOptionType::Boolean)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("show")
+                            .description("Show the files that may be posted")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+            .create_option(|group| {
+                group
+                    .name("spoilers")
+                    .description(
+                        "Manage the channels where the memes are always sent \
+                         as spoilers",
+                    )
+                    .kind(CommandOptionType::SubCommandGroup);
+                for (subcommand, description) in [
+                    ("add", "Always send the memes as spoilers in a channel"),
+                    ("remove", "Stop forcing the spoilers in a channel"),
+                ] {
+                    group.create_sub_option(|option| {
+                        option
+                            .name(subcommand)
+                            .description(description)
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("channel")
+                                    .description("The channel's name")
+                                    .kind(CommandOptionType::Channel)
+                                    .channel_types(&[ChannelType::Text])
+                                    .required(true)
+                            })
+                    });
+                }
+                group.create_sub_option(|option| {
+                    option
+                        .name("list")
+                        .description(
+                            "List the channels where the memes are always \
+                             sent as spoilers",
+                        )
+                        .kind(CommandOptionType::SubCommand)
+                })
+            })
+            .create_option(|group| {
+                group
+                    .name("templates")
+                    .description("Manage the meme templates of the server")
+                    .kind(CommandOptionType::SubCommandGroup)
+                    .create_sub_option(|option| {
+                        option
+                            .name("add")
+                            .description(
+                                "Upload a meme template, or replace one",
+                            )
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("name")
+                                    .description(
+                                        "The template's name, made of \
+                                         lowercase letters, digits, - and _",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .max_length(MAX_TEMPLATE_NAME_LENGTH)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("image")
+                                    .description("The template's image")
+                                    .kind(CommandOptionType::Attachment)
+                                    .required(true)
+                            })
+                            .create_sub_option(|option| {
+                                option
+                                    .name("boxes")
+                                    .description(
+                                        "The text boxes as x, y, width, \
+                                         height in % and an optional \
+                                         alignment, separated by ;",
+                                    )
+                                    .kind(CommandOptionType::String)
+                                    .required(false)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("remove")
+                            .description("Remove a meme template")
+                            .kind(CommandOptionType::SubCommand)
+                            .create_sub_option(|option| {
+                                option
+                                    .name("name")
+                                    .description("The template's name")
+                                    .kind(CommandOptionType::String)
+                                    .set_autocomplete(true)
+                                    .required(true)
+                            })
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("list")
+                            .description("List the meme templates")
+                            .kind(CommandOptionType::SubCommand)
+                    })
+            })
+    }
+
+    /// Update the guild's config with the provided subcommand and
+    /// re-register the guild's commands.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        log::trace!("Running '{}' command ...", name());
+
+        let guild_id = match command.guild_id {
+            | Some(guild_id) => guild_id,
+            | None => return,
+        };
+        let (group, subcommand) = match get_subcommand(&command) {
+            | Some(v) => v,
+            | None => {
+                log::warn!("Received config command with no subcommand");
+                return;
+            }
+        };
+        // NOTE: removing a reward role revokes it from the whole guild, and
+        // adding a template downloads its image, so the response is deferred
+        // privately.
+        if let Err(why) = command
+            .create_interaction_response(&ctx.http, |response| {
+                response
+                    .kind(InteractionResponseType::DeferredChannelMessageWithSource)
+                    .interaction_response_data(|message| {
+                        message.flags(MessageFlags::EPHEMERAL)
+                    })
+            })
+            .await
+        {
+            log::warn!("Failed to defer config command: {}", why);
+        }
+        let content = match (group.name.as_str(), subcommand.name.as_str()) {
+            | ("commands", "list") => list_commands(state, guild_id).await,
+            | ("commands", toggle) => {
+                toggle_command(&ctx, state, guild_id, subcommand, toggle).await
+            }
+            | ("timezone", "set") => {
+                set_timezone(state, guild_id, subcommand).await
+            }
+            | ("timezone", "show") => show_timezone(state, guild_id).await,
+            | ("announcements", "add") => {
+                add_announcement(state, guild_id, subcommand).await
+            }
+            | ("announcements", "remove") => {
+                remove_announcement(state, guild_id, subcommand).await
+            }
+            | ("announcements", "list") => {
+                list_announcements(state, guild_id).await
+            }
+            | ("roles", "list") => list_reward_roles(state, guild_id).await,
+            | ("roles", "remove") => {
+                remove_reward_role(&ctx, state, guild_id, subcommand).await
+            }
+            | ("roles", _) => {
+                set_reward_role(state, guild_id, subcommand).await
+            }
+            | ("decay", "set") => set_decay(state, guild_id, subcommand).await,
+            | ("decay", "disable") => disable_decay(state, guild_id).await,
+            | ("decay", "show") => show_decay(state, guild_id).await,
+            | ("voting", "show") => show_voting(state, guild_id).await,
+            | ("voting", _) => set_voting(state, guild_id, subcommand).await,
+            | ("reports", "show") => show_reports(state, guild_id).await,
+            | ("reports", _) => set_reports(state, guild_id, subcommand).await,
+            | ("attachments", "show") => {
+                show_attachment_rules(state, guild_id).await
+            }
+            | ("attachments", _) => {
+                set_attachment_rules(state, guild_id, subcommand).await
+            }
+            | ("spoilers", "list") => {
+                list_spoiler_channels(state, guild_id).await
+            }
+            | ("spoilers", toggle) => {
+                toggle_spoiler_channel(state, guild_id, subcommand, toggle)
+                    .await
+            }
+            | ("templates", "add") => {
+                add_template(state, &command, guild_id, subcommand).await
+            }
+            | ("templates", "remove") => {
+                remove_template(state, guild_id, subcommand).await
+            }
+            | ("templates", "list") => list_templates(state, guild_id).await,
+            | _ => Err(String::from("Unknown subcommand")),
+        };
+        let content = content.unwrap_or_else(|why| {
+            log::warn!("Failed to update config: {}", why);
+            String::from("Failed to update the config")
+        });
+        if let Err(why) = command
+            .edit_original_interaction_response(&ctx.http, |response| {
+                response.content(content)
+            })
+            .await
+        {
+            log::warn!("Failed to respond to config command: {}", why);
+        }
+    }
+
+    /// Suggest the names of the templates while the template to remove is
+    /// typed.
+    async fn handle_autocomplete(
+        &self,
+        ctx: Context,
+        autocomplete: AutocompleteInteraction,
+        state: &State,
+    ) {
+        suggest_templates(&ctx, &autocomplete, state).await
+    }
+}
+
+async fn list_commands(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let disabled = datastore::guild::get_disabled_commands(
+        &state.datastore.pool,
+        guild_id,
+    )
+    .await?;
+    if disabled.is_empty() {
+        return Ok(String::from("All commands are enabled in this server"));
+    }
+    Ok(format!("Disabled commands: **_{}_**", disabled.join(", ")))
+}
+
+async fn toggle_command(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+    toggle: &str,
+) -> Result<String, String> {
+    let enabled = toggle == "enable";
+    let name = subcommand
+        .options
+        .iter()
+        .find(|option| option.name == "command")
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No command name provided"))?;
+    datastore::guild::set_command_enabled(
+        &state.datastore.pool,
+        guild_id,
+        name,
+        enabled,
+    )
+    .await?;
+    if let Err(why) = state.commands.register_guild(ctx, guild_id, state).await
+    {
+        log::warn!("Failed to re-register guild commands: {}", why);
+    }
+    Ok(format!("Command **_{}_** is now {}d", name, toggle))
+}
+
+async fn set_timezone(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let timezone = subcommand
+        .options
+        .iter()
+        .find(|option| option.name == "zone")
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No time zone provided"))?;
+    if !datastore::guild::set_timezone(
+        &state.datastore.pool,
+        guild_id,
+        timezone,
+    )
+    .await?
+    {
+        return Ok(format!("Unknown time zone **_{}_**", timezone));
+    }
+    Ok(format!("The time zone is now **_{}_**", timezone))
+}
+
+async fn show_timezone(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let timezone =
+        datastore::guild::get_timezone(&state.datastore.pool, guild_id).await?;
+    Ok(format!("The time zone is **_{}_**", timezone))
+}
+
+async fn add_announcement(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let name = get_value(subcommand, "name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No announcement name provided"))?;
+    let schedule = get_value(subcommand, "schedule")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No schedule provided"))?;
+    let channel_id = get_value(subcommand, "channel")
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(ChannelId)
+        .ok_or_else(|| String::from("No channel provided"))?;
+    let bonus = get_value(subcommand, "bonus")
+        .and_then(|value| value.as_i64())
+        .unwrap_or(0);
+    let role_id = get_value(subcommand, "role")
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(RoleId);
+    if let Err(why) = scheduler::parse_schedule(schedule) {
+        return Ok(format!("Invalid schedule `{}`: {}", schedule, why));
+    }
+    datastore::announcement::set_announcement(
+        &state.datastore.pool,
+        guild_id,
+        name,
+        schedule,
+        channel_id,
+        bonus,
+        role_id,
+    )
+    .await?;
+    Ok(format!(
+        "Announcement **_{}_** is sent to <#{}> on schedule `{}`",
+        name, channel_id, schedule
+    ))
+}
+
+async fn remove_announcement(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let name = get_value(subcommand, "name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No announcement name provided"))?;
+    if !datastore::announcement::remove_announcement(
+        &state.datastore.pool,
+        guild_id,
+        name,
+    )
+    .await?
+    {
+        return Ok(format!("No announcement named **_{}_**", name));
+    }
+    Ok(format!("Announcement **_{}_** removed", name))
+}
+
+async fn list_announcements(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let announcements = datastore::announcement::get_announcements(
+        &state.datastore.pool,
+        guild_id,
+    )
+    .await?;
+    if announcements.is_empty() {
+        return Ok(String::from("There are no announcements in this server"));
+    }
+    Ok(announcements
+        .iter()
+        .map(|announcement| {
+            let mut row = format!(
+                "**_{}_**: `{}` in <#{}>",
+                announcement.name,
+                announcement.schedule,
+                announcement.channel_id
+            );
+            if announcement.bonus != 0 {
+                row.push_str(
+                    format!(", {} bonus points", announcement.bonus).as_str(),
+                );
+            }
+            if let Some(role_id) = announcement.role_id {
+                row.push_str(format!(", role <@&{}>", role_id).as_str());
+            }
+            row
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+async fn set_reward_role(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let role_id = get_role_id(subcommand)?;
+    let rule = match subcommand.name.as_str() {
+        | "score" => Rule::Score(
+            get_value(subcommand, "score")
+                .and_then(|value| value.as_i64())
+                .ok_or_else(|| String::from("No score provided"))?,
+        ),
+        | _ => Rule::Rank(
+            get_value(subcommand, "rank")
+                .and_then(|value| value.as_i64())
+                .ok_or_else(|| String::from("No rank provided"))?,
+            get_value(subcommand, "period")
+                .and_then(|value| value.as_str())
+                .and_then(Period::from_name)
+                .unwrap_or(Period::AllTime),
+        ),
+    };
+    datastore::reward::set_reward_role(
+        &state.datastore.pool,
+        guild_id,
+        role_id,
+        &rule,
+    )
+    .await?;
+    state.queue_rank_sync(guild_id);
+    Ok(format!(
+        "Role <@&{}> is rewarded {}, it is granted within a minute",
+        role_id,
+        describe_rule(&rule)
+    ))
+}
+
+async fn remove_reward_role(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let role_id = get_role_id(subcommand)?;
+    reward::revoke_all(ctx, state, guild_id, role_id).await?;
+    if !datastore::reward::remove_reward_role(
+        &state.datastore.pool,
+        guild_id,
+        role_id,
+    )
+    .await?
+    {
+        return Ok(format!("Role <@&{}> is not rewarded", role_id));
+    }
+    Ok(format!("Role <@&{}> is no longer rewarded", role_id))
+}
+
+async fn list_reward_roles(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let rewards =
+        datastore::reward::get_reward_roles(&state.datastore.pool, guild_id)
+            .await?;
+    if rewards.is_empty() {
+        return Ok(String::from("There are no reward roles in this server"));
+    }
+    Ok(rewards
+        .iter()
+        .map(|reward| {
+            format!("<@&{}> {}", reward.role_id, describe_rule(&reward.rule))
+        })
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+async fn set_decay(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let mode = get_value(subcommand, "mode")
+        .and_then(|value| value.as_str())
+        .and_then(DecayMode::from_name)
+        .ok_or_else(|| String::from("No decay mode provided"))?;
+    let half_life_days = get_value(subcommand, "half-life")
+        .and_then(|value| value.as_i64())
+        .and_then(|days| i32::try_from(days).ok())
+        .ok_or_else(|| String::from("No half-life provided"))?;
+    datastore::guild::set_decay(
+        &state.datastore.pool,
+        guild_id,
+        Some((mode, half_life_days)),
+    )
+    .await?;
+    Ok(format!("The votes now decay {}", describe_decay(mode, half_life_days)))
+}
+
+async fn disable_decay(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    datastore::guild::set_decay(&state.datastore.pool, guild_id, None).await?;
+    Ok(String::from("The votes no longer decay"))
+}
+
+async fn show_decay(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let decay =
+        datastore::guild::get_decay(&state.datastore.pool, guild_id).await?;
+    Ok(match decay {
+        | Some(decay) => format!(
+            "The votes decay {}",
+            describe_decay(decay.mode, decay.half_life_days)
+        ),
+        | None => String::from("The votes do not decay"),
+    })
+}
+
+fn describe_decay(mode: DecayMode, half_life_days: i32) -> String {
+    match mode {
+        | DecayMode::Step => {
+            format!("by half every {} day/s", half_life_days)
+        }
+        | DecayMode::Exponential => format!(
+            "exponentially, losing half of their value in {} day/s",
+            half_life_days
+        ),
+    }
+}
+
+async fn set_voting(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let pool = &state.datastore.pool;
+    let mut config =
+        datastore::guild::get_voting_config(pool, guild_id).await?;
+    match subcommand.name.as_str() {
+        | "min-member-age" => {
+            config.min_member_days = get_value(subcommand, "days")
+                .and_then(|value| value.as_i64())
+                .and_then(|days| i32::try_from(days).ok())
+        }
+        | "flagged-weight" => {
+            config.flagged_vote_weight = get_value(subcommand, "weight")
+                .and_then(|value| value.as_f64())
+                .map(|weight| weight as f32)
+        }
+        | "min-account-age" => {
+            config.min_account_days = get_value(subcommand, "days")
+                .and_then(|value| value.as_i64())
+                .and_then(|days| i32::try_from(days).ok())
+        }
+        | "max-downvotes" => {
+            config.max_daily_downvotes = get_value(subcommand, "count")
+                .and_then(|value| value.as_i64())
+                .and_then(|count| i32::try_from(count).ok())
+        }
+        | "max-author-votes" => {
+            config.max_daily_author_votes = get_value(subcommand, "count")
+                .and_then(|value| value.as_i64())
+                .and_then(|count| i32::try_from(count).ok())
+        }
+        | "window" => {
+            config.voting_window_days = get_value(subcommand, "days")
+                .and_then(|value| value.as_i64())
+                .and_then(|days| i32::try_from(days).ok())
+        }
+        | "final-tally" => {
+            config.show_final_tally = get_value(subcommand, "enabled")
+                .and_then(|value| value.as_bool())
+                .ok_or_else(|| String::from("No value provided"))?
+        }
+        | "budget-notice" => {
+            config.notify_over_budget = get_value(subcommand, "enabled")
+                .and_then(|value| value.as_bool())
+                .ok_or_else(|| String::from("No value provided"))?
+        }
+        | _ => return Err(String::from("Unknown subcommand")),
+    }
+    datastore::guild::set_voting_config(pool, guild_id, &config).await?;
+    Ok(describe_voting(&config))
+}
+
+async fn show_voting(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let config =
+        datastore::guild::get_voting_config(&state.datastore.pool, guild_id)
+            .await?;
+    Ok(describe_voting(&config))
+}
+
+fn describe_voting(config: &VotingConfig) -> String {
+    let min_member_days = match config.min_member_days {
+        | Some(days) => format!(
+            "The votes of the members that joined less than {} day/s ago are \
+             ignored",
+            days
+        ),
+        | None => String::from("The votes of the new members are counted"),
+    };
+    let flagged_vote_weight = match config.flagged_vote_weight {
+        | Some(weight) => {
+            format!("The votes of the flagged voters count {:.2}", weight)
+        }
+        | None => String::from("The votes of the flagged voters count fully"),
+    };
+    let min_account_days = match config.min_account_days {
+        | Some(days) => format!(
+            "The votes of the accounts created less than {} day/s ago are \
+             ignored",
+            days
+        ),
+        | None => String::from("The votes of the new accounts are counted"),
+    };
+    let max_daily_downvotes = match config.max_daily_downvotes {
+        | Some(count) => {
+            format!("Each member's first {} downvote/s a day count", count)
+        }
+        | None => String::from("All the downvotes count"),
+    };
+    let max_daily_author_votes = match config.max_daily_author_votes {
+        | Some(count) => format!(
+            "Each member's first {} vote/s a day on each author count",
+            count
+        ),
+        | None => String::from("All the votes on each author count"),
+    };
+    let notify_over_budget = if config.notify_over_budget {
+        "The members are notified when their votes no longer count"
+    } else {
+        "The members are not notified when their votes no longer count"
+    };
+    let voting_window_days = match config.voting_window_days {
+        | Some(days) if config.show_final_tally => format!(
+            "The posts may be voted on for {} day/s, then show their final \
+             tally",
+            days
+        ),
+        | Some(days) => format!("The posts may be voted on for {} day/s", days),
+        | None => String::from("The posts may always be voted on"),
+    };
+    [
+        voting_window_days.as_str(),
+        min_member_days.as_str(),
+        min_account_days.as_str(),
+        flagged_vote_weight.as_str(),
+        max_daily_downvotes.as_str(),
+        max_daily_author_votes.as_str(),
+        notify_over_budget,
+    ]
+    .join("\n")
+}
+
+async fn set_reports(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let pool = &state.datastore.pool;
+    let mut config =
+        datastore::guild::get_report_config(pool, guild_id).await?;
+    match subcommand.name.as_str() {
+        | "channel" => {
+            config.channel_id = get_value(subcommand, "channel")
+                .and_then(|value| value.as_str())
+                .and_then(|id| id.parse::<u64>().ok())
+                .map(ChannelId)
+        }
+        | "threshold" => {
+            config.threshold = get_value(subcommand, "count")
+                .and_then(|value| value.as_i64())
+                .and_then(|count| i32::try_from(count).ok())
+        }
+        | "hide-mode" => {
+            config.hide_mode = get_value(subcommand, "mode")
+                .and_then(|value| value.as_str())
+                .and_then(HideMode::from_name)
+                .ok_or_else(|| String::from("No mode provided"))?
+        }
+        | _ => return Err(String::from("Unknown subcommand")),
+    }
+    datastore::guild::set_report_config(pool, guild_id, &config).await?;
+    Ok(describe_reports(&config))
+}
+
+async fn show_reports(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let config =
+        datastore::guild::get_report_config(&state.datastore.pool, guild_id)
+            .await?;
+    Ok(describe_reports(&config))
+}
+
+fn describe_reports(config: &ReportConfig) -> String {
+    let channel = match config.channel_id {
+        | Some(channel_id) => {
+            format!("The reported posts are reviewed in <#{}>", channel_id)
+        }
+        | None => String::from("No review cards are sent for the reports"),
+    };
+    let threshold = match (config.threshold, config.hide_mode) {
+        | (Some(count), HideMode::Spoiler) => {
+            format!("The posts are marked as spoilers after {} report/s", count)
+        }
+        | (Some(count), HideMode::Delete) => {
+            format!("The posts are deleted after {} report/s", count)
+        }
+        | (None, _) => String::from("The reported posts are never hidden"),
+    };
+    [channel, threshold].join("\n")
+}
+
+async fn set_attachment_rules(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let pool = &state.datastore.pool;
+    let mut rules =
+        datastore::guild::get_attachment_rules(pool, guild_id).await?;
+    match subcommand.name.as_str() {
+        | "types" => {
+            let types = get_value(subcommand, "types")
+                .and_then(|value| value.as_str())
+                .unwrap_or_default();
+            let allowed_types = parse_attachment_types(types);
+            if allowed_types.is_empty() {
+                return Ok(format!("_{}_ are not valid file types", types));
+            }
+            rules.allowed_types = allowed_types;
+        }
+        | "max-size" => {
+            rules.max_size_mb = get_value(subcommand, "megabytes")
+                .and_then(|value| value.as_i64())
+                .and_then(|size| i32::try_from(size).ok())
+        }
+        | "max-dimension" => {
+            rules.max_dimension = get_value(subcommand, "pixels")
+                .and_then(|value| value.as_i64())
+                .and_then(|pixels| i32::try_from(pixels).ok())
+        }
+        | "max-count" => {
+            rules.max_count = get_value(subcommand, "count")
+                .and_then(|value| value.as_i64())
+                .and_then(|count| i32::try_from(count).ok())
+        }
+        | "strip-metadata" => {
+            rules.strip_metadata = get_value(subcommand, "enabled")
+                .and_then(|value| value.as_bool())
+                .ok_or_else(|| String::from("No value provided"))?
+        }
+        | _ => return Err(String::from("Unknown subcommand")),
+    }
+    datastore::guild::set_attachment_rules(pool, guild_id, &rules).await?;
+    Ok(describe_attachment_rules(&rules))
+}
+
+async fn show_attachment_rules(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let rules =
+        datastore::guild::get_attachment_rules(&state.datastore.pool, guild_id)
+            .await?;
+    Ok(describe_attachment_rules(&rules))
+}
+
+async fn toggle_spoiler_channel(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+    toggle: &str,
+) -> Result<String, String> {
+    let channel_id = get_value(subcommand, "channel")
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(ChannelId)
+        .ok_or_else(|| String::from("No channel provided"))?;
+    let forced = toggle == "add";
+    datastore::guild::set_spoiler_channel(
+        &state.datastore.pool,
+        guild_id,
+        channel_id,
+        forced,
+    )
+    .await?;
+    Ok(if forced {
+        format!("The memes are always sent as spoilers in <#{}>", channel_id)
+    } else {
+        format!("The spoilers are no longer forced in <#{}>", channel_id)
+    })
+}
+
+async fn list_spoiler_channels(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    let channels =
+        datastore::guild::get_spoiler_channels(&state.datastore.pool, guild_id)
+            .await?;
+    if channels.is_empty() {
+        return Ok(String::from("The spoilers are not forced in any channel"));
+    }
+    Ok(format!(
+        "The memes are always sent as spoilers in: {}",
+        channels
+            .iter()
+            .map(|channel_id| format!("<#{}>", channel_id))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+async fn add_template(
+    state: &State,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let name = get_value(subcommand, "name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No name provided"))?;
+    if !template::is_valid_name(name) {
+        return Ok(format!(
+            "_{}_ is not a valid name, it may only contain lowercase letters, \
+             digits, - and _",
+            name
+        ));
+    }
+    let boxes =
+        match get_value(subcommand, "boxes").and_then(|value| value.as_str()) {
+            | Some(boxes) => match template::parse_boxes(boxes) {
+                | Ok(boxes) => boxes,
+                | Err(explanation) => return Ok(explanation),
+            },
+            | None => template::default_boxes(),
+        };
+    let attachment = subcommand
+        .options
+        .iter()
+        .find(|option| option.name == "image")
+        .and_then(|option| match &option.resolved {
+            | Some(CommandDataOptionValue::Attachment(attachment)) => {
+                Some(attachment)
+            }
+            | _ => None,
+        })
+        .ok_or_else(|| String::from("No image provided"))?;
+    if !attachment
+        .content_type
+        .as_ref()
+        .is_some_and(|content_type| content_type.starts_with("image/"))
+    {
+        return Ok(format!("_{}_ is not an image", attachment.filename));
+    }
+    if attachment.size > MAX_TEMPLATE_SIZE {
+        return Ok(format!(
+            "_{}_ is too large, the templates may be at most {} MB",
+            attachment.filename,
+            MAX_TEMPLATE_SIZE / (1024 * 1024)
+        ));
+    }
+    let image = attachment.download().await.map_err(|err| err.to_string())?;
+    if let Err(why) = render::dimensions(&image) {
+        log::debug!("Failed to read a template: {}", why);
+        return Ok(format!(
+            "_{}_ could not be read, it may be of an unsupported format",
+            attachment.filename
+        ));
+    }
+    let template = Template {
+        name: name.to_string(),
+        image,
+        boxes,
+    };
+    datastore::template::add_template(
+        &state.datastore.pool,
+        guild_id,
+        &template,
+        command.user.id,
+    )
+    .await?;
+    log::debug!("Added template '{}' to guild {}", name, guild_id);
+    Ok(format!(
+        "The template _{}_ was added with {} text box/es",
+        name,
+        template.boxes.len()
+    ))
+}
+
+async fn remove_template(
+    state: &State,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    let name = get_value(subcommand, "name")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No name provided"))?;
+    let removed = datastore::template::remove_template(
+        &state.datastore.pool,
+        guild_id,
+        name,
+    )
+    .await?;
+    Ok(if removed {
+        format!("The template _{}_ was removed", name)
+    } else {
+        format!("This server has no template named _{}_", name)
+    })
+}
+
+async fn list_templates(
+    state: &State,
+    guild_id: GuildId,
+) -> Result<String, String> {
+    // NOTE: the names are listed in a single message, so that only as many
+    // as a message may hold are listed.
+    let names = datastore::template::get_template_names(
+        &state.datastore.pool,
+        guild_id,
+        "",
+        MAX_LISTED_TEMPLATES,
+    )
+    .await?;
+    let installed = render::template_names();
+    let mut lines = Vec::new();
+    if !names.is_empty() {
+        lines.push(format!("The server's templates: {}", names.join(", ")));
+    }
+    if !installed.is_empty() {
+        lines.push(format!(
+            "The templates installed with the bot: {}",
+            installed.join(", ")
+        ));
+    }
+    if lines.is_empty() {
+        return Ok(String::from("There are no templates yet"));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Parse the comma separated content types, a type without a subtype, such
+/// as `image`, allows all of its subtypes.
+fn parse_attachment_types(types: &str) -> Vec<String> {
+    types
+        .split(',')
+        .map(|content_type| content_type.trim().to_lowercase())
+        .filter(|content_type| {
+            !content_type.is_empty()
+                && content_type
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/.+-".contains(c))
+        })
+        .map(|content_type| {
+            if content_type.contains('/') {
+                content_type
+            } else {
+                format!("{}/", content_type)
+            }
+        })
+        .collect()
+}
+
+fn describe_attachment_rules(rules: &AttachmentRules) -> String {
+    let allowed_types = format!(
+        "These types of files may be posted: {}",
+        rules.allowed_types.join(", ")
+    );
+    let max_size = match rules.max_size_mb {
+        | Some(size) => format!("The files may be at most {} MB", size),
+        | None => String::from("The files may be of any size"),
+    };
+    let max_dimension = match rules.max_dimension {
+        | Some(pixels) => format!(
+            "The images and videos may be at most {} pixels wide and high",
+            pixels
+        ),
+        | None => {
+            String::from("The images and videos may be of any dimensions")
+        }
+    };
+    let max_count = match rules.max_count {
+        | Some(count) => {
+            format!("At most {} file/s may be posted at once", count)
+        }
+        | None => String::from("All the files of a meme may be posted at once"),
+    };
+    let strip_metadata = if rules.strip_metadata {
+        String::from(
+            "The metadata of the images, such as the location, is stripped",
+        )
+    } else {
+        String::from("The images are posted with their metadata")
+    };
+    [allowed_types, max_size, max_dimension, max_count, strip_metadata]
+        .join("\n")
+}
+
+fn describe_rule(rule: &Rule) -> String {
+    match rule {
+        | Rule::Score(score) => format!("for a score of at least {}", score),
+        | Rule::Rank(rank, period) => format!(
+            "for the top {} of the leaderboard ({})",
+            rank,
+            period.describe()
+        ),
+    }
+}
+
+fn get_role_id(subcommand: &CommandDataOption) -> Result<RoleId, String> {
+    get_value(subcommand, "role")
+        .and_then(|value| value.as_str())
+        .and_then(|id| id.parse::<u64>().ok())
+        .map(RoleId)
+        .ok_or_else(|| String::from("No role provided"))
+}
+
+/// Get the subcommand group and the subcommand of the
+/// provided interaction.
+fn get_subcommand(
+    command: &ApplicationCommandInteraction,
+) -> Option<(&CommandDataOption, &CommandDataOption)> {
+    let group = command.data.options.first()?;
+    let subcommand = group.options.first()?;
+    Some((group, subcommand))
+}
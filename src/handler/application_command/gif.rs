@@ -1,72 +1,76 @@
 use rand::{seq::SliceRandom, thread_rng};
 use reqwest::Response;
 use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
     model::prelude::{
-        command::{Command, CommandOptionType},
+        command::CommandOptionType,
         interaction::{
             application_command::ApplicationCommandInteraction, MessageFlags,
         },
-        ReactionType,
     },
     prelude::Context,
 };
 
-use crate::util;
+use super::SlashCommand;
+use crate::{
+    datastore::post::Marking,
+    handler::{post, state::State},
+};
 
-pub fn name() -> String { String::from("gif") }
-pub fn description() -> String { String::from("Send a gif") }
+fn name() -> String { String::from("gif") }
+fn description() -> String { String::from("Send a gif") }
 
 #[derive(serde::Deserialize)]
 struct GifResponse {
-    data: Vec<Gif>,
+    data: Vec<GifData>,
 }
 
 #[derive(serde::Deserialize)]
-struct Gif {
+struct GifData {
     url: String,
 }
 
-/// Register the hif command, it has a required
-/// string option, which should contain some keywords, so we may find a gif.
-pub async fn register(ctx: &Context) {
-    log::trace!("Registering '{}' command ...", name());
-    match Command::create_global_application_command(&ctx.http, |command| {
-        command.name(name()).description(description()).create_option(
-            |option| {
-                option
-                    .name("keywords")
-                    .description("The keywords to find the gif by")
-                    .kind(CommandOptionType::String)
-                    .required(true)
-            },
-        )
-    })
-    .await
-    {
-        | Ok(_) => log::info!("Registered '{}' slash command", name()),
-        | Err(why) => {
-            log::info!("Failed to register '{}' slash command: {}", name(), why)
-        }
+/// The gif slash command, it has a required string option,
+/// which should contain some keywords, so we may find a gif.
+pub struct Gif;
+
+#[async_trait]
+impl SlashCommand for Gif {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command.create_option(|option| {
+            option
+                .name("keywords")
+                .description("The keywords to find the gif by")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+    }
+
+    /// Respond to the provided command with a gif matching the keywords.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
     }
 }
 
-pub async fn handle_command(
+async fn handle_command(
     ctx: Context,
     command: ApplicationCommandInteraction,
-    giphy_token: &str,
+    state: &State,
 ) {
     log::trace!("Running '{}' command ...", name());
-    // NOTE: ensure the correct application command interaction
-    // has been passes to this function, as it depends on the
-    // command configuration specified in the `register` function.
-    if command.data.name != name() {
-        log::warn!(
-            "Received command interaction for '{}' but expected '{}'",
-            command.data.name,
-            name()
-        );
-        return;
-    }
     let keywords = match command
         .data
         .options
@@ -85,7 +89,7 @@ pub async fn handle_command(
             return;
         }
     };
-    let giphy_url = get_giphy_url(keywords.clone(), giphy_token);
+    let giphy_url = get_giphy_url(keywords.clone(), state.giphy_key.as_str());
 
     log::trace!("Fetching gifs for keywords: {}", keywords);
 
@@ -93,7 +97,7 @@ pub async fn handle_command(
         | Ok(url) => {
             log::trace!("Successfully fetched a gif: {}", url);
 
-            respond_with_gif_url(&ctx, &command, url.as_str()).await
+            respond_with_gif_url(&ctx, state, &command, url.as_str()).await
         }
         | Err(why) => {
             log::warn!("Failed to fetch a gif: {}", why);
@@ -104,12 +108,16 @@ pub async fn handle_command(
 
 async fn respond_with_gif_url(
     ctx: &Context,
+    state: &State,
     command: &ApplicationCommandInteraction,
     url: &str,
 ) {
+    let spoiler = post::is_spoiler_channel(state, command).await;
+    let content = post::spoiler_content(url, spoiler);
     match command
         .create_interaction_response(&ctx.http, |response| {
-            response.interaction_response_data(|message| message.content(url))
+            response
+                .interaction_response_data(|message| message.content(content))
         })
         .await
     {
@@ -118,22 +126,13 @@ async fn respond_with_gif_url(
             match command.get_interaction_response(&ctx.http).await {
                 | Err(_) => (),
                 | Ok(message) => {
-                    // NOTE: On successful meme gif, react to the sent
-                    // message with thumbs up and thumbs down.
-                    for reaction in
-                        vec![util::get_thumbs_up(), util::get_thumbs_down()]
-                            .iter()
-                    {
-                        if let Err(why) = message
-                            .react(
-                                &ctx.http,
-                                ReactionType::Unicode(reaction.to_string()),
-                            )
-                            .await
-                        {
-                            log::warn!("Error when reaction to meme: {why}");
-                        }
-                    }
+                    // NOTE: On successful gif response, publish the sent
+                    // message as a post that may be voted on.
+                    post::publish(ctx, state, command, &message, Marking {
+                        spoiler,
+                        cw: None,
+                    })
+                    .await;
                 }
             };
         }
@@ -1,53 +1,168 @@
-use deadpool_postgres::Pool;
 use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateComponents, CreateEmbed},
     model::prelude::{
-        command::Command,
+        command::CommandOptionType,
+        component::ButtonStyle,
         interaction::{
-            application_command::ApplicationCommandInteraction, MessageFlags,
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction,
+            InteractionResponseType, MessageFlags,
         },
         GuildId, UserId,
     },
     prelude::Context,
 };
 
-use crate::datastore;
+use super::{defer_response, get_option, reject_deferred, SlashCommand};
+use crate::{
+    datastore::{
+        self,
+        user::{Filter, Order, Period, RankedScore, Ranking, ScoreQuery},
+    },
+    handler::{member, state::State},
+};
 
-pub fn name() -> String { String::from("leaderboard") }
-pub fn description() -> String { String::from("Show the server's leaderboard") }
+fn name() -> String { String::from("leaderboard") }
+fn description() -> String { String::from("Show the server's leaderboard") }
 
-/// Register the leaderboard command, it has a required
-/// string option, which should contain a valid url.
-pub async fn register(ctx: &Context) {
-    log::trace!("Registering '{}' command ...", name());
-    match Command::create_global_application_command(&ctx.http, |command| {
-        command.name(name()).description(description())
-    })
-    .await
-    {
-        | Ok(_) => log::info!("Registered '{}' slash command", name()),
-        | Err(why) => {
-            log::info!("Failed to register '{}' slash command: {}", name(), why)
-        }
+/// The number of scores shown on a single page of the leaderboard.
+const PAGE_SIZE: u16 = 10;
+
+/// The leaderboard slash command, it has an optional `page` option,
+/// an optional `ranking` option, determining how the tied scores
+/// are ranked, an optional `order` option and an optional `include` option,
+/// determining which scores are listed. The scores may be limited to the
+/// votes received in a period, or between the `from` and `to` dates. The
+/// pages may be switched with the buttons below the leaderboard.
+pub struct Leaderboard;
+
+/// A single page of the leaderboard, as viewed by a user.
+struct Page {
+    page: i64,
+    pages: i64,
+    query: ScoreQuery,
+    period: Period,
+    content: String,
+}
+
+#[async_trait]
+impl SlashCommand for Leaderboard {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .create_option(|option| {
+                option
+                    .name("page")
+                    .description("The page of the leaderboard to show")
+                    .kind(CommandOptionType::Integer)
+                    .min_int_value(1)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("ranking")
+                    .description("How the tied scores are ranked")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("standard (1224)", "standard")
+                    .add_string_choice("dense (1223)", "dense")
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("order")
+                    .description("Whether to list the highest or lowest scores")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("highest first", Order::Top.name())
+                    .add_string_choice("lowest first", Order::Bottom.name())
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("include")
+                    .description("Which scores to include")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("positive", Filter::Positive.name())
+                    .add_string_choice(
+                        "zero and positive",
+                        Filter::NonNegative.name(),
+                    )
+                    .add_string_choice("all", Filter::All.name())
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("period")
+                    .description("Count only the votes received in the period")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("all time", Period::AllTime.name())
+                    .add_string_choice("today", Period::Today.name())
+                    .add_string_choice("this week", Period::Week.name())
+                    .add_string_choice("this month", Period::Month.name())
+                    .add_string_choice("last 7 days", Period::Last7Days.name())
+                    .add_string_choice(
+                        "last 30 days",
+                        Period::Last30Days.name(),
+                    )
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("from")
+                    .description(
+                        "Count only the votes received from the date \
+                         (YYYY-MM-DD)",
+                    )
+                    .kind(CommandOptionType::String)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("to")
+                    .description(
+                        "Count only the votes received until the date \
+                         (YYYY-MM-DD)",
+                    )
+                    .kind(CommandOptionType::String)
+                    .required(false)
+            })
+    }
+
+    /// Respond to the provided command with a page of the server's
+    /// leaderboard.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+
+    /// Switch the leaderboard to the page selected with the
+    /// Previous or Next button.
+    async fn handle_component(
+        &self,
+        ctx: Context,
+        component: MessageComponentInteraction,
+        state: &State,
+    ) {
+        handle_page_button(ctx, component, state).await
     }
 }
 
-pub async fn handle_command(
+async fn handle_command(
     ctx: Context,
     command: ApplicationCommandInteraction,
-    pool: &Pool,
+    state: &State,
 ) {
     log::trace!("Running '{}' command ...", name());
-    // NOTE: ensure the correct application command interaction
-    // has been passes to this function, as it depends on the
-    // command configuration specified in the `register` function.
-    if command.data.name != name() {
-        log::warn!(
-            "Received command interaction for '{}' but expected '{}'",
-            command.data.name,
-            name()
-        );
-        return;
-    }
     let guild_id = match command.guild_id {
         | Some(id) => id,
         | None => {
@@ -55,79 +170,374 @@ pub async fn handle_command(
             return;
         }
     };
+    let page = get_option(&command, "page")
+        .and_then(|value| value.as_i64())
+        .unwrap_or(1);
+    let query = ScoreQuery {
+        ranking: get_option(&command, "ranking")
+            .and_then(|value| value.as_str())
+            .and_then(Ranking::from_name)
+            .unwrap_or(Ranking::Standard),
+        order: get_option(&command, "order")
+            .and_then(|value| value.as_str())
+            .and_then(Order::from_name)
+            .unwrap_or(Order::Top),
+        filter: get_option(&command, "include")
+            .and_then(|value| value.as_str())
+            .and_then(Filter::from_name)
+            .unwrap_or(Filter::Positive),
+        window: None,
+        decay: None,
+    };
+    let period = match get_period(&command) {
+        | Some(period) => period,
+        | None => {
+            respond_ephemeral(
+                ctx,
+                command,
+                "Provide both the `from` and `to` dates, as valid dates \
+                 formatted as YYYY-MM-DD, with `from` not after `to`",
+            )
+            .await;
+            return;
+        }
+    };
 
-    match datastore::user::get_scores(pool, guild_id, 20).await {
+    defer_response(&ctx, &command).await;
+    match get_page(
+        &ctx,
+        state,
+        guild_id,
+        command.user.id,
+        page,
+        query,
+        period.clone(),
+    )
+    .await
+    {
         | Err(why) => {
             log::trace!("Error when fetching scores: {}", why);
-            respond_no_results(ctx, command).await;
+            respond_no_results(ctx, command, query.filter, &period).await;
+        }
+        | Ok(None) => {
+            respond_no_results(ctx, command, query.filter, &period).await
+        }
+        | Ok(Some(page)) => {
+            log::trace!("Responding to a command with a leaderboard");
+            match command
+                .edit_original_interaction_response(&ctx.http, |response| {
+                    response.set_embed(page.embed()).components(|components| {
+                        *components = page.components();
+                        components
+                    })
+                })
+                .await
+            {
+                | Err(why) => {
+                    log::warn!("Failed to respond with a leaderboard: {}", why)
+                }
+                | Ok(_) => {
+                    log::trace!("Successfully responded with a leaderboard")
+                }
+            };
+        }
+    }
+}
+
+async fn handle_page_button(
+    ctx: Context,
+    component: MessageComponentInteraction,
+    state: &State,
+) {
+    let guild_id = match component.guild_id {
+        | Some(id) => id,
+        | None => return,
+    };
+    let (page, query, period) = match parse_custom_id(&component.data.custom_id)
+    {
+        | Some(v) => v,
+        | None => {
+            log::warn!(
+                "Received invalid leaderboard button: {}",
+                component.data.custom_id
+            );
+            return;
+        }
+    };
+    let page = match get_page(
+        &ctx,
+        state,
+        guild_id,
+        component.user.id,
+        page,
+        query,
+        period,
+    )
+    .await
+    {
+        | Ok(Some(page)) => page,
+        | Ok(None) => {
+            respond_component_error(
+                ctx,
+                component,
+                "No scores were found in this server anymore",
+            )
+            .await;
+            return;
         }
-        | Ok(scores) => {
-            log::trace!("Fetched {} scores", scores.len());
-            if scores.len() == 0 {
-                respond_no_results(ctx, command).await;
-                return;
-            }
-            respond_with_scores(ctx, guild_id, command, scores).await;
+        | Err(why) => {
+            log::warn!("Error when fetching scores: {}", why);
+            respond_component_error(
+                ctx,
+                component,
+                "The leaderboard could not be fetched, try again later",
+            )
+            .await;
+            return;
         }
+    };
+    if let Err(why) = component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|message| {
+                    message
+                        .set_embed(page.embed())
+                        .set_components(page.components())
+                })
+        })
+        .await
+    {
+        log::warn!("Failed to switch the leaderboard page: {}", why);
     }
 }
 
 async fn respond_no_results(
     ctx: Context,
     command: ApplicationCommandInteraction,
+    filter: Filter,
+    period: &Period,
 ) {
     log::trace!("Responding to a command with an empty leaderboard");
+    let content = match filter {
+        | Filter::Positive => "No positive scores were found in this server",
+        | Filter::NonNegative => {
+            "No zero or positive scores were found in this server"
+        }
+        | Filter::All => "No scores were found in this server",
+    };
+    let content = match period {
+        | Period::AllTime => content.to_string(),
+        | _ => format!("{} ({})", content, period.describe()),
+    };
+    reject_deferred(&ctx, &command, content).await;
+}
+
+async fn respond_ephemeral(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    content: &str,
+) {
     match command
         .create_interaction_response(&ctx.http, |response| {
             response.interaction_response_data(|message| {
-                message
-                    .flags(MessageFlags::EPHEMERAL)
-                    .content("No positive scores were found in this server")
+                message.flags(MessageFlags::EPHEMERAL).content(content)
             })
         })
         .await
     {
-        | Ok(_) => log::trace!("Successfully responded with empty leaderboard"),
+        | Ok(_) => log::trace!("Successfully responded to leaderboard command"),
         | Err(why) => {
-            log::warn!("Failed to respond with an empty leaderboard: {}", why)
+            log::warn!("Failed to respond to leaderboard command: {}", why)
         }
     };
 }
 
-async fn respond_with_scores(
+/// Answer a page button that could not be switched with a message only
+/// visible to the user that pressed it, so that the button does not fail.
+async fn respond_component_error(
     ctx: Context,
-    guild_id: GuildId,
-    command: ApplicationCommandInteraction,
-    scores: Vec<(UserId, i64)>,
+    component: MessageComponentInteraction,
+    content: &str,
 ) {
-    let mut content: Vec<String> = Vec::new();
-    for (id, score) in scores.iter() {
-        match guild_id.member(&ctx.http, id).await {
-            | Ok(member) => {
-                let name = member.display_name();
-                content.push(format!("**_{}_**: {}", name, score));
-            }
-            | _ => (),
-        }
-    }
-    log::trace!("Responding to a command with a leaderboard");
-    let content = content.join("\n");
-    let footer = format!("Showing top {} result/s", scores.len());
-    match command
+    if let Err(why) = component
         .create_interaction_response(&ctx.http, |response| {
             response.interaction_response_data(|message| {
-                message.embed(|e| {
-                    e.footer(|f| f.text(footer))
-                        .description(content)
-                        .title("Leaderboard")
-                })
+                message.flags(MessageFlags::EPHEMERAL).content(content)
             })
         })
         .await
     {
-        | Err(why) => {
-            log::warn!("Failed to respond with a leaderboard: {}", why)
+        log::warn!("Failed to respond to a leaderboard button: {}", why);
+    }
+}
+
+/// Build the provided page of the guild's leaderboard, as viewed by the
+/// provided user. The viewer's row is highlighted, or appended below the
+/// page when they are not on it. Returns `None` when there are no scores.
+/// The query's window is set from the provided period, and its decay from
+/// the guild's config.
+async fn get_page(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    viewer_id: UserId,
+    page: i64,
+    mut query: ScoreQuery,
+    period: Period,
+) -> Result<Option<Page>, String> {
+    let pool = &state.datastore.pool;
+    query.window =
+        datastore::guild::get_window(pool, guild_id, &period).await?;
+    query.decay = datastore::guild::get_decay(pool, guild_id).await?;
+    let count = datastore::user::count_scores(pool, guild_id, &query).await?;
+    if count == 0 {
+        return Ok(None);
+    }
+    let pages = (count + i64::from(PAGE_SIZE) - 1) / i64::from(PAGE_SIZE);
+    let page = page.clamp(1, pages);
+    let scores = datastore::user::get_scores(
+        pool,
+        guild_id,
+        &query,
+        PAGE_SIZE,
+        (page - 1) * i64::from(PAGE_SIZE),
+    )
+    .await?;
+    log::trace!("Fetched {} scores", scores.len());
+
+    let ids: Vec<UserId> = scores.iter().map(|score| score.user_id).collect();
+    let names = member::display_names(ctx, state, guild_id, &ids).await;
+    let mut rows: Vec<String> = scores
+        .iter()
+        .zip(names.iter())
+        .map(|(score, name)| format_row(score, name, viewer_id))
+        .collect();
+    if !ids.contains(&viewer_id) {
+        if let Some(score) =
+            datastore::user::get_rank(pool, viewer_id, guild_id, &query).await?
+        {
+            let name =
+                member::display_name(ctx, state, guild_id, viewer_id).await;
+            rows.push(String::from("…"));
+            rows.push(format_row(&score, &name.ok(), viewer_id));
         }
-        | Ok(_) => log::trace!("Successfully responded with a leaderboard"),
+    }
+    Ok(Some(Page {
+        page,
+        pages,
+        query,
+        period,
+        content: rows.join("\n"),
+    }))
+}
+
+/// Format a single row of the leaderboard. Members that could not be
+/// fetched are shown as mentions, so that the rows are never skipped.
+fn format_row(
+    score: &RankedScore,
+    name: &Option<String>,
+    viewer_id: UserId,
+) -> String {
+    let name = match name {
+        | Some(name) => format!("**_{}_**", name),
+        | None => format!("<@{}>", score.user_id),
+    };
+    let row = format!("`#{}` {}: {}", score.rank, name, score.score);
+    if score.user_id == viewer_id {
+        return format!("{}  ⬅️", row);
+    }
+    row
+}
+
+/// Get the period provided with the command's options. The `from` and
+/// `to` dates take precedence over the `period` option. `None` is returned
+/// when the dates are invalid.
+fn get_period(command: &ApplicationCommandInteraction) -> Option<Period> {
+    let from = get_option(command, "from").and_then(|value| value.as_str());
+    let to = get_option(command, "to").and_then(|value| value.as_str());
+    match (from, to) {
+        | (None, None) => Some(
+            get_option(command, "period")
+                .and_then(|value| value.as_str())
+                .and_then(Period::from_name)
+                .unwrap_or(Period::AllTime),
+        ),
+        | (Some(from), Some(to)) => Period::range(from, to),
+        | _ => None,
+    }
+}
+
+fn custom_id(page: i64, query: &ScoreQuery, period: &Period) -> String {
+    format!(
+        "{}:{}:{}:{}:{}:{}",
+        name(),
+        page,
+        query.ranking.name(),
+        query.order.name(),
+        query.filter.name(),
+        period.id()
+    )
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(i64, ScoreQuery, Period)> {
+    let mut parts = custom_id.split(':').skip(1);
+    let page = parts.next()?.parse::<i64>().ok()?;
+    let query = ScoreQuery {
+        ranking: Ranking::from_name(parts.next()?)?,
+        order: Order::from_name(parts.next()?)?,
+        filter: Filter::from_name(parts.next()?)?,
+        window: None,
+        decay: None,
     };
+    let period = Period::from_id(parts.next()?)?;
+    Some((page, query, period))
+}
+
+impl Page {
+    fn embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::default();
+        let title = match self.query.order {
+            | Order::Top => String::from("Leaderboard"),
+            | Order::Bottom => String::from("Leaderboard (lowest first)"),
+        };
+        let title = match self.period {
+            | Period::AllTime => title,
+            | _ => format!("{}: {}", title, self.period.describe()),
+        };
+        embed
+            .title(title)
+            .description(&self.content)
+            .footer(|f| f.text(format!("Page {}/{}", self.page, self.pages)));
+        embed
+    }
+
+    fn components(&self) -> CreateComponents {
+        let mut components = CreateComponents::default();
+        components.create_action_row(|row| {
+            row.create_button(|button| {
+                button
+                    .custom_id(custom_id(
+                        self.page - 1,
+                        &self.query,
+                        &self.period,
+                    ))
+                    .label("Previous")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(self.page <= 1)
+            })
+            .create_button(|button| {
+                button
+                    .custom_id(custom_id(
+                        self.page + 1,
+                        &self.query,
+                        &self.period,
+                    ))
+                    .label("Next")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(self.page >= self.pages)
+            })
+        });
+        components
+    }
 }
@@ -1,67 +1,65 @@
 use serenity::{
-    model::{
-        application::command::Command,
-        prelude::{
-            command::CommandOptionType,
-            interaction::{
-                application_command::ApplicationCommandInteraction,
-                MessageFlags,
-            },
-            ReactionType,
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::CommandOptionType,
+        interaction::{
+            application_command::ApplicationCommandInteraction, MessageFlags,
         },
     },
     prelude::Context,
 };
 
-use crate::util;
+use super::SlashCommand;
+use crate::{
+    datastore::post::Marking,
+    handler::{post, state::State},
+    util,
+};
 
-pub fn name() -> String { String::from("link") }
-pub fn description() -> String { String::from("Send a link") }
+fn name() -> String { String::from("link") }
+fn description() -> String { String::from("Send a link") }
 
-/// Register the link command, it has a required
+/// The link slash command, it has a required
 /// string option, which should contain a valid url.
-pub async fn register(ctx: &Context) {
-    log::trace!("Registering '{}' command ...", name());
-    match Command::create_global_application_command(&ctx.http, |command| {
-        command.name(name()).description(description()).create_option(
-            |option| {
-                option
-                    .name("link")
-                    .description("The link to be sent")
-                    .kind(CommandOptionType::String)
-                    .required(true)
-            },
-        )
-    })
-    .await
-    {
-        | Ok(_) => log::info!("Registered '{}' slash command", name()),
-        | Err(why) => {
-            log::info!("Failed to register '{}' slash command: {}", name(), why)
-        }
+pub struct Link;
+
+#[async_trait]
+impl SlashCommand for Link {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command.create_option(|option| {
+            option
+                .name("link")
+                .description("The link to be sent")
+                .kind(CommandOptionType::String)
+                .required(true)
+        })
+    }
+
+    /// Respond to the provided command with the provided link.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
     }
 }
 
-/// Handle the link application command. This expects the command name to match
-/// the value returned from the `name()` function. Responds to the
-/// provided command with the provided attachment and content, also
-/// mentions the user who used the command.
-pub async fn handle_command(
+async fn handle_command(
     ctx: Context,
     command: ApplicationCommandInteraction,
+    state: &State,
 ) {
     log::trace!("Running '{}' command ...", name());
-    // NOTE: ensure the correct application command interaction
-    // has been passes to this function, as it depends on the
-    // command configuration specified in the `register` function.
-    if command.data.name != name() {
-        log::warn!(
-            "Received command interaction for '{}' but expected '{}'",
-            command.data.name,
-            name()
-        );
-        return;
-    }
     let url = match command
         .data
         .options
@@ -85,7 +83,7 @@ pub async fn handle_command(
         respond_to_invalid_url(&ctx, &command, url.as_str()).await;
         return;
     }
-    respond_to_valid_url(&ctx, &command, url.as_str()).await;
+    respond_to_valid_url(&ctx, state, &command, url.as_str()).await;
 }
 
 async fn respond_to_invalid_url(
@@ -112,12 +110,16 @@ async fn respond_to_invalid_url(
 
 async fn respond_to_valid_url(
     ctx: &Context,
+    state: &State,
     command: &ApplicationCommandInteraction,
     url: &str,
 ) {
+    let spoiler = post::is_spoiler_channel(state, command).await;
+    let content = post::spoiler_content(url, spoiler);
     match command
         .create_interaction_response(&ctx.http, |response| {
-            response.interaction_response_data(|message| message.content(url))
+            response
+                .interaction_response_data(|message| message.content(content))
         })
         .await
     {
@@ -126,22 +128,13 @@ async fn respond_to_valid_url(
             match command.get_interaction_response(&ctx.http).await {
                 | Err(_) => (),
                 | Ok(message) => {
-                    // NOTE: On successful link response, react to the sent
-                    // message with thumbs up and thumbs down.
-                    for reaction in
-                        vec![util::get_thumbs_up(), util::get_thumbs_down()]
-                            .iter()
-                    {
-                        if let Err(why) = message
-                            .react(
-                                &ctx.http,
-                                ReactionType::Unicode(reaction.to_string()),
-                            )
-                            .await
-                        {
-                            log::warn!("Error when reaction to meme: {why}");
-                        }
-                    }
+                    // NOTE: On successful link response, publish the sent
+                    // message as a post that may be voted on.
+                    post::publish(ctx, state, command, &message, Marking {
+                        spoiler,
+                        cw: None,
+                    })
+                    .await;
                 }
             };
         }
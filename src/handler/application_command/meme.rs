@@ -1,182 +1,196 @@
 use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
     model::{
-        application::command::Command,
-        channel::Message,
+        channel::{Attachment, Message},
         prelude::{
             command::CommandOptionType,
-            interaction::{
-                application_command::{
-                    ApplicationCommandInteraction, CommandDataOptionValue,
-                },
-                InteractionResponseType,
+            interaction::application_command::{
+                ApplicationCommandInteraction, CommandDataOptionValue,
             },
-            ReactionType,
         },
     },
     prelude::Context,
 };
 
-use crate::util;
-
-pub fn name() -> String { String::from("meme") }
-pub fn description() -> String { String::from("Send a meme") }
-
-/// Register the meme slash command. The command has the name
-/// and the description matching the values returned by `name()` and
-/// `description()`. It has multiple options, one for the text content of the
-/// meme, and 4 for the attachments. At least one attachment should be provided.
-pub async fn register(ctx: &Context) {
-    log::trace!("Registering '{}' command ...", name());
-    match Command::create_global_application_command(
-        &ctx.http,
-        |mut command| {
-            for i in 0..4 {
-                let attachment = if i > 0 {
-                    format!("attachment{}", i)
-                } else {
-                    String::from("attachment")
-                };
-                command = command.create_option(|option| {
-                    option
-                        .name(attachment)
-                        .description("A file containing a meme")
-                        .kind(CommandOptionType::Attachment)
-                        .required(i == 0)
-                })
-            }
-            command = command
-                .name(name())
-                .description(description())
-                .create_option(|option| {
-                    option
-                        .name("content")
-                        .description("The optional content of the meme")
-                        .kind(CommandOptionType::String)
-                        .required(false)
-                });
-            command
-        },
-    )
-    .await
-    {
-        | Ok(_) => log::info!("Registered '{}' slash command", name()),
-        | Err(why) => {
-            log::info!("Failed to register '{}' slash command: {}", name(), why)
+use super::{defer_response, get_option, reject_deferred, SlashCommand};
+use crate::{
+    datastore::{guild::AttachmentRules, post::Marking},
+    handler::{post, state::State},
+};
+
+fn name() -> String { String::from("meme") }
+fn description() -> String { String::from("Send a meme") }
+
+/// The longest content warning of a meme.
+const MAX_CW_LENGTH: u16 = 100;
+
+/// The meme slash command. It has multiple options, one for the text content
+/// of the meme, 4 for the attachments, and two used to send the meme as a
+/// spoiler, with an optional content warning. At least one attachment should
+/// be provided.
+pub struct Meme;
+
+#[async_trait]
+impl SlashCommand for Meme {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        for i in 0..4 {
+            let attachment = if i > 0 {
+                format!("attachment{}", i)
+            } else {
+                String::from("attachment")
+            };
+            command.create_option(|option| {
+                option
+                    .name(attachment)
+                    .description("A file containing a meme")
+                    .kind(CommandOptionType::Attachment)
+                    .required(i == 0)
+            });
         }
+        command
+            .create_option(|option| {
+                option
+                    .name("content")
+                    .description("The optional content of the meme")
+                    .kind(CommandOptionType::String)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("spoiler")
+                    .description("Whether the meme is sent as a spoiler")
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("cw")
+                    .description(
+                        "A content warning shown above the meme, which is \
+                         sent as a spoiler",
+                    )
+                    .kind(CommandOptionType::String)
+                    .max_length(MAX_CW_LENGTH)
+                    .required(false)
+            })
+    }
+
+    /// Respond to the provided command with the provided attachment and
+    /// content, also mentions the user who used the command.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
     }
 }
 
-/// Handle the meme application command. This expects the command name to match
-/// the value returned from the `name()` function. Responds to the
-/// provided command with the provided attachment and content, also
-/// mentions the user who used the command.
-pub async fn handle_command(
+async fn handle_command(
     ctx: Context,
     command: ApplicationCommandInteraction,
+    state: &State,
 ) {
     log::trace!("Running '{}' command ...", name());
-    // NOTE: ensure the correct application command interaction
-    // has been passes to this function, as it depends on the
-    // command configuration specified in the `register` function.
-    if command.data.name != name() {
-        log::warn!(
-            "Received command interaction for '{}' but expected '{}'",
-            command.data.name,
-            name()
-        );
-        return;
-    }
 
-    // NOTE: mention the user who sent the meme
-    let content = command
-        .data
-        .options
-        .iter()
-        .find(|option| option.kind == CommandOptionType::String)
-        .map_or(String::new(), |content| {
-            content
-                .value
-                .as_ref()
-                .map_or(String::new(), |value| format!("{}", value.to_string()))
-        });
+    let content = get_option(&command, "content")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+    let cw = get_option(&command, "cw").and_then(|value| value.as_str());
+    let spoiler = cw.is_some()
+        || get_option(&command, "spoiler")
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
 
-    defer_meme_response(&ctx, &command).await;
+    defer_response(&ctx, &command).await;
+
+    let spoiler = spoiler || post::is_spoiler_channel(state, &command).await;
+    let content = post::compose_content(content, cw, spoiler);
+
+    let attachments = get_attachments(&command);
+    let rules = post::attachment_rules(state, command.guild_id).await;
+    if let Some(violation) = find_violation(&attachments, &rules) {
+        log::debug!("Rejected meme attachments: {}", violation);
+        reject_deferred(&ctx, &command, violation).await;
+        return;
+    }
 
-    match respond_with_meme(&ctx, &command, content).await {
+    match respond_with_meme(&ctx, &command, content, spoiler, &rules).await {
         | Err(why) => {
             log::info!("Err when responding with meme: {:?}", why);
-            remove_original_response_on_error(&ctx, &command).await;
+            reject_deferred(
+                &ctx,
+                &command,
+                format!("The meme could not be sent: {}", why),
+            )
+            .await;
         }
         | Ok(message) => {
-            // NOTE: On successful meme response, react to the sent
-            // message with thumbs up and thumbs down.
-            for reaction in
-                vec![util::get_thumbs_up(), util::get_thumbs_down()].iter()
-            {
-                if let Err(why) = message
-                    .react(
-                        &ctx.http,
-                        ReactionType::Unicode(reaction.to_string()),
-                    )
-                    .await
-                {
-                    log::warn!("Error when reaction to meme: {:?}", why);
-                }
-            }
+            // NOTE: On successful meme response, publish the sent
+            // message as a post that may be voted on.
+            post::publish(&ctx, state, &command, &message, Marking {
+                spoiler,
+                cw,
+            })
+            .await;
         }
     }
 }
 
-/// Defer the response to the meme application command,
-/// so the interaction does not timeout before the response
-/// is sent. This is neccessary as it may take a long time
-/// to uploead videos or such attachments.
-async fn defer_meme_response(
-    ctx: &Context,
-    command: &ApplicationCommandInteraction,
-) {
-    log::trace!("Deffering '{}' slash command", name());
-
-    if let Err(why) = command
-        .create_interaction_response(&ctx.http, |response| {
-            response
-                .kind(InteractionResponseType::DeferredChannelMessageWithSource)
-        })
-        .await
-    {
-        log::warn!("Failed to defer an interaction: {:?}", why);
-    };
-}
-
 /// Create a followup message to the meme slash command,
 /// responding with the attachment and content provided in
-/// the command. If this fails, it tries to delete the original
-/// response (created when deferring the command).
+/// the command. The spoiler attachments are downloaded and
+/// uploaded again, as discord only shows the attachments whose
+/// names start with `SPOILER_` as spoilers, and so are the images
+/// exceeding the guild's upload limit, encoded again to fit, and the images
+/// whose metadata the guild's rules require to strip.
 async fn respond_with_meme(
     ctx: &Context,
     command: &ApplicationCommandInteraction,
     content: String,
+    spoiler: bool,
+    rules: &AttachmentRules,
 ) -> Result<Message, String> {
     log::trace!(
         "Responding to '{}' slash command with the provided attachment",
         name()
     );
 
-    let mut attachments_urls: Vec<String> = Vec::new();
-
-    for option in command.data.options.iter() {
-        match &option.resolved {
-            | Some(CommandDataOptionValue::Attachment(attachment)) => {
-                attachments_urls.push(attachment.url.clone())
-            }
-            | _ => (),
-        }
+    let limit = post::upload_limit(ctx, command.guild_id).await;
+    let mut files = Vec::new();
+    let mut stripped = 0;
+    for attachment in get_attachments(command) {
+        let (file, is_stripped) = post::prepare_upload(
+            attachment,
+            limit,
+            spoiler,
+            rules.strip_metadata,
+        )
+        .await?;
+        files.push(file);
+        stripped += usize::from(is_stripped);
+    }
+    if stripped > 0 {
+        log::info!(
+            "Stripped the metadata of {} file/s posted by {}",
+            stripped,
+            command.user.id
+        );
     }
     command
         .create_followup_message(&ctx.http, |mut message| {
             message = message.content(&content);
-            for url in attachments_urls.iter() {
-                message = message.add_file(url.as_str());
+            for file in files {
+                message = message.add_file(file);
             }
             message
         })
@@ -184,18 +198,82 @@ async fn respond_with_meme(
         .map_err(|err| err.to_string())
 }
 
-async fn remove_original_response_on_error(
-    ctx: &Context,
+/// Get the attachments provided in the command's options.
+fn get_attachments(
     command: &ApplicationCommandInteraction,
-) {
-    log::trace!("Removing origin response to '{}' slash command", name());
-
-    if let Err(why) =
-        command.delete_original_interaction_response(&ctx.http).await
-    {
-        log::warn!(
-            "Error when deleting original interaction response: {:?}",
-            why
-        );
-    };
+) -> Vec<&Attachment> {
+    command
+        .data
+        .options
+        .iter()
+        .filter_map(|option| match &option.resolved {
+            | Some(CommandDataOptionValue::Attachment(attachment)) => {
+                Some(attachment)
+            }
+            | _ => None,
+        })
+        .collect()
+}
+
+/// Check the provided attachments against the guild's rules. Returns the
+/// explanation of the first rule that is broken, if any.
+fn find_violation(
+    attachments: &[&Attachment],
+    rules: &AttachmentRules,
+) -> Option<String> {
+    if let Some(max_count) = rules.max_count {
+        if attachments.len() > max_count as usize {
+            return Some(format!(
+                "At most {} file/s may be posted at once in this server",
+                max_count
+            ));
+        }
+    }
+    for attachment in attachments.iter() {
+        let content_type = match &attachment.content_type {
+            | Some(content_type) => content_type,
+            | None => {
+                return Some(format!(
+                    "_{}_ has an unknown file type",
+                    attachment.filename
+                ))
+            }
+        };
+        if !rules
+            .allowed_types
+            .iter()
+            .any(|allowed| content_type.starts_with(allowed.as_str()))
+        {
+            return Some(format!(
+                "_{}_ is a {} file, only these types may be posted in this \
+                 server: {}",
+                attachment.filename,
+                content_type,
+                rules.allowed_types.join(", ")
+            ));
+        }
+        if let Some(max_size_mb) = rules.max_size_mb {
+            if attachment.size > max_size_mb as u64 * 1024 * 1024 {
+                return Some(format!(
+                    "_{}_ is {:.1} MB, the files posted in this server may be \
+                     at most {} MB",
+                    attachment.filename,
+                    attachment.size as f64 / (1024.0 * 1024.0),
+                    max_size_mb
+                ));
+            }
+        }
+        if let (Some(max_dimension), Some((width, height))) =
+            (rules.max_dimension, attachment.dimensions())
+        {
+            if width.max(height) > max_dimension as u64 {
+                return Some(format!(
+                    "_{}_ is {}×{} pixels, the files posted in this server \
+                     may be at most {} pixels wide and high",
+                    attachment.filename, width, height, max_dimension
+                ));
+            }
+        }
+    }
+    None
 }
@@ -0,0 +1,252 @@
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::CommandOptionType,
+        interaction::{
+            application_command::{
+                ApplicationCommandInteraction, CommandDataOption,
+            },
+            MessageFlags,
+        },
+        GuildId,
+    },
+    prelude::Context,
+};
+
+use super::{get_value, parse_post_id, SlashCommand};
+use crate::{
+    datastore::{self, post::Post},
+    handler::{post, reward, state::State},
+};
+
+fn name() -> String { String::from("mypost") }
+fn description() -> String { String::from("Edit or delete one of your posts") }
+
+/// The longest caption of a post, the length of a discord message.
+const MAX_CAPTION_LENGTH: u16 = 2000;
+
+/// The mypost slash command. It has an `edit` subcommand used to change
+/// the caption of a meme, and a `delete` subcommand used to remove a post
+/// and reverse the votes it received, until its voting is closed. Both only
+/// apply to the posts of the command's user, their latest one by default.
+pub struct MyPost;
+
+#[async_trait]
+impl SlashCommand for MyPost {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .dm_permission(false)
+            .create_option(|option| {
+                option
+                    .name("edit")
+                    .description("Edit the caption of one of your memes")
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("caption")
+                            .description("The new caption of the meme")
+                            .kind(CommandOptionType::String)
+                            .max_length(MAX_CAPTION_LENGTH)
+                            .required(true)
+                    })
+                    .create_sub_option(|option| {
+                        option
+                            .name("post")
+                            .description(
+                                "The link to the meme, or its id, your latest \
+                                 post by default",
+                            )
+                            .kind(CommandOptionType::String)
+                            .required(false)
+                    })
+            })
+            .create_option(|option| {
+                option
+                    .name("delete")
+                    .description(
+                        "Delete one of your posts, removing its votes as well",
+                    )
+                    .kind(CommandOptionType::SubCommand)
+                    .create_sub_option(|option| {
+                        option
+                            .name("post")
+                            .description(
+                                "The link to the post, or its id, your latest \
+                                 post by default",
+                            )
+                            .kind(CommandOptionType::String)
+                            .required(false)
+                    })
+            })
+    }
+
+    /// Edit or delete the post of the command's user, and respond with
+    /// the outcome, only visible to the user.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    let subcommand = match command.data.options.first() {
+        | Some(subcommand) => subcommand,
+        | None => {
+            log::warn!("Received mypost command with no subcommand");
+            return;
+        }
+    };
+    let content = match get_post(state, &command, guild_id, subcommand).await {
+        | Ok(Ok(post)) => match subcommand.name.as_str() {
+            | "edit" => edit_post(&ctx, &post, subcommand).await,
+            | "delete" => delete_post(&ctx, state, &post).await,
+            | _ => Err(String::from("Unknown subcommand")),
+        },
+        | Ok(Err(content)) => Ok(content),
+        | Err(why) => Err(why),
+    };
+    let content = content.unwrap_or_else(|why| {
+        log::warn!("Failed to update a post: {}", why);
+        String::from("Failed to update the post")
+    });
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response.interaction_response_data(|message| {
+                message.content(content).flags(MessageFlags::EPHEMERAL)
+            })
+        })
+        .await
+    {
+        log::warn!("Failed to respond to mypost command: {}", why);
+    }
+}
+
+/// Get the post provided as the subcommand's option, or the latest post of
+/// the command's user. When the post may not be updated by the user, the
+/// response explaining why is returned instead.
+async fn get_post(
+    state: &State,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    subcommand: &CommandDataOption,
+) -> Result<Result<Post, String>, String> {
+    let pool = &state.datastore.pool;
+    let post = match get_value(subcommand, "post")
+        .and_then(|value| value.as_str())
+    {
+        | Some(post) => match parse_post_id(post) {
+            | Some(post_id) => datastore::post::get_post(pool, post_id).await?,
+            | None => {
+                return Ok(Err(format!("_{}_ is not a valid post", post)))
+            }
+        },
+        | None => {
+            datastore::post::get_latest_post(pool, guild_id, command.user.id)
+                .await?
+        }
+    };
+    Ok(match post {
+        | Some(post)
+            if post.guild_id == guild_id
+                && post.author_id == command.user.id =>
+        {
+            Ok(post)
+        }
+        | _ => Err(String::from("You have no such post in this server")),
+    })
+}
+
+async fn edit_post(
+    ctx: &Context,
+    post: &Post,
+    subcommand: &CommandDataOption,
+) -> Result<String, String> {
+    if post.kind != "meme" {
+        return Ok(String::from(
+            "Only the captions of the memes may be edited",
+        ));
+    }
+    let caption = get_value(subcommand, "caption")
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| String::from("No caption provided"))?;
+    let message = post
+        .channel_id
+        .message(ctx, post.id)
+        .await
+        .map_err(|err| err.to_string())?;
+    // NOTE: the post is kept as a spoiler when its attachments still are,
+    // as it may have been hidden since it was sent.
+    let spoiler = post.spoiler
+        || message
+            .attachments
+            .iter()
+            .any(|attachment| post::is_spoiler_name(&attachment.filename));
+    let mut content =
+        post::compose_content(caption, post.cw.as_deref(), spoiler);
+    if let Some(tally) = post::final_tally(&message.content) {
+        content = if content.is_empty() {
+            tally.to_string()
+        } else {
+            format!("{}\n\n{}", content, tally)
+        };
+    }
+    post.channel_id
+        .edit_message(ctx, post.id, |message| message.content(content))
+        .await
+        .map_err(|err| err.to_string())?;
+    log::debug!("User {} edited post {}", post.author_id, post.id);
+    Ok(format!(
+        "The caption of your [meme]({}) was edited",
+        post.id.link(post.channel_id, Some(post.guild_id))
+    ))
+}
+
+async fn delete_post(
+    ctx: &Context,
+    state: &State,
+    post: &Post,
+) -> Result<String, String> {
+    let config = datastore::guild::get_voting_config(
+        &state.datastore.pool,
+        post.guild_id,
+    )
+    .await?;
+    // NOTE: the scores of the closed posts are final, so they may no longer
+    // be deleted along with their votes.
+    if post::is_voting_closed(&config, post.id) {
+        return Ok(String::from(
+            "The voting on this post is closed, so it may no longer be deleted",
+        ));
+    }
+    post.channel_id
+        .delete_message(ctx, post.id)
+        .await
+        .map_err(|err| err.to_string())?;
+    datastore::post::delete_post(&state.datastore.pool, post.guild_id, post.id)
+        .await?;
+    log::debug!("User {} deleted post {}", post.author_id, post.id);
+    reward::sync_user(ctx, state, post.guild_id, post.author_id).await;
+    Ok(String::from("Your post was deleted, along with its votes"))
+}
@@ -0,0 +1,436 @@
+use serenity::{
+    async_trait,
+    builder::{CreateApplicationCommand, CreateComponents, CreateEmbed},
+    model::prelude::{
+        command::CommandType,
+        component::ButtonStyle,
+        interaction::{
+            application_command::ApplicationCommandInteraction,
+            message_component::MessageComponentInteraction,
+            InteractionResponseType, MessageFlags,
+        },
+        ChannelId, GuildId, MessageId, UserId,
+    },
+    prelude::Context,
+};
+
+use super::SlashCommand;
+use crate::{
+    datastore::{
+        self,
+        audit::{self, Action},
+        guild::HideMode,
+        post::Post,
+        report::{self, Review},
+    },
+    handler::{post, reward, state::State},
+};
+
+fn name() -> String { String::from("Report meme") }
+
+/// The report message command, listed in the context menu of the messages.
+/// It records the reports of the posts, hides a post once it reaches the
+/// server's report threshold, and sends a review card to the server's
+/// report channel. The moderators approve or remove the reported post with
+/// the card's buttons, and their decision is recorded in the audit log.
+pub struct ReportMeme;
+
+#[async_trait]
+impl SlashCommand for ReportMeme {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { String::new() }
+
+    fn kind(&self) -> CommandType { CommandType::Message }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command.dm_permission(false)
+    }
+
+    /// Record the report of the targeted post, respond to the reporter
+    /// privately, then hide the post and send its review card when needed.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+
+    /// Approve or remove the reported post, when a moderator presses one
+    /// of the buttons of its review card.
+    async fn handle_component(
+        &self,
+        ctx: Context,
+        component: MessageComponentInteraction,
+        state: &State,
+    ) {
+        handle_review_button(ctx, component, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    let post_id = match command.data.target_id {
+        | Some(target_id) => target_id.to_message_id(),
+        | None => {
+            log::warn!("Received report command with no target");
+            return;
+        }
+    };
+    let (content, reported) =
+        match add_report(state, &command, guild_id, post_id).await {
+            | Ok(outcome) => outcome,
+            | Err(why) => {
+                log::warn!("Failed to report a post: {}", why);
+                (String::from("Failed to report the post"), None)
+            }
+        };
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response.interaction_response_data(|message| {
+                message.content(content).flags(MessageFlags::EPHEMERAL)
+            })
+        })
+        .await
+    {
+        log::warn!("Failed to respond to report command: {}", why);
+    }
+    // NOTE: the post is hidden and its card is sent after responding, as
+    // hiding it may take longer than an interaction may wait for.
+    if let Some(post) = reported {
+        if let Err(why) = escalate(&ctx, state, &post).await {
+            log::warn!("Failed to escalate report of {}: {}", post.id, why);
+        }
+    }
+}
+
+/// Record the report of the post identified by the provided id. Returns
+/// the content of the response, along with the post when the report was
+/// recorded.
+async fn add_report(
+    state: &State,
+    command: &ApplicationCommandInteraction,
+    guild_id: GuildId,
+    post_id: MessageId,
+) -> Result<(String, Option<Post>), String> {
+    let pool = &state.datastore.pool;
+    let post = match datastore::post::get_post(pool, post_id).await? {
+        | Some(post) if post.guild_id == guild_id => post,
+        | _ => {
+            return Ok((
+                String::from(
+                    "Only the posts sent with memsther may be reported",
+                ),
+                None,
+            ))
+        }
+    };
+    if post.author_id == command.user.id {
+        return Ok((String::from("You may not report your own post"), None));
+    }
+    if report::get_review(pool, post_id)
+        .await?
+        .is_some_and(|review| review.approved)
+    {
+        return Ok((
+            String::from("This post was already approved by the moderators"),
+            None,
+        ));
+    }
+    if !report::add_report(pool, post_id, command.user.id).await? {
+        return Ok((String::from("You already reported this post"), None));
+    }
+    log::debug!("User {} reported post {}", command.user.id, post_id);
+    Ok((
+        String::from("Thank you, the post was reported to the moderators"),
+        Some(post),
+    ))
+}
+
+/// Hide the reported post once it reaches the guild's report threshold,
+/// and send or update its review card in the guild's report channel.
+async fn escalate(
+    ctx: &Context,
+    state: &State,
+    post: &Post,
+) -> Result<(), String> {
+    let pool = &state.datastore.pool;
+    let config =
+        datastore::guild::get_report_config(pool, post.guild_id).await?;
+    let mut review = match report::get_review(pool, post.id).await? {
+        | Some(review) => review,
+        | None => return Ok(()),
+    };
+    if !review.hidden
+        && config
+            .threshold
+            .is_some_and(|threshold| review.reports >= i64::from(threshold))
+    {
+        match config.hide_mode {
+            | HideMode::Spoiler => {
+                post::set_spoiler(ctx, post.channel_id, post.id, true).await?
+            }
+            | HideMode::Delete => post
+                .channel_id
+                .delete_message(ctx, post.id)
+                .await
+                .map_err(|err| err.to_string())?,
+        }
+        let deleted = config.hide_mode == HideMode::Delete;
+        report::set_hidden(pool, post.id, deleted).await?;
+        review.hidden = true;
+        review.deleted = deleted;
+        log::info!("Hid post {} after {} report/s", post.id, review.reports);
+    }
+    if let Some(channel_id) = config.channel_id {
+        send_card(ctx, state, post, &review, channel_id).await?;
+    }
+    Ok(())
+}
+
+/// Send the review card of the reported post to the provided channel, or
+/// update the card that was already sent.
+async fn send_card(
+    ctx: &Context,
+    state: &State,
+    post: &Post,
+    review: &Review,
+    channel_id: ChannelId,
+) -> Result<(), String> {
+    let status = if review.deleted {
+        "Deleted, waiting for review"
+    } else if review.hidden {
+        "Hidden, waiting for review"
+    } else {
+        "Waiting for review"
+    };
+    let embed = card(post, review.reports, status);
+    // NOTE: a new card is sent when the old one may not be updated, as it
+    // may have been deleted or the report channel may have changed.
+    if let Some(card_id) = review.card_id {
+        let edited = channel_id
+            .edit_message(ctx, card_id, |message| {
+                message.set_embed(embed.clone())
+            })
+            .await;
+        if edited.is_ok() {
+            return Ok(());
+        }
+    }
+    let card = channel_id
+        .send_message(ctx, |message| {
+            message.set_embed(embed).set_components(buttons(post.id))
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+    report::set_card(&state.datastore.pool, post.id, card.id).await
+}
+
+async fn handle_review_button(
+    ctx: Context,
+    component: MessageComponentInteraction,
+    state: &State,
+) {
+    let guild_id = match component.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    let (decision, post_id) = match parse_custom_id(&component.data.custom_id) {
+        | Some(v) => v,
+        | None => {
+            log::warn!(
+                "Received invalid review button: {}",
+                component.data.custom_id
+            );
+            return;
+        }
+    };
+    let is_moderator = component
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.moderate_members());
+    let outcome = if is_moderator {
+        review(&ctx, state, guild_id, component.user.id, decision, post_id)
+            .await
+            .map_err(|why| {
+                log::warn!("Failed to review post {}: {}", post_id, why);
+                String::from("Failed to review the post")
+            })
+    } else {
+        Err(String::from("Only the moderators may review the reported posts"))
+    };
+    let result = match outcome {
+        | Ok(Some(embed)) => {
+            component
+                .create_interaction_response(&ctx.http, |response| {
+                    response
+                        .kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|message| {
+                            message
+                                .set_embed(embed)
+                                .set_components(CreateComponents::default())
+                        })
+                })
+                .await
+        }
+        | Ok(None) => {
+            respond_privately(
+                &ctx,
+                &component,
+                "This post was already reviewed",
+            )
+            .await
+        }
+        | Err(content) => {
+            respond_privately(&ctx, &component, content.as_str()).await
+        }
+    };
+    if let Err(why) = result {
+        log::warn!("Failed to respond to review button: {}", why);
+    }
+}
+
+/// Take the moderator's decision on the reported post, and record it in the
+/// guild's audit log. Returns the updated review card, or `None` when the
+/// post was already reviewed.
+async fn review(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    moderator_id: UserId,
+    decision: &str,
+    post_id: MessageId,
+) -> Result<Option<CreateEmbed>, String> {
+    let pool = &state.datastore.pool;
+    let post = match datastore::post::get_post(pool, post_id).await? {
+        | Some(post) if post.guild_id == guild_id => post,
+        | _ => return Ok(None),
+    };
+    let review = match report::get_review(pool, post_id).await? {
+        | Some(review) if !review.approved => review,
+        | _ => return Ok(None),
+    };
+    let (action, mut status) = match decision {
+        | "approve" => (
+            Action::ApprovePost(post_id),
+            format!("Approved by <@{}>", moderator_id),
+        ),
+        | "remove" => (
+            Action::RemovePost(post_id),
+            format!("Removed by <@{}>", moderator_id),
+        ),
+        | _ => return Err(format!("Unknown decision '{}'", decision)),
+    };
+    if !audit::take_action(pool, guild_id, moderator_id, action, None).await? {
+        return Ok(None);
+    }
+    log::info!(
+        "Moderator {} took action '{}' in guild {}",
+        moderator_id,
+        action.name(),
+        guild_id
+    );
+    match action {
+        | Action::RemovePost(_) => {
+            // NOTE: the post may already have been deleted when it was
+            // hidden, in which case there is nothing left to delete.
+            if let Err(why) = post.channel_id.delete_message(ctx, post.id).await
+            {
+                log::debug!("Failed to delete post {}: {}", post.id, why);
+            }
+            reward::sync_user(ctx, state, guild_id, post.author_id).await;
+        }
+        | _ if review.deleted => {
+            status.push_str(
+                ", the post was deleted so there is nothing to restore",
+            );
+        }
+        // NOTE: the posts sent as spoilers are left as they are, as only
+        // the ones that were hidden have to be restored.
+        | _ if review.hidden && !post.spoiler => {
+            if let Err(why) =
+                post::set_spoiler(ctx, post.channel_id, post.id, false).await
+            {
+                log::debug!("Failed to restore post {}: {}", post.id, why);
+            }
+        }
+        | _ => (),
+    }
+    Ok(Some(card(&post, review.reports, status.as_str())))
+}
+
+async fn respond_privately(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    content: &str,
+) -> serenity::Result<()> {
+    component
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|message| {
+                    message.content(content).flags(MessageFlags::EPHEMERAL)
+                })
+        })
+        .await
+}
+
+fn card(post: &Post, reports: i64, status: &str) -> CreateEmbed {
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Reported post")
+        .description(format!(
+            "[Jump to the post]({}) in <#{}>",
+            post.id.link(post.channel_id, Some(post.guild_id)),
+            post.channel_id
+        ))
+        .field("Author", format!("<@{}>", post.author_id), true)
+        .field("Reports", reports, true)
+        .field("Status", status, false);
+    embed
+}
+
+fn buttons(post_id: MessageId) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|button| {
+            button
+                .custom_id(custom_id("approve", post_id))
+                .label("Approve")
+                .style(ButtonStyle::Success)
+        })
+        .create_button(|button| {
+            button
+                .custom_id(custom_id("remove", post_id))
+                .label("Remove")
+                .style(ButtonStyle::Danger)
+        })
+    });
+    components
+}
+
+fn custom_id(decision: &str, post_id: MessageId) -> String {
+    format!("{}:{}:{}", name(), decision, post_id)
+}
+
+fn parse_custom_id(custom_id: &str) -> Option<(&str, MessageId)> {
+    let mut parts = custom_id.split(':').skip(1);
+    let decision = parts.next()?;
+    let post_id = parts.next()?.parse::<u64>().ok()?;
+    Some((decision, MessageId(post_id)))
+}
@@ -1,99 +1,119 @@
-use deadpool_postgres::Pool;
 use serenity::{
-    model::{
-        application::command::Command,
-        prelude::{
-            command::CommandOptionType,
-            interaction::{
-                application_command::{
-                    ApplicationCommandInteraction, CommandDataOptionValue,
-                },
-                MessageFlags,
-            },
-            UserId,
+    async_trait,
+    builder::{CreateApplicationCommand, CreateEmbed},
+    model::prelude::{
+        command::CommandOptionType,
+        interaction::{
+            application_command::ApplicationCommandInteraction, MessageFlags,
         },
+        GuildId, User,
     },
     prelude::Context,
 };
 
-use crate::datastore;
+use super::{get_user_or_caller, SlashCommand};
+use crate::{
+    datastore::{
+        self,
+        user::{Filter, Order, Ranking, ScoreQuery},
+    },
+    handler::{member, state::State},
+};
+
+fn name() -> String { String::from("score") }
+fn description() -> String { String::from("Show a user's profile") }
+
+/// The score slash command. It has an optional `user` option, defaulting
+/// to the user of the command, and an optional `public` option, determining
+/// whether the profile is shown to everyone in the channel.
+pub struct Score;
+
+#[async_trait]
+impl SlashCommand for Score {
+    fn name(&self) -> String { name() }
 
-pub fn name() -> String { String::from("score") }
-pub fn description() -> String { String::from("Get a user's score") }
+    fn description(&self) -> String { description() }
 
-/// Register the score slash command. The command has the name
-/// and the description matching the values returned by `name()` and
-/// `description()`. It has 1 mandatory option, containing a user.
-pub async fn register(ctx: &Context) {
-    log::trace!("Registering '{}' command ...", name());
-    match Command::create_global_application_command(&ctx.http, |command| {
-        command.name(name()).description(description()).create_option(
-            |option| {
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command
+            .create_option(|option| {
                 option
                     .name("user")
-                    .description("The user to get the score of")
+                    .description("The user to show the profile of")
                     .kind(CommandOptionType::User)
-                    .required(true)
-            },
-        )
-    })
-    .await
-    {
-        | Ok(_) => log::info!("Registered '{}' slash command", name()),
-        | Err(why) => {
-            log::info!("Failed to register '{}' slash command: {}", name(), why)
-        }
+                    .required(false)
+            })
+            .create_option(|option| {
+                option
+                    .name("public")
+                    .description("Show the profile to everyone in the channel")
+                    .kind(CommandOptionType::Boolean)
+                    .required(false)
+            })
+    }
+
+    /// Respond to the provided command with the profile of the user provided
+    /// as the command's option, or the command's user.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
     }
 }
 
-/// Handle the meme application command. This expects the command name to match
-/// the value returned from the `name()` function. Responds to the
-/// provided command with the score of the user provided as the command's
-/// option.
-pub async fn handle_command(
+async fn handle_command(
     ctx: Context,
     command: ApplicationCommandInteraction,
-    pool: &Pool,
+    state: &State,
 ) {
     log::trace!("Handling '{}' command ...", name());
-    // NOTE: ensure the correct application command interaction
-    // has been passes to this function, as it depends on the
-    // command configuration specified in the `register` function.
-    if command.data.name != name() {
-        log::warn!(
-            "Received command interaction for '{}' but expected '{}'",
-            command.data.name,
-            name()
-        );
-        return;
-    };
 
     let guild_id = match command.guild_id {
         | Some(guild_id) => guild_id,
         | None => return,
     };
+    let user = get_user_or_caller(&command);
+    let public = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "public")
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false);
 
-    let (user_id, username) = match get_user_id_from_interaction(&command).await
-    {
-        | Ok((user_id, username)) => (user_id, username),
+    let embed = match get_profile(&ctx, state, guild_id, user).await {
+        | Ok(embed) => embed,
         | Err(why) => {
-            log::warn!("Failed to get user id: {}", why);
-            return;
-        }
-    };
-    let score = match datastore::user::get_score(&pool, user_id, guild_id).await
-    {
-        | Err(why) => {
-            log::warn!("Failed to get user score: {}", why);
+            log::warn!("Failed to get user profile: {}", why);
+            if let Err(why) = command
+                .create_interaction_response(&ctx.http, |response| {
+                    response.interaction_response_data(|message| {
+                        message
+                            .content("Failed to get the profile")
+                            .flags(MessageFlags::EPHEMERAL)
+                    })
+                })
+                .await
+            {
+                log::warn!("Failed to respond to score command: {}", why);
+            }
             return;
         }
-        | Ok(score) => score,
     };
-    let content = format!("**_{}_** has a score of **_{}_**", username, score);
     if let Err(why) = command
         .create_interaction_response(&ctx.http, |response| {
             response.interaction_response_data(|message| {
-                message.content(content).flags(MessageFlags::EPHEMERAL)
+                if !public {
+                    message.flags(MessageFlags::EPHEMERAL);
+                }
+                message.set_embed(embed)
             })
         })
         .await
@@ -102,16 +122,85 @@ pub async fn handle_command(
     }
 }
 
-async fn get_user_id_from_interaction(
-    command: &ApplicationCommandInteraction,
-) -> Result<(UserId, String), String> {
-    for option in command.data.options.iter() {
-        match &option.resolved {
-            | Some(CommandDataOptionValue::User(user, _)) => {
-                return Ok((user.id, user.name.clone()))
-            }
-            | _ => return Err(String::from("Failed to resolve an option")),
-        }
+/// Build the profile of the provided user in the provided guild.
+async fn get_profile(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user: &User,
+) -> Result<CreateEmbed, String> {
+    let pool = &state.datastore.pool;
+    let query = ScoreQuery {
+        ranking: Ranking::Standard,
+        order: Order::Top,
+        filter: Filter::All,
+        window: None,
+        decay: datastore::guild::get_decay(pool, guild_id).await?,
+    };
+    let score = datastore::user::get_score(pool, user.id, guild_id).await?;
+    let rank =
+        datastore::user::get_rank(pool, user.id, guild_id, &query).await?;
+    let (upvotes, downvotes) =
+        datastore::post::get_received_votes(pool, user.id, guild_id).await?;
+    let posts =
+        datastore::post::count_posts_by_kind(pool, user.id, guild_id).await?;
+    let best_post =
+        datastore::post::get_best_post(pool, user.id, guild_id).await?;
+    let streak = datastore::post::get_streak(pool, user.id, guild_id).await?;
+    let votes_given =
+        datastore::post::count_votes_given(pool, user.id, guild_id).await?;
+    let name = member::display_name(ctx, state, guild_id, user.id)
+        .await
+        .unwrap_or_else(|_| user.name.clone());
+
+    // NOTE: the rank is the one shown on the leaderboard, so it is based
+    // on the decayed score when the guild's scores decay.
+    let decayed_score = query.decay.and(rank.as_ref().map(|rank| rank.score));
+    let rank = match rank {
+        | Some(rank) => format!("#{}", rank.rank),
+        | None => String::from("unranked"),
+    };
+    let post_count: i64 = posts.iter().map(|(_, count)| count).sum();
+    let posts = if posts.is_empty() {
+        String::from("none")
+    } else {
+        posts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let average = if post_count == 0 {
+        String::from("-")
+    } else {
+        format!("{:.2}", (upvotes - downvotes) as f64 / post_count as f64)
+    };
+    let best_post = match best_post {
+        | Some(post) => format!(
+            "[jump]({}) {} 👍 {} 👎",
+            post.id.link(post.channel_id, Some(guild_id)),
+            post.upvotes,
+            post.downvotes
+        ),
+        | None => String::from("none"),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed.title(name).thumbnail(user.face()).field("Score", score, true);
+    if let Some(decayed_score) = decayed_score {
+        embed.field("Decayed score", decayed_score, true);
     }
-    Err("No user id found in the interaction".to_string())
+    embed
+        .field("Rank", rank, true)
+        .field(
+            "Votes received",
+            format!("{} 👍 {} 👎", upvotes, downvotes),
+            true,
+        )
+        .field("Posts", posts, true)
+        .field("Average score per post", average, true)
+        .field("Streak", format!("{} day/s", streak), true)
+        .field("Votes given", votes_given, true)
+        .field("Best post", best_post, false);
+    Ok(embed)
 }
@@ -0,0 +1,165 @@
+use serenity::{
+    async_trait,
+    builder::CreateEmbed,
+    model::prelude::{
+        interaction::{
+            application_command::ApplicationCommandInteraction, MessageFlags,
+        },
+        GuildId, UserId,
+    },
+    prelude::Context,
+};
+
+use super::SlashCommand;
+use crate::{
+    datastore::{self, post::PostVotes},
+    handler::{member, state::State},
+};
+
+fn name() -> String { String::from("shame") }
+fn description() -> String {
+    String::from("Show the server's most downvoted users and posts")
+}
+
+/// The number of users and posts shown in the hall of shame.
+const SHAME_SIZE: u16 = 5;
+
+/// The shame slash command, it lists the users whose posts received
+/// the most downvotes and the most downvoted posts. It may be disabled
+/// per server with the config command.
+pub struct Shame;
+
+#[async_trait]
+impl SlashCommand for Shame {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    /// Respond to the provided command with the server's hall of shame.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+    let guild_id = match command.guild_id {
+        | Some(id) => id,
+        | None => return,
+    };
+
+    let embed = match get_embed(&ctx, state, guild_id).await {
+        | Ok(Some(embed)) => embed,
+        | Ok(None) => {
+            respond_ephemeral(
+                &ctx,
+                &command,
+                "No downvotes were found in this server",
+            )
+            .await;
+            return;
+        }
+        | Err(why) => {
+            log::warn!("Error when fetching downvotes: {}", why);
+            respond_ephemeral(
+                &ctx,
+                &command,
+                "Failed to fetch the hall of shame",
+            )
+            .await;
+            return;
+        }
+    };
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response
+                .interaction_response_data(|message| message.set_embed(embed))
+        })
+        .await
+    {
+        log::warn!("Failed to respond with the hall of shame: {}", why);
+    }
+}
+
+/// Build the guild's hall of shame. Returns `None` when no post in the
+/// guild has been downvoted.
+async fn get_embed(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+) -> Result<Option<CreateEmbed>, String> {
+    let pool = &state.datastore.pool;
+    let users =
+        datastore::post::get_most_downvoted_users(pool, guild_id, SHAME_SIZE)
+            .await?;
+    if users.is_empty() {
+        return Ok(None);
+    }
+    let posts =
+        datastore::post::get_most_downvoted_posts(pool, guild_id, SHAME_SIZE)
+            .await?;
+
+    let ids: Vec<UserId> = users.iter().map(|(id, _)| *id).collect();
+    let names = member::display_names(ctx, state, guild_id, &ids).await;
+    let users: Vec<String> = users
+        .iter()
+        .zip(names.iter())
+        .enumerate()
+        .map(|(i, ((id, downvotes), name))| {
+            let name = match name {
+                | Some(name) => format!("**_{}_**", name),
+                | None => format!("<@{}>", id),
+            };
+            format!("`#{}` {}: {} 👎", i + 1, name, downvotes)
+        })
+        .collect();
+    let posts: Vec<String> = posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| format_post(i + 1, guild_id, post))
+        .collect();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Hall of shame")
+        .field("Most downvoted users", users.join("\n"), false)
+        .field("Most downvoted posts", posts.join("\n"), false);
+    Ok(Some(embed))
+}
+
+fn format_post(position: usize, guild_id: GuildId, post: &PostVotes) -> String {
+    format!(
+        "`#{}` [post]({}) by <@{}>: {} 👎 {} 👍",
+        position,
+        post.id.link(post.channel_id, Some(guild_id)),
+        post.author_id,
+        post.downvotes,
+        post.upvotes
+    )
+}
+
+async fn respond_ephemeral(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    content: &str,
+) {
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |response| {
+            response.interaction_response_data(|message| {
+                message.flags(MessageFlags::EPHEMERAL).content(content)
+            })
+        })
+        .await
+    {
+        log::warn!("Failed to respond to shame command: {}", why);
+    }
+}
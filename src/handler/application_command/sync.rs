@@ -0,0 +1,368 @@
+use serde_json::{Map, Value};
+use serenity::{
+    builder::CreateApplicationCommand,
+    http::Http,
+    model::{
+        application::command::Command,
+        id::{CommandId, GuildId},
+    },
+};
+
+use crate::util;
+
+/// Options controlling how the desired commands are synced with
+/// the commands registered on discord.
+pub struct SyncOptions {
+    /// Only log the changes that would be made, without applying them.
+    pub dry_run: bool,
+    /// Replace all the registered commands with a single bulk overwrite
+    /// request whenever any of them differ, instead of updating them
+    /// one by one.
+    pub overwrite: bool,
+    /// Guilds the commands are registered to instantly during development,
+    /// instead of registering them globally.
+    pub dev_guilds: Vec<GuildId>,
+    /// Register the commands separately for every guild, so that each guild
+    /// may have its own set of commands, instead of registering them
+    /// globally.
+    pub guild_scoped: bool,
+}
+
+impl SyncOptions {
+    /// Read the sync options from the `MEMSTHER_COMMANDS_DRY_RUN`,
+    /// `MEMSTHER_COMMANDS_OVERWRITE`, `MEMSTHER_DEV_GUILDS` and
+    /// `MEMSTHER_GUILD_COMMANDS` env variables.
+    pub fn from_env() -> SyncOptions {
+        let dev_guilds = util::env_list("MEMSTHER_DEV_GUILDS")
+            .iter()
+            .filter_map(|id| match id.parse::<u64>() {
+                | Ok(id) => Some(GuildId(id)),
+                | Err(_) => {
+                    log::warn!("Ignoring invalid dev guild id: {}", id);
+                    None
+                }
+            })
+            .collect();
+        SyncOptions {
+            dry_run: util::env_flag("MEMSTHER_COMMANDS_DRY_RUN"),
+            overwrite: util::env_flag("MEMSTHER_COMMANDS_OVERWRITE"),
+            dev_guilds,
+            guild_scoped: util::env_flag("MEMSTHER_GUILD_COMMANDS"),
+        }
+    }
+
+    /// Whether the commands are registered per guild rather than globally.
+    pub fn per_guild(&self) -> bool {
+        self.guild_scoped || !self.dev_guilds.is_empty()
+    }
+
+    /// Whether the commands should be registered to the provided guild.
+    pub fn includes_guild(&self, guild_id: GuildId) -> bool {
+        if !self.dev_guilds.is_empty() {
+            return self.dev_guilds.contains(&guild_id);
+        }
+        self.guild_scoped
+    }
+}
+
+/// Where the commands are registered, either globally or
+/// to a single guild.
+#[derive(Clone, Copy)]
+pub enum Scope {
+    Global,
+    Guild(GuildId),
+}
+
+impl Scope {
+    pub fn describe(&self) -> String {
+        match self {
+            | Scope::Global => String::from("global"),
+            | Scope::Guild(guild_id) => format!("guild {}", guild_id),
+        }
+    }
+
+    pub async fn fetch(&self, http: &Http) -> serenity::Result<Vec<Command>> {
+        match self {
+            | Scope::Global => {
+                Command::get_global_application_commands(http).await
+            }
+            | Scope::Guild(guild_id) => {
+                guild_id.get_application_commands(http).await
+            }
+        }
+    }
+
+    pub async fn create(
+        &self,
+        http: &Http,
+        definition: CreateApplicationCommand,
+    ) -> serenity::Result<()> {
+        let build = replace_with(definition);
+        match self {
+            | Scope::Global => {
+                Command::create_global_application_command(http, build).await
+            }
+            | Scope::Guild(guild_id) => {
+                guild_id.create_application_command(http, build).await
+            }
+        }
+        .map(|_| ())
+    }
+
+    pub async fn edit(
+        &self,
+        http: &Http,
+        id: CommandId,
+        definition: CreateApplicationCommand,
+    ) -> serenity::Result<()> {
+        let build = replace_with(definition);
+        match self {
+            | Scope::Global => {
+                Command::edit_global_application_command(http, id, build).await
+            }
+            | Scope::Guild(guild_id) => {
+                guild_id.edit_application_command(http, id, build).await
+            }
+        }
+        .map(|_| ())
+    }
+
+    pub async fn delete(
+        &self,
+        http: &Http,
+        id: CommandId,
+    ) -> serenity::Result<()> {
+        match self {
+            | Scope::Global => {
+                Command::delete_global_application_command(http, id).await
+            }
+            | Scope::Guild(guild_id) => {
+                guild_id.delete_application_command(http, id).await
+            }
+        }
+    }
+
+    pub async fn overwrite(
+        &self,
+        http: &Http,
+        definitions: Vec<CreateApplicationCommand>,
+    ) -> serenity::Result<()> {
+        match self {
+            | Scope::Global => {
+                Command::set_global_application_commands(http, |commands| {
+                    commands.set_application_commands(definitions)
+                })
+                .await
+            }
+            | Scope::Guild(guild_id) => {
+                guild_id
+                    .set_application_commands(http, |commands| {
+                        commands.set_application_commands(definitions)
+                    })
+                    .await
+            }
+        }
+        .map(|_| ())
+    }
+}
+
+/// A builder closure replacing the built command with the provided
+/// definition.
+fn replace_with(
+    definition: CreateApplicationCommand,
+) -> impl FnOnce(&mut CreateApplicationCommand) -> &mut CreateApplicationCommand
+{
+    move |command| {
+        *command = definition;
+        command
+    }
+}
+
+/// A single change required to bring the registered commands
+/// in line with the desired definitions.
+pub enum Change {
+    Create(String),
+    Update(String, CommandId, Vec<String>),
+    Delete(String, CommandId),
+}
+
+impl Change {
+    pub fn describe(&self) -> String {
+        match self {
+            | Change::Create(name) => format!("create '{}'", name),
+            | Change::Update(name, _, fields) => {
+                format!("update '{}' ({} changed)", name, fields.join(", "))
+            }
+            | Change::Delete(name, _) => format!("delete '{}'", name),
+        }
+    }
+}
+
+/// Compare the desired command definitions with the registered
+/// commands, and return the changes required to sync them.
+pub fn plan(
+    desired: &[CreateApplicationCommand],
+    registered: &[Command],
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    for definition in desired.iter() {
+        let definition = normalize_definition(definition);
+        let name = definition
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        match registered.iter().find(|command| command.name == name) {
+            | None => changes.push(Change::Create(name)),
+            | Some(command) => {
+                let fields =
+                    differing_fields(&definition, &normalize_command(command));
+                if !fields.is_empty() {
+                    changes.push(Change::Update(name, command.id, fields));
+                }
+            }
+        }
+    }
+    for command in registered.iter() {
+        if !desired.iter().any(|definition| {
+            definition.0.get("name").and_then(Value::as_str)
+                == Some(command.name.as_str())
+        }) {
+            changes.push(Change::Delete(command.name.clone(), command.id));
+        }
+    }
+    changes
+}
+
+/// Names of the top level fields that differ between the two
+/// normalized command definitions.
+fn differing_fields(desired: &Value, registered: &Value) -> Vec<String> {
+    let mut fields = Vec::new();
+    for field in
+        ["type", "description", "options", "default_member_permissions"]
+    {
+        if desired.get(field) != registered.get(field) {
+            fields.push(field.to_string());
+        }
+    }
+    fields
+}
+
+fn normalize_definition(definition: &CreateApplicationCommand) -> Value {
+    let map: Map<String, Value> = definition
+        .0
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    normalize_command_value(&Value::Object(map))
+}
+
+fn normalize_command(command: &Command) -> Value {
+    match serde_json::to_value(command) {
+        | Ok(value) => normalize_command_value(&value),
+        | Err(_) => Value::Null,
+    }
+}
+
+/// Keep only the fields of a command that are set by the definitions,
+/// filling in the defaults discord applies to the omitted ones, so
+/// that a definition and a registered command may be compared directly.
+fn normalize_command_value(value: &Value) -> Value {
+    let mut normalized = Map::new();
+    normalized.insert(
+        "name".to_string(),
+        value.get("name").cloned().unwrap_or(Value::Null),
+    );
+    normalized.insert(
+        "type".to_string(),
+        value.get("type").cloned().unwrap_or(Value::from(1)),
+    );
+    normalized.insert(
+        "description".to_string(),
+        value.get("description").cloned().unwrap_or(Value::from("")),
+    );
+    normalized.insert("options".to_string(), normalize_options(value));
+    // NOTE: the permissions are sent as a string, but may be received as
+    // a number, so compare them by their bits.
+    let permissions = match value.get("default_member_permissions") {
+        | Some(Value::String(bits)) => bits.parse::<u64>().ok(),
+        | Some(Value::Number(bits)) => bits.as_u64(),
+        | _ => None,
+    };
+    normalized.insert(
+        "default_member_permissions".to_string(),
+        permissions.map_or(Value::Null, Value::from),
+    );
+    Value::Object(normalized)
+}
+
+fn normalize_options(value: &Value) -> Value {
+    let options = match value.get("options").and_then(Value::as_array) {
+        | Some(options) => options,
+        | None => return Value::Array(Vec::new()),
+    };
+    Value::Array(
+        options
+            .iter()
+            .map(|option| {
+                let mut normalized = Map::new();
+                for field in ["type", "name", "description"] {
+                    normalized.insert(
+                        field.to_string(),
+                        option.get(field).cloned().unwrap_or(Value::Null),
+                    );
+                }
+                for field in ["required", "autocomplete"] {
+                    normalized.insert(
+                        field.to_string(),
+                        option
+                            .get(field)
+                            .cloned()
+                            .unwrap_or(Value::from(false)),
+                    );
+                }
+                normalized.insert(
+                    "choices".to_string(),
+                    Value::Array(
+                        option.get("choices").and_then(Value::as_array).map_or(
+                            Vec::new(),
+                            |choices| {
+                                choices.iter().map(normalize_choice).collect()
+                            },
+                        ),
+                    ),
+                );
+                normalized.insert(
+                    "channel_types".to_string(),
+                    option
+                        .get("channel_types")
+                        .filter(|value| !value.is_null())
+                        .cloned()
+                        .unwrap_or(Value::Array(Vec::new())),
+                );
+                for field in
+                    ["min_value", "max_value", "min_length", "max_length"]
+                {
+                    normalized.insert(
+                        field.to_string(),
+                        option.get(field).cloned().unwrap_or(Value::Null),
+                    );
+                }
+                normalized
+                    .insert("options".to_string(), normalize_options(option));
+                Value::Object(normalized)
+            })
+            .collect(),
+    )
+}
+
+fn normalize_choice(choice: &Value) -> Value {
+    let mut normalized = Map::new();
+    for field in ["name", "value"] {
+        normalized.insert(
+            field.to_string(),
+            choice.get(field).cloned().unwrap_or(Value::Null),
+        );
+    }
+    Value::Object(normalized)
+}
@@ -0,0 +1,155 @@
+use serenity::{
+    async_trait,
+    builder::CreateApplicationCommand,
+    model::prelude::{
+        command::CommandOptionType,
+        interaction::{
+            application_command::ApplicationCommandInteraction,
+            autocomplete::AutocompleteInteraction,
+        },
+    },
+    prelude::Context,
+};
+
+use super::{
+    defer_response, get_option, send_rendered, suggest_templates, SlashCommand,
+};
+use crate::{
+    handler::{
+        state::State,
+        template::{self, MAX_BOXES},
+    },
+    render,
+};
+
+fn name() -> String { String::from("template") }
+fn description() -> String {
+    String::from("Fill the text boxes of a meme template")
+}
+
+/// The longest text of a box.
+const MAX_TEXT_LENGTH: u16 = 200;
+
+/// The template slash command. It renders the named template, either one
+/// uploaded to the server with the config command or one installed with
+/// the bot, with each text drawn in the template's text boxes, in order.
+/// The rendered meme is sent as a post that may be voted on, same as the
+/// memes sent with the meme command.
+pub struct Template;
+
+#[async_trait]
+impl SlashCommand for Template {
+    fn name(&self) -> String { name() }
+
+    fn description(&self) -> String { description() }
+
+    fn options<'a>(
+        &self,
+        command: &'a mut CreateApplicationCommand,
+    ) -> &'a mut CreateApplicationCommand {
+        command.create_option(|option| {
+            option
+                .name("name")
+                .description("The name of the template")
+                .kind(CommandOptionType::String)
+                .set_autocomplete(true)
+                .required(true)
+        });
+        for i in 1..=MAX_BOXES {
+            command.create_option(|option| {
+                option
+                    .name(format!("text{}", i))
+                    .description(format!("The text of the box {}", i))
+                    .kind(CommandOptionType::String)
+                    .max_length(MAX_TEXT_LENGTH)
+                    .required(i == 1)
+            });
+        }
+        command
+    }
+
+    /// Respond to the provided command with the filled template, and
+    /// publish it as a post.
+    async fn handle(
+        &self,
+        ctx: Context,
+        command: ApplicationCommandInteraction,
+        state: &State,
+    ) {
+        handle_command(ctx, command, state).await
+    }
+
+    /// Suggest the names of the templates while the name is typed.
+    async fn handle_autocomplete(
+        &self,
+        ctx: Context,
+        autocomplete: AutocompleteInteraction,
+        state: &State,
+    ) {
+        suggest_templates(&ctx, &autocomplete, state).await
+    }
+}
+
+async fn handle_command(
+    ctx: Context,
+    command: ApplicationCommandInteraction,
+    state: &State,
+) {
+    log::trace!("Running '{}' command ...", name());
+
+    defer_response(&ctx, &command).await;
+
+    let prepared = prepare_meme(&command, state).await;
+    send_rendered(&ctx, &command, state, prepared).await;
+}
+
+/// Prepare the rendering of the command's texts in the boxes of the named
+/// template. When the template may not be rendered, the response explaining
+/// why is returned instead.
+async fn prepare_meme(
+    command: &ApplicationCommandInteraction,
+    state: &State,
+) -> Result<
+    Result<impl FnOnce() -> Result<Vec<u8>, String> + Send + 'static, String>,
+    String,
+> {
+    let name = get_option(command, "name")
+        .and_then(|value| value.as_str())
+        .unwrap_or_default();
+    let texts: Vec<String> = (1..=MAX_BOXES)
+        .map(|i| {
+            get_option(command, format!("text{}", i).as_str())
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect();
+    let (image, boxes) =
+        match template::find(state, command.guild_id, name).await? {
+            | Some(template) => template,
+            | None => return Ok(Err(format!("_{}_ is not a template", name))),
+        };
+    // NOTE: the trailing empty texts are dropped, so that only the texts
+    // that were provided have to fit in the template's boxes.
+    let count = texts
+        .iter()
+        .rposition(|text| !text.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    if count == 0 {
+        return Ok(Err(String::from("Provide the text of at least one box")));
+    }
+    if count > boxes.len() {
+        return Ok(Err(format!(
+            "The template _{}_ only has {} text box/es",
+            name,
+            boxes.len()
+        )));
+    }
+    Ok(Ok(move || {
+        let texts: Vec<_> = boxes
+            .into_iter()
+            .zip(texts.iter().map(|text| text.as_str()))
+            .collect();
+        render::render(&image, &texts)
+    }))
+}
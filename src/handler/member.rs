@@ -0,0 +1,57 @@
+use futures::future::join_all;
+use serenity::{
+    model::prelude::{GuildId, UserId},
+    prelude::Context,
+};
+
+use super::state::State;
+
+/// Get the display name of the guild's member identified by the
+/// provided id. The names are cached, so that they are not fetched
+/// for every command.
+pub async fn display_name(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<String, String> {
+    if let Some(name) = state.member_names.get(&(guild_id, user_id)) {
+        return Ok(name);
+    }
+    log::trace!("Fetching member {} of guild {}", user_id, guild_id);
+
+    let member =
+        guild_id.member(ctx, user_id).await.map_err(|err| err.to_string())?;
+    let name = member.display_name().to_string();
+    state.member_names.insert((guild_id, user_id), name.clone());
+    Ok(name)
+}
+
+/// Get the display names of all the provided users, the names are fetched
+/// concurrently. `None` is returned for the users that are no longer
+/// members of the guild.
+pub async fn display_names(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_ids: &[UserId],
+) -> Vec<Option<String>> {
+    join_all(user_ids.iter().map(|user_id| async move {
+        display_name(ctx, state, guild_id, *user_id).await.ok()
+    }))
+    .await
+}
+
+/// Get the time the guild's member identified by the provided id joined
+/// the guild, as a unix timestamp.
+pub async fn joined_at(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<Option<i64>, String> {
+    log::trace!("Fetching member {} of guild {}", user_id, guild_id);
+
+    let member =
+        guild_id.member(ctx, user_id).await.map_err(|err| err.to_string())?;
+    Ok(member.joined_at.map(|joined_at| joined_at.unix_timestamp()))
+}
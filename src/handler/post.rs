@@ -0,0 +1,428 @@
+use std::path::Path;
+
+use chrono::Utc;
+use serenity::{
+    model::prelude::{
+        interaction::application_command::ApplicationCommandInteraction,
+        Attachment, AttachmentType, ChannelId, GuildId, Message, MessageId,
+        PremiumTier, ReactionType,
+    },
+    prelude::Context,
+};
+
+use super::{
+    achievement::{self, Event},
+    state::State,
+};
+use crate::{
+    datastore::{
+        self,
+        guild::{AttachmentRules, VotingConfig},
+        post::{Marking, PostVotes},
+    },
+    media, util,
+};
+
+/// The number of posts finalized at once.
+const FINALIZE_BATCH: i64 = 20;
+
+/// The prefix of the names of the attachments discord shows as spoilers.
+const SPOILER_PREFIX: &str = "SPOILER_";
+
+/// The prefix of the final tally shown on the posts once their voting
+/// window closed.
+const TALLY_PREFIX: &str = "🔒 Final score:";
+
+/// The largest file that may be uploaded to a guild without boosts, and to
+/// the guilds of the second and third boost tiers, in MiB. Each may be set
+/// with the `MEMSTHER_UPLOAD_LIMIT`, `MEMSTHER_TIER_2_UPLOAD_LIMIT` and
+/// `MEMSTHER_TIER_3_UPLOAD_LIMIT` env variables, as discord may change them.
+const UPLOAD_LIMIT: u64 = 10;
+const TIER_2_UPLOAD_LIMIT: u64 = 50;
+const TIER_3_UPLOAD_LIMIT: u64 = 100;
+
+/// Publish the message sent in response to the provided command as a post,
+/// that may be voted on. React to the message with thumbs up and thumbs
+/// down, record it as a post of the user who used the command, along with
+/// how it is marked, and check the achievements it may unlock.
+pub async fn publish(
+    ctx: &Context,
+    state: &State,
+    command: &ApplicationCommandInteraction,
+    message: &Message,
+    marking: Marking<'_>,
+) {
+    for reaction in [util::get_thumbs_up(), util::get_thumbs_down()].iter() {
+        if let Err(why) = message
+            .react(&ctx.http, ReactionType::Unicode(reaction.to_string()))
+            .await
+        {
+            log::warn!("Error when reaction to meme: {:?}", why);
+        }
+    }
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    state.message_authors.insert(message.id, Some(command.user.id));
+    if let Err(why) = datastore::post::record_post(
+        &state.datastore.pool,
+        message,
+        guild_id,
+        command.user.id,
+        command.data.name.as_str(),
+        true,
+        marking,
+    )
+    .await
+    {
+        log::warn!("Failed to record a post: {}", why);
+        return;
+    }
+    achievement::check(
+        ctx,
+        state,
+        guild_id,
+        command.user.id,
+        message.channel_id,
+        Event::Post(message.id, command.data.name.as_str()),
+    )
+    .await;
+}
+
+/// Whether the posts are always sent as spoilers in the command's channel.
+pub async fn is_spoiler_channel(
+    state: &State,
+    command: &ApplicationCommandInteraction,
+) -> bool {
+    let guild_id = match command.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return false,
+    };
+    match datastore::guild::get_spoiler_channels(
+        &state.datastore.pool,
+        guild_id,
+    )
+    .await
+    {
+        | Ok(channels) => channels.contains(&command.channel_id),
+        | Err(why) => {
+            log::warn!("Failed to fetch spoiler channels: {}", why);
+            false
+        }
+    }
+}
+
+/// Whether the voting window of the post identified by the provided id
+/// is closed, so that the votes on it are no longer counted and its score
+/// is final.
+pub fn is_voting_closed(config: &VotingConfig, post_id: MessageId) -> bool {
+    config.voting_window_days.is_some_and(|days| {
+        Utc::now().timestamp() - post_id.created_at().unix_timestamp()
+            >= i64::from(days) * 24 * 60 * 60
+    })
+}
+
+/// Edit the posts whose voting window closed to show their final tally,
+/// in the guilds that enabled it.
+pub async fn finalize_closed(ctx: &Context, state: &State) {
+    let pool = &state.datastore.pool;
+    let posts =
+        match datastore::post::get_closed_posts(pool, FINALIZE_BATCH).await {
+            | Ok(posts) => posts,
+            | Err(why) => {
+                log::error!("Failed to fetch the closed posts: {}", why);
+                return;
+            }
+        };
+    for post in posts.iter() {
+        show_final_tally(ctx, post).await;
+        // NOTE: the post is finalized even if it could not be edited, as
+        // it may have been deleted, so that it is not retried forever.
+        if let Err(why) = datastore::post::set_finalized(pool, post.id).await {
+            log::error!("Failed to finalize post {}: {}", post.id, why);
+        }
+    }
+}
+
+async fn show_final_tally(ctx: &Context, post: &PostVotes) {
+    let message = match post.channel_id.message(ctx, post.id).await {
+        | Ok(message) => message,
+        | Err(why) => {
+            log::warn!("Failed to fetch closed post {}: {}", post.id, why);
+            return;
+        }
+    };
+    let tally =
+        format!("{} {} 👍 {} 👎", TALLY_PREFIX, post.upvotes, post.downvotes);
+    let content = if message.content.is_empty() {
+        tally
+    } else {
+        format!("{}\n\n{}", message.content, tally)
+    };
+    if let Err(why) = post
+        .channel_id
+        .edit_message(ctx, post.id, |message| message.content(content))
+        .await
+    {
+        log::warn!("Failed to show the final tally of {}: {}", post.id, why);
+    }
+}
+
+/// Mark the attachments and the content of the provided post as spoilers,
+/// or unmark them. As the attachments of a message may not be renamed,
+/// they are downloaded and uploaded again under their new names.
+pub async fn set_spoiler(
+    ctx: &Context,
+    channel_id: ChannelId,
+    post_id: MessageId,
+    spoiler: bool,
+) -> Result<(), String> {
+    let message = channel_id
+        .message(ctx, post_id)
+        .await
+        .map_err(|err| err.to_string())?;
+    let mut attachments = Vec::new();
+    for attachment in message.attachments.iter() {
+        let data =
+            attachment.download().await.map_err(|err| err.to_string())?;
+        attachments.push(AttachmentType::Bytes {
+            data: data.into(),
+            filename: spoiler_name(&attachment.filename, spoiler),
+        });
+    }
+    let content = spoiler_content(&message.content, spoiler);
+    channel_id
+        .edit_message(ctx, post_id, |edit| {
+            edit.content(content);
+            for attachment in message.attachments.iter() {
+                edit.remove_existing_attachment(attachment.id);
+            }
+            for attachment in attachments {
+                edit.attachment(attachment);
+            }
+            edit
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+/// The largest file that may be uploaded to the guild identified by the
+/// provided id, based on its boost tier, in bytes.
+pub async fn upload_limit(ctx: &Context, guild_id: Option<GuildId>) -> u64 {
+    let guild_id = match guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return tier_upload_limit(PremiumTier::Tier0),
+    };
+    let premium_tier =
+        match ctx.cache.guild_field(guild_id, |guild| guild.premium_tier) {
+            | Some(premium_tier) => premium_tier,
+            | None => match guild_id.to_partial_guild(ctx).await {
+                | Ok(guild) => guild.premium_tier,
+                | Err(why) => {
+                    log::warn!("Failed to fetch guild {}: {}", guild_id, why);
+                    PremiumTier::Tier0
+                }
+            },
+        };
+    tier_upload_limit(premium_tier)
+}
+
+fn tier_upload_limit(premium_tier: PremiumTier) -> u64 {
+    let (name, default) = match premium_tier {
+        | PremiumTier::Tier2 => {
+            ("MEMSTHER_TIER_2_UPLOAD_LIMIT", TIER_2_UPLOAD_LIMIT)
+        }
+        | PremiumTier::Tier3 => {
+            ("MEMSTHER_TIER_3_UPLOAD_LIMIT", TIER_3_UPLOAD_LIMIT)
+        }
+        | _ => ("MEMSTHER_UPLOAD_LIMIT", UPLOAD_LIMIT),
+    };
+    util::env_number(name).unwrap_or(default) * 1024 * 1024
+}
+
+/// The attachment rules of the guild identified by the provided id, the
+/// default ones when they may not be fetched.
+pub async fn attachment_rules(
+    state: &State,
+    guild_id: Option<GuildId>,
+) -> AttachmentRules {
+    let guild_id = match guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return AttachmentRules::default(),
+    };
+    datastore::guild::get_attachment_rules(&state.datastore.pool, guild_id)
+        .await
+        .unwrap_or_else(|why| {
+            log::warn!("Failed to fetch attachment rules: {}", why);
+            AttachmentRules::default()
+        })
+}
+
+/// Prepare the provided attachment to be uploaded again, along with whether
+/// its metadata was stripped. The attachment is only downloaded when it has
+/// to be renamed as a spoiler, when it is an image whose metadata has to be
+/// stripped, or when it exceeds the provided upload limit. Otherwise the
+/// original is uploaded as it is.
+pub async fn prepare_upload(
+    attachment: &Attachment,
+    limit: u64,
+    spoiler: bool,
+    strip_metadata: bool,
+) -> Result<(AttachmentType<'_>, bool), String> {
+    let is_image = attachment
+        .content_type
+        .as_ref()
+        .is_some_and(|content_type| content_type.starts_with("image/"));
+    if attachment.size <= limit && !spoiler && !(strip_metadata && is_image) {
+        return Ok((AttachmentType::from(attachment.url.as_str()), false));
+    }
+    let data = attachment.download().await.map_err(|err| err.to_string())?;
+    prepare_file(
+        data,
+        &attachment.filename,
+        attachment.content_type.as_deref(),
+        limit,
+        spoiler,
+        strip_metadata,
+    )
+    .await
+}
+
+/// Prepare the provided file to be uploaded, along with whether its
+/// metadata was stripped. The metadata of the images is stripped when
+/// required, and the images exceeding the provided upload limit are encoded
+/// again to fit.
+pub async fn prepare_file(
+    mut data: Vec<u8>,
+    filename: &str,
+    content_type: Option<&str>,
+    limit: u64,
+    spoiler: bool,
+    strip_metadata: bool,
+) -> Result<(AttachmentType<'static>, bool), String> {
+    let is_image = content_type
+        .is_some_and(|content_type| content_type.starts_with("image/"));
+    let mut filename = filename.to_string();
+    let mut stripped = false;
+    if strip_metadata && is_image {
+        if let Some(image) = media::strip_metadata(&data) {
+            log::debug!(
+                "Stripped {} bytes of metadata from {}",
+                data.len() - image.len(),
+                filename
+            );
+            data = image;
+            stripped = true;
+        }
+    }
+    // NOTE: the animated images are left as they are, as only their first
+    // frame would be kept.
+    let compressible = is_image && !media::is_animated(&data);
+    if data.len() as u64 > limit && compressible {
+        let original = data.clone();
+        let compressed = tokio::task::spawn_blocking(move || {
+            media::compress(&original, limit)
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+        match compressed {
+            | Ok(Some(encoded)) => {
+                log::info!(
+                    "Compressed {} from {} to {} bytes",
+                    filename,
+                    data.len(),
+                    encoded.data.len()
+                );
+                filename = Path::new(&filename)
+                    .with_extension(encoded.extension)
+                    .to_string_lossy()
+                    .to_string();
+                data = encoded.data;
+            }
+            | Ok(None) => log::debug!(
+                "Failed to compress {} under {} bytes",
+                filename,
+                limit
+            ),
+            | Err(why) => {
+                log::debug!("Failed to compress {}: {}", filename, why)
+            }
+        }
+    }
+    Ok((
+        AttachmentType::Bytes {
+            data: data.into(),
+            filename: spoiler_name(&filename, spoiler),
+        },
+        stripped,
+    ))
+}
+
+/// The final tally shown at the end of the provided content, if any.
+pub fn final_tally(content: &str) -> Option<&str> {
+    content.rfind(TALLY_PREFIX).map(|i| &content[i..])
+}
+
+/// Whether the attachment is shown as a spoiler.
+pub fn is_spoiler_name(filename: &str) -> bool {
+    filename.starts_with(SPOILER_PREFIX)
+}
+
+/// The name of the attachment, marked as a spoiler or unmarked.
+pub fn spoiler_name(filename: &str, spoiler: bool) -> String {
+    match (spoiler, filename.strip_prefix(SPOILER_PREFIX)) {
+        | (true, None) => format!("{}{}", SPOILER_PREFIX, filename),
+        | (false, Some(filename)) => filename.to_string(),
+        | _ => filename.to_string(),
+    }
+}
+
+/// The content of the message, marked as a spoiler or unmarked.
+pub fn spoiler_content(content: &str, spoiler: bool) -> String {
+    let unmarked = content
+        .strip_prefix("||")
+        .and_then(|content| content.strip_suffix("||"))
+        .unwrap_or(content);
+    if spoiler && !unmarked.is_empty() {
+        format!("||{}||", unmarked)
+    } else {
+        unmarked.to_string()
+    }
+}
+
+/// The link posted as the content of the post, without the markers added
+/// around it, such as its spoiler bars, its content warning or its final
+/// tally. Returns `None` when the content is not a link.
+pub fn posted_link(content: &str) -> Option<&str> {
+    let content = match content.find(TALLY_PREFIX) {
+        | Some(i) => &content[..i],
+        | None => content,
+    };
+    let content = match content.strip_prefix("**CW: ") {
+        | Some(content) => content.split_once('\n').map_or("", |(_, c)| c),
+        | None => content,
+    };
+    let content = content.trim();
+    let link = content
+        .strip_prefix("||")
+        .and_then(|content| content.strip_suffix("||"))
+        .unwrap_or(content);
+    Some(link).filter(|link| util::is_url(link))
+}
+
+/// Compose the content of the post, marked as a spoiler when required,
+/// below the content warning if one is provided.
+pub fn compose_content(
+    content: &str,
+    cw: Option<&str>,
+    spoiler: bool,
+) -> String {
+    let content = spoiler_content(content, spoiler);
+    match cw {
+        | Some(cw) if content.is_empty() => format!("**CW: {}**", cw),
+        | Some(cw) => format!("**CW: {}**\n{}", cw, content),
+        | None => content,
+    }
+}
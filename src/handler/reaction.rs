@@ -1,11 +1,23 @@
-use deadpool_postgres::Pool;
+use chrono::Utc;
 use serenity::{
     model::prelude::{GuildId, Message, Reaction, UserId},
     prelude::Context,
 };
 
-use super::BOT_USER_ID;
-use crate::{datastore, util};
+use super::{
+    achievement::{self, Event},
+    member, post, reward,
+    state::State,
+};
+use crate::{
+    datastore::{
+        self,
+        guild::VotingConfig,
+        post::{Marking, Vote},
+        user::Period,
+    },
+    util,
+};
 
 /// Check whether the reaction has been added to a message sent
 /// by the bot, and if the reaction is either thumbs up or thumbs down.
@@ -14,7 +26,7 @@ use crate::{datastore, util};
 pub async fn handle_reaction_add(
     ctx: Context,
     reaction: Reaction,
-    pool: &Pool,
+    state: &State,
 ) {
     if !reaction.emoji.unicode_eq(util::get_thumbs_up().as_str())
         && !reaction.emoji.unicode_eq(util::get_thumbs_down().as_str())
@@ -22,28 +34,118 @@ pub async fn handle_reaction_add(
         return;
     }
 
-    let (meme_author_id, guild_id) =
-        match extract_reaction_data(&ctx, &reaction).await {
+    let meme_author_id =
+        match extract_reaction_data(&ctx, &reaction, state).await {
             | Ok(v) => v,
             | Err(why) => {
                 log::warn!("{}", why);
                 return;
             }
         };
-    if !validate_author_id(&meme_author_id, &reaction.user_id) {
+    let voter_id =
+        match validate_author_id(&meme_author_id, &reaction.user_id, state) {
+            | Some(voter_id) => voter_id,
+            | None => return,
+        };
+    let guild_id = match reaction.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+
+    let voter_joined_at = match reaction
+        .member
+        .as_ref()
+        .and_then(|member| member.joined_at)
+    {
+        | Some(joined_at) => Some(joined_at.unix_timestamp()),
+        | None => member::joined_at(&ctx, guild_id, voter_id)
+            .await
+            .unwrap_or_else(|why| {
+                log::warn!("Failed to fetch the voter's join time: {}", why);
+                None
+            }),
+    };
+    let value = vote_value(&reaction);
+    let config = match datastore::guild::get_voting_config(
+        &state.datastore.pool,
+        guild_id,
+    )
+    .await
+    {
+        | Ok(config) => config,
+        | Err(why) => {
+            log::error!("Failed to fetch the voting config: {}", why);
+            return;
+        }
+    };
+    if post::is_voting_closed(&config, reaction.message_id) {
+        log::trace!("Voting on post {} is closed", reaction.message_id);
         return;
     }
-
-    match update_user_score(
-        meme_author_id,
+    match datastore::user::is_voting_banned(
+        &state.datastore.pool,
+        voter_id,
         guild_id,
-        pool,
-        reaction.emoji.unicode_eq(util::get_thumbs_down().as_str()),
     )
     .await
     {
+        | Ok(false) => (),
+        | Ok(true) => {
+            log::trace!("User {} is banned from voting", voter_id);
+            return;
+        }
+        | Err(why) => {
+            log::error!("Failed to check the voting ban: {}", why);
+            return;
+        }
+    }
+    let mitigation = match get_mitigation(
+        state,
+        &config,
+        guild_id,
+        voter_id,
+        meme_author_id,
+        value,
+        voter_joined_at,
+    )
+    .await
+    {
+        | Ok(mitigation) => mitigation,
+        | Err(why) => {
+            log::error!("Could not weigh the vote: {}", why);
+            return;
+        }
+    };
+    if let (Mitigation::OverBudget(since), true) =
+        (&mitigation, config.notify_over_budget)
+    {
+        notify_over_budget(&ctx, state, guild_id, voter_id, *since).await;
+    }
+    let vote = Vote {
+        post_id: reaction.message_id,
+        voter_id,
+        value,
+        weight: mitigation.weight(),
+        voter_joined_at,
+    };
+
+    match datastore::post::add_vote(&state.datastore.pool, &vote).await {
         | Err(why) => log::error!("Could not update user score: {}", why),
-        | Ok(_) => log::trace!("Updated user {}'s score", meme_author_id),
+        | Ok(_) => {
+            log::trace!("Updated user {}'s score", meme_author_id);
+            reward::sync_user(&ctx, state, guild_id, meme_author_id).await;
+            if vote.value > 0 && vote.weight >= 1.0 {
+                achievement::check(
+                    &ctx,
+                    state,
+                    guild_id,
+                    meme_author_id,
+                    reaction.channel_id,
+                    Event::Upvote(reaction.message_id),
+                )
+                .await;
+            }
+        }
     }
 }
 
@@ -54,89 +156,241 @@ pub async fn handle_reaction_add(
 pub async fn handle_reaction_remove(
     ctx: Context,
     reaction: Reaction,
-    pool: &Pool,
+    state: &State,
 ) {
     if !reaction.emoji.unicode_eq(util::get_thumbs_up().as_str())
         && !reaction.emoji.unicode_eq(util::get_thumbs_down().as_str())
     {
         return;
     }
-    let (meme_author_id, guild_id) =
-        match extract_reaction_data(&ctx, &reaction).await {
+    let meme_author_id =
+        match extract_reaction_data(&ctx, &reaction, state).await {
             | Ok(v) => v,
             | Err(why) => {
                 log::warn!("{}", why);
                 return;
             }
         };
-    if !validate_author_id(&meme_author_id, &reaction.user_id) {
-        return;
+    let voter_id =
+        match validate_author_id(&meme_author_id, &reaction.user_id, state) {
+            | Some(voter_id) => voter_id,
+            | None => return,
+        };
+    let guild_id = match reaction.guild_id {
+        | Some(guild_id) => guild_id,
+        | None => return,
+    };
+    match datastore::guild::get_voting_config(&state.datastore.pool, guild_id)
+        .await
+    {
+        | Ok(config) if post::is_voting_closed(&config, reaction.message_id) => {
+            log::trace!("Voting on post {} is closed", reaction.message_id);
+            return;
+        }
+        | Ok(_) => (),
+        | Err(why) => {
+            log::error!("Failed to fetch the voting config: {}", why);
+            return;
+        }
     }
 
-    match update_user_score(
-        meme_author_id,
-        guild_id,
-        pool,
-        reaction.emoji.unicode_eq(util::get_thumbs_up().as_str()),
+    match datastore::post::remove_vote(
+        &state.datastore.pool,
+        reaction.message_id,
+        voter_id,
+        vote_value(&reaction),
     )
     .await
     {
         | Err(why) => log::error!("Could not update user score: {}", why),
-        | Ok(_) => log::trace!("Updated user {}'s score", meme_author_id),
+        | Ok(_) => {
+            log::trace!("Updated user {}'s score", meme_author_id);
+            reward::sync_user(&ctx, state, guild_id, meme_author_id).await;
+        }
     }
 }
 
-pub async fn update_user_score(
-    user_id: UserId,
-    guild_id: GuildId,
-    pool: &Pool,
-    decrement: bool,
-) -> Result<(), String> {
-    if decrement {
-        datastore::user::decrement_score(pool, user_id, guild_id).await
+/// The value of the vote, -1 for thumbs down and 1 for thumbs up.
+fn vote_value(reaction: &Reaction) -> i16 {
+    if reaction.emoji.unicode_eq(util::get_thumbs_down().as_str()) {
+        -1
     } else {
-        datastore::user::increment_score(pool, user_id, guild_id).await
+        1
+    }
+}
+
+/// Why a vote carries less than its full weight.
+enum Mitigation {
+    /// The vote is counted fully.
+    None,
+    /// The voter's account, or membership, is too recent for the vote
+    /// to be counted.
+    NewVoter,
+    /// The voter went over their daily vote budget, which started at the
+    /// provided unix timestamp.
+    OverBudget(i64),
+    /// The voter's votes on the author are flagged, and carry the
+    /// provided weight.
+    Flagged(f32),
+}
+
+impl Mitigation {
+    fn weight(&self) -> f32 {
+        match self {
+            | Mitigation::None => 1.0,
+            | Mitigation::NewVoter | Mitigation::OverBudget(_) => 0.0,
+            | Mitigation::Flagged(weight) => *weight,
+        }
     }
 }
 
+/// Get the mitigation applied to the vote, based on the guild's voting
+/// config. The votes of the voters whose account or membership is more
+/// recent than the guild's minimum age, and the votes beyond the voter's
+/// daily budget, carry no weight, while the flagged votes carry the
+/// guild's flagged vote weight.
+async fn get_mitigation(
+    state: &State,
+    config: &VotingConfig,
+    guild_id: GuildId,
+    voter_id: UserId,
+    author_id: UserId,
+    value: i16,
+    voter_joined_at: Option<i64>,
+) -> Result<Mitigation, String> {
+    let pool = &state.datastore.pool;
+    let now = Utc::now().timestamp();
+    let is_recent =
+        |since: i64, days: i32| now - since < i64::from(days) * 24 * 60 * 60;
+    if let (Some(days), Some(joined_at)) =
+        (config.min_member_days, voter_joined_at)
+    {
+        if is_recent(joined_at, days) {
+            log::debug!("Ignoring the vote of new member {}", voter_id);
+            return Ok(Mitigation::NewVoter);
+        }
+    }
+    if let Some(days) = config.min_account_days {
+        if is_recent(voter_id.created_at().unix_timestamp(), days) {
+            log::debug!("Ignoring the vote of new account {}", voter_id);
+            return Ok(Mitigation::NewVoter);
+        }
+    }
+    if config.max_daily_downvotes.is_some()
+        || config.max_daily_author_votes.is_some()
+    {
+        let since =
+            datastore::guild::get_window(pool, guild_id, &Period::Today)
+                .await?
+                .map_or(now, |window| window.start);
+        let (downvotes, author_votes) = datastore::abuse::count_votes_since(
+            pool, guild_id, voter_id, author_id, since,
+        )
+        .await?;
+        let over_downvotes = value < 0
+            && config
+                .max_daily_downvotes
+                .is_some_and(|max| downvotes >= i64::from(max));
+        let over_author_votes = config
+            .max_daily_author_votes
+            .is_some_and(|max| author_votes >= i64::from(max));
+        if over_downvotes || over_author_votes {
+            log::debug!("Voter {} is over their daily budget", voter_id);
+            return Ok(Mitigation::OverBudget(since));
+        }
+    }
+    if let Some(weight) = config.flagged_vote_weight {
+        if datastore::abuse::is_flagged(pool, guild_id, voter_id, author_id)
+            .await?
+        {
+            log::debug!("Down-weighting the flagged vote of {}", voter_id);
+            return Ok(Mitigation::Flagged(weight));
+        }
+    }
+    Ok(Mitigation::None)
+}
+
+/// Notify the voter that their votes are no longer counted today, unless
+/// they were already notified since the provided time.
+async fn notify_over_budget(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    voter_id: UserId,
+    since: i64,
+) {
+    match datastore::abuse::claim_budget_notice(
+        &state.datastore.pool,
+        guild_id,
+        voter_id,
+        since,
+    )
+    .await
+    {
+        | Ok(true) => (),
+        | Ok(false) => return,
+        | Err(why) => {
+            log::error!("Failed to claim the budget notice: {}", why);
+            return;
+        }
+    }
+    let guild_name = guild_id
+        .name(&ctx.cache)
+        .unwrap_or_else(|| String::from("this server"));
+    let content = format!(
+        "You reached your daily vote limit in **{}**, your further votes \
+         there are not counted until tomorrow.",
+        guild_name
+    );
+    let result = match voter_id.create_dm_channel(ctx).await {
+        | Ok(channel) => channel
+            .send_message(ctx, |message| message.content(content))
+            .await
+            .map(|_| ()),
+        | Err(why) => Err(why),
+    };
+    if let Err(why) = result {
+        log::warn!("Failed to notify voter {}: {}", voter_id, why);
+    }
+}
+
+/// Returns the id of the reaction's author, when they may vote on the meme.
 fn validate_author_id(
     meme_author_id: &UserId,
     reaction_author_id: &Option<UserId>,
-) -> bool {
+    state: &State,
+) -> Option<UserId> {
     // NOTE: ensure that the meme's author does not vote on it's
     // their own meme
     let reaction_author_id = match reaction_author_id {
         | Some(user_id) => {
             if user_id == meme_author_id {
                 log::trace!("User voted on his message, not updating score");
-                return false;
+                return None;
             };
             user_id
         }
         | None => {
             log::trace!("No user id found in the reaction, not updating score");
-            return false;
+            return None;
         }
     };
-    unsafe {
-        let bot_user_id = match BOT_USER_ID {
-            | Some(id) => id,
-            | None => return false,
-        };
-        if bot_user_id == *reaction_author_id {
-            log::trace!(
-                "Bot is the author of the reaction, not updating score"
-            );
-            return false;
-        }
+    let bot_user_id = match state.bot_user_id() {
+        | Some(id) => id,
+        | None => return None,
+    };
+    if bot_user_id == *reaction_author_id {
+        log::trace!("Bot is the author of the reaction, not updating score");
+        return None;
     }
-    true
+    Some(*reaction_author_id)
 }
 
 async fn extract_reaction_data(
     ctx: &Context,
     reaction: &Reaction,
-) -> Result<(UserId, GuildId), String> {
+    state: &State,
+) -> Result<UserId, String> {
     let guild_id = match reaction.guild_id {
         | Some(guild_id) => guild_id,
         | None => {
@@ -144,32 +398,62 @@ async fn extract_reaction_data(
         }
     };
 
-    let message =
-        reaction.message(&ctx.http).await.map_err(|err| err.to_string())?;
-
-    let author_id = get_bot_message_author_id(&message)
-        .await
-        .map_err(|err| err.to_string())?;
+    // NOTE: the resolved authors are cached, so that the message is not
+    // fetched for every reaction.
+    let author_id = match state.message_authors.get(&reaction.message_id) {
+        | Some(author_id) => author_id,
+        | None => {
+            let message = reaction
+                .channel_id
+                .message(ctx, reaction.message_id)
+                .await
+                .map_err(|err| err.to_string())?;
+            let author_id = get_bot_message_author_id(&message, state)?;
+            if let (Some(author_id), Some(interaction)) =
+                (author_id, &message.interaction)
+            {
+                // NOTE: the posts sent before the votes were recorded are
+                // recorded once they receive a vote.
+                datastore::post::record_post(
+                    &state.datastore.pool,
+                    &message,
+                    guild_id,
+                    author_id,
+                    interaction.name.as_str(),
+                    false,
+                    Marking {
+                        spoiler: message.attachments.iter().any(|attachment| {
+                            post::is_spoiler_name(&attachment.filename)
+                        }),
+                        cw: None,
+                    },
+                )
+                .await?;
+            }
+            state.message_authors.insert(reaction.message_id, author_id);
+            author_id
+        }
+    };
+    let author_id =
+        author_id.ok_or_else(|| String::from("Not a memsther message"))?;
 
-    Ok((UserId::from(author_id), guild_id))
+    Ok(author_id)
 }
 
-async fn get_bot_message_author_id(
+/// Get the id of the user who used the command the provided message was
+/// sent in response to. `None` is returned when the message was not sent by
+/// memsther.
+fn get_bot_message_author_id(
     message: &Message,
-) -> Result<UserId, String> {
-    unsafe {
-        match BOT_USER_ID {
-            | None => return Err(String::from("Bot user not available")),
-            | Some(id) => {
-                if id != message.author.id {
-                    return Err(String::from("Not a memsther message"));
-                }
+    state: &State,
+) -> Result<Option<UserId>, String> {
+    match state.bot_user_id() {
+        | None => return Err(String::from("Bot user not available")),
+        | Some(id) => {
+            if id != message.author.id {
+                return Ok(None);
             }
         }
     }
-    let interaction = match &message.interaction {
-        | Some(interaction) => interaction,
-        | None => return Err(String::from("Not a memsther message")),
-    };
-    Ok(interaction.user.id)
+    Ok(message.interaction.as_ref().map(|interaction| interaction.user.id))
 }
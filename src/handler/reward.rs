@@ -0,0 +1,217 @@
+use serenity::{
+    model::prelude::{GuildId, RoleId, UserId},
+    prelude::Context,
+};
+
+use super::state::State;
+use crate::datastore::{
+    self,
+    reward::{RewardRole, Rule},
+    user::{Filter, Order, Ranking, ScoreQuery},
+};
+
+/// The audit log reason of the granted and revoked reward roles.
+const REASON: &str = "Reward role";
+
+/// Sync the guild's reward roles after the score of the provided user
+/// changed. The score rules are only checked for the provided user, while
+/// the rank rules are queued to be checked for everyone by the scheduler,
+/// as ranking the whole guild on every vote would be too costly.
+pub async fn sync_user(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_id: UserId,
+) {
+    state.queue_rank_sync(guild_id);
+    sync(ctx, state, guild_id, Some(user_id)).await
+}
+
+/// Sync all the reward roles of the guilds whose scores changed since
+/// they were last synced.
+pub async fn sync_pending(ctx: &Context, state: &State) {
+    for guild_id in state.take_rank_syncs() {
+        sync_guild(ctx, state, guild_id).await;
+    }
+}
+
+/// Sync all the reward roles of the guild.
+pub async fn sync_guild(ctx: &Context, state: &State, guild_id: GuildId) {
+    sync(ctx, state, guild_id, None).await
+}
+
+/// Sync the reward roles of all the guilds that have any.
+pub async fn sync_all(ctx: &Context, state: &State) {
+    let guilds =
+        match datastore::reward::get_reward_guilds(&state.datastore.pool).await
+        {
+            | Ok(guilds) => guilds,
+            | Err(why) => {
+                log::error!("Failed to fetch the reward guilds: {}", why);
+                return;
+            }
+        };
+    log::debug!("Syncing the reward roles of {} guild/s", guilds.len());
+    for guild_id in guilds {
+        sync_guild(ctx, state, guild_id).await;
+    }
+}
+
+/// Revoke the role identified by the provided id from all the users it
+/// was granted to.
+pub async fn revoke_all(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    role_id: RoleId,
+) -> Result<(), String> {
+    let granted =
+        datastore::reward::get_grants(&state.datastore.pool, guild_id, role_id)
+            .await?;
+    for user_id in granted {
+        set_role(ctx, state, guild_id, role_id, user_id, false).await;
+    }
+    Ok(())
+}
+
+async fn sync(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    user_id: Option<UserId>,
+) {
+    let rewards = match datastore::reward::get_reward_roles(
+        &state.datastore.pool,
+        guild_id,
+    )
+    .await
+    {
+        | Ok(rewards) => rewards,
+        | Err(why) => {
+            log::error!("Failed to fetch the reward roles: {}", why);
+            return;
+        }
+    };
+    for reward in rewards.iter() {
+        if let Err(why) = sync_role(ctx, state, guild_id, reward, user_id).await
+        {
+            log::error!(
+                "Failed to sync reward role {} in guild {}: {}",
+                reward.role_id,
+                guild_id,
+                why
+            );
+        }
+    }
+}
+
+/// Grant the reward role to the eligible users, and revoke it from the
+/// users that are no longer eligible. When a user is provided, the score
+/// rules are only checked for them, and the rank rules are skipped.
+async fn sync_role(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    reward: &RewardRole,
+    user_id: Option<UserId>,
+) -> Result<(), String> {
+    let pool = &state.datastore.pool;
+    let (eligible, checked) = match (&reward.rule, user_id) {
+        | (Rule::Score(min_score), Some(user_id)) => {
+            let score =
+                datastore::user::get_score(pool, user_id, guild_id).await?;
+            let eligible =
+                if score >= *min_score { vec![user_id] } else { Vec::new() };
+            (eligible, Some(user_id))
+        }
+        | (Rule::Score(min_score), None) => (
+            datastore::user::get_users_with_score(pool, guild_id, *min_score)
+                .await?,
+            None,
+        ),
+        | (Rule::Rank(..), Some(_)) => return Ok(()),
+        | (Rule::Rank(max_rank, period), None) => {
+            let query = ScoreQuery {
+                ranking: Ranking::Standard,
+                order: Order::Top,
+                filter: Filter::Positive,
+                window: datastore::guild::get_window(pool, guild_id, period)
+                    .await?,
+                decay: datastore::guild::get_decay(pool, guild_id).await?,
+            };
+            // NOTE: the users are filtered by their rank rather than limited
+            // to the first ones, so that all the users tied at the last
+            // rank are rewarded.
+            let eligible = datastore::user::get_users_within_rank(
+                pool, guild_id, &query, *max_rank,
+            )
+            .await?;
+            (eligible, None)
+        }
+    };
+    let granted =
+        datastore::reward::get_grants(pool, guild_id, reward.role_id).await?;
+
+    for user_id in granted.iter() {
+        if !eligible.contains(user_id)
+            && checked.is_none_or(|checked| checked == *user_id)
+        {
+            set_role(ctx, state, guild_id, reward.role_id, *user_id, false)
+                .await;
+        }
+    }
+    for user_id in eligible.iter() {
+        if !granted.contains(user_id) {
+            set_role(ctx, state, guild_id, reward.role_id, *user_id, true)
+                .await;
+        }
+    }
+    Ok(())
+}
+
+/// Grant or revoke the role, and record the change once it is applied,
+/// so that the failed changes are retried on the next sync.
+async fn set_role(
+    ctx: &Context,
+    state: &State,
+    guild_id: GuildId,
+    role_id: RoleId,
+    user_id: UserId,
+    granted: bool,
+) {
+    log::trace!(
+        "Setting reward role {} granted to {} for user {}",
+        role_id,
+        granted,
+        user_id
+    );
+    let result = if granted {
+        ctx.http
+            .add_member_role(guild_id.0, user_id.0, role_id.0, Some(REASON))
+            .await
+    } else {
+        ctx.http
+            .remove_member_role(guild_id.0, user_id.0, role_id.0, Some(REASON))
+            .await
+    };
+    if let Err(why) = result {
+        log::warn!(
+            "Failed to update reward role {} of user {}: {}",
+            role_id,
+            user_id,
+            why
+        );
+        return;
+    }
+    if let Err(why) = datastore::reward::set_granted(
+        &state.datastore.pool,
+        guild_id,
+        role_id,
+        user_id,
+        granted,
+    )
+    .await
+    {
+        log::error!("Failed to record reward role: {}", why);
+    }
+}
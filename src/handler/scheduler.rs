@@ -0,0 +1,266 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serenity::{
+    builder::CreateEmbed,
+    model::prelude::{RoleId, UserId},
+    prelude::Context,
+};
+
+use super::{member, post, reward, state::State};
+use crate::datastore::{self, announcement::Announcement, post::PostVotes};
+
+/// How often the schedules are checked for due announcements, the closed
+/// posts are finalized, and the reward roles of the guilds whose scores
+/// changed are synced.
+const TICK: Duration = Duration::from_secs(60);
+
+/// How often all the reward roles are synced, so that the roles missed
+/// while the bot was offline, or that failed to update, are corrected.
+const REWARD_SYNC: Duration = Duration::from_secs(60 * 60);
+
+/// Start the background tasks sending the guilds' scheduled announcements,
+/// finalizing the posts whose voting window closed and syncing the guilds'
+/// reward roles.
+pub fn start(ctx: Context, state: Arc<State>) {
+    log::info!("Starting the scheduler");
+    {
+        let ctx = ctx.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TICK);
+            loop {
+                interval.tick().await;
+                run_due_announcements(&ctx, &state).await;
+                post::finalize_closed(&ctx, &state).await;
+                reward::sync_pending(&ctx, &state).await;
+            }
+        });
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REWARD_SYNC);
+        loop {
+            interval.tick().await;
+            reward::sync_all(&ctx, &state).await;
+        }
+    });
+}
+
+/// Parse the provided cron expression. The expressions without the
+/// seconds field are accepted as well, as they are the more common ones.
+pub fn parse_schedule(expression: &str) -> Result<Schedule, String> {
+    let expression = match expression.split_whitespace().count() {
+        | 5 => format!("0 {}", expression),
+        | _ => expression.to_string(),
+    };
+    Schedule::from_str(expression.as_str()).map_err(|err| err.to_string())
+}
+
+/// Send the announcements whose next run is due. Only a single run of an
+/// announcement is sent on every tick, so the runs missed while the bot
+/// was offline are caught up gradually rather than all at once.
+async fn run_due_announcements(ctx: &Context, state: &State) {
+    let announcements = match datastore::announcement::get_all_announcements(
+        &state.datastore.pool,
+    )
+    .await
+    {
+        | Ok(announcements) => announcements,
+        | Err(why) => {
+            log::error!("Failed to fetch announcements: {}", why);
+            return;
+        }
+    };
+    let now = Utc::now().timestamp();
+    for announcement in announcements.iter() {
+        let run = match next_run(state, announcement).await {
+            | Ok(Some(run)) if run <= now => run,
+            | Ok(_) => continue,
+            | Err(why) => {
+                log::warn!(
+                    "Invalid schedule of announcement '{}' in guild {}: {}",
+                    announcement.name,
+                    announcement.guild_id,
+                    why
+                );
+                continue;
+            }
+        };
+        if let Err(why) = announce(ctx, state, announcement, run).await {
+            log::error!(
+                "Failed to send announcement '{}' in guild {}: {}",
+                announcement.name,
+                announcement.guild_id,
+                why
+            );
+        }
+    }
+}
+
+/// The first run of the announcement's schedule after its last run, as a
+/// unix timestamp. The schedule is evaluated in the guild's time zone.
+async fn next_run(
+    state: &State,
+    announcement: &Announcement,
+) -> Result<Option<i64>, String> {
+    let schedule = parse_schedule(announcement.schedule.as_str())?;
+    let timezone = datastore::guild::get_timezone(
+        &state.datastore.pool,
+        announcement.guild_id,
+    )
+    .await?
+    .parse::<Tz>()
+    .unwrap_or(Tz::UTC);
+    let last_run = timezone
+        .timestamp_opt(announcement.last_run, 0)
+        .single()
+        .ok_or_else(|| String::from("Invalid last run"))?;
+    Ok(schedule.after(&last_run).next().map(|run| run.timestamp()))
+}
+
+/// Announce the top post sent between the announcement's last run and the
+/// provided run. The run is claimed first, so that it is never announced
+/// twice, and released again if the announcement could not be sent.
+async fn announce(
+    ctx: &Context,
+    state: &State,
+    announcement: &Announcement,
+    run: i64,
+) -> Result<(), String> {
+    let pool = &state.datastore.pool;
+    let post = datastore::post::get_top_post(
+        pool,
+        announcement.guild_id,
+        announcement.last_run,
+        run,
+    )
+    .await?;
+    let winner_id = post.as_ref().map(|post| post.author_id);
+    if !datastore::announcement::claim_run(pool, announcement, run, winner_id)
+        .await?
+    {
+        log::debug!("Announcement '{}' already claimed", announcement.name);
+        return Ok(());
+    }
+    let post = match post {
+        | Some(post) => post,
+        | None => {
+            log::debug!("No posts to announce for '{}'", announcement.name);
+            return Ok(());
+        }
+    };
+
+    let embed = get_embed(ctx, state, announcement, &post).await;
+    if let Err(why) = announcement
+        .channel_id
+        .send_message(ctx, |message| message.set_embed(embed))
+        .await
+    {
+        datastore::announcement::release_run(
+            pool,
+            announcement,
+            run,
+            winner_id,
+        )
+        .await?;
+        return Err(why.to_string());
+    }
+    log::info!(
+        "Announced '{}' in guild {}",
+        announcement.name,
+        announcement.guild_id
+    );
+
+    if announcement.bonus != 0 {
+        reward::sync_user(ctx, state, announcement.guild_id, post.author_id)
+            .await;
+    }
+    if let Some(role_id) = announcement.role_id {
+        move_role(ctx, announcement, role_id, post.author_id).await;
+    }
+    Ok(())
+}
+
+async fn get_embed(
+    ctx: &Context,
+    state: &State,
+    announcement: &Announcement,
+    post: &PostVotes,
+) -> CreateEmbed {
+    let name = match member::display_name(
+        ctx,
+        state,
+        announcement.guild_id,
+        post.author_id,
+    )
+    .await
+    {
+        | Ok(name) => format!("**_{}_**", name),
+        | Err(_) => format!("<@{}>", post.author_id),
+    };
+    let mut description =
+        format!("{} won with {} 👍 {} 👎", name, post.upvotes, post.downvotes);
+    if announcement.bonus != 0 {
+        description.push_str(
+            format!(" and receives {} bonus points", announcement.bonus)
+                .as_str(),
+        );
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(announcement.name.as_str())
+        .url(post.id.link(post.channel_id, Some(announcement.guild_id)))
+        .description(description);
+    // NOTE: the meme is shown in the embed when the post may still be
+    // fetched, otherwise only the link to it is announced.
+    if let Ok(message) = post.channel_id.message(ctx, post.id).await {
+        if let Some(attachment) = message.attachments.first() {
+            embed.image(attachment.url.as_str());
+        } else if let Some(link) = post::posted_link(&message.content) {
+            embed.image(link);
+        }
+    }
+    embed
+}
+
+/// Grant the announcement's role to the provided winner, and revoke it
+/// from the previous winner.
+async fn move_role(
+    ctx: &Context,
+    announcement: &Announcement,
+    role_id: RoleId,
+    winner_id: UserId,
+) {
+    let reason = format!("Won '{}'", announcement.name);
+    if let Some(last_winner_id) = announcement.last_winner_id {
+        if last_winner_id != winner_id {
+            if let Err(why) = ctx
+                .http
+                .remove_member_role(
+                    announcement.guild_id.0,
+                    last_winner_id.0,
+                    role_id.0,
+                    Some(reason.as_str()),
+                )
+                .await
+            {
+                log::warn!("Failed to revoke announcement role: {}", why);
+            }
+        }
+    }
+    if let Err(why) = ctx
+        .http
+        .add_member_role(
+            announcement.guild_id.0,
+            winner_id.0,
+            role_id.0,
+            Some(reason.as_str()),
+        )
+        .await
+    {
+        log::warn!("Failed to grant announcement role: {}", why);
+    }
+}
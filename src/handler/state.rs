@@ -0,0 +1,152 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use serenity::{
+    model::prelude::{
+        interaction::Interaction, GuildId, MessageId, Reaction, UserId,
+    },
+    prelude::Context,
+};
+
+use super::application_command::Registry;
+use crate::{cache::TtlCache, datastore::Datastore};
+
+/// The maximum number of events kept while waiting for the shards
+/// to become ready, the oldest events are dropped once it is reached.
+const MAX_PENDING_EVENTS: usize = 1000;
+
+/// The number of entries kept in each of the caches.
+const CACHE_CAPACITY: usize = 10000;
+
+/// The author of a message never changes, so the resolved authors
+/// may be kept for a long time.
+const MESSAGE_AUTHORS_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const MEMBER_NAMES_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// The state shared by all the event and command handlers.
+pub struct State {
+    pub giphy_key: String,
+    pub datastore: Datastore,
+    pub commands: Registry,
+    /// The users who posted the memsther messages, identified by the
+    /// messages' ids. `None` is cached for messages that were not
+    /// posted through memsther, so they are not fetched again.
+    pub message_authors: TtlCache<MessageId, Option<UserId>>,
+    /// The display names of the guilds' members.
+    pub member_names: TtlCache<(GuildId, UserId), String>,
+    /// The guilds whose scores changed since their rank reward roles were
+    /// last synced.
+    pending_rank_syncs: Mutex<HashSet<GuildId>>,
+    bot_user_id: OnceLock<UserId>,
+    readiness: Mutex<Readiness>,
+    scheduler_started: AtomicBool,
+}
+
+/// An event received by a shard before it was ready, handled once the
+/// shard becomes ready.
+pub enum PendingEvent {
+    Interaction(Context, Box<Interaction>),
+    ReactionAdd(Context, Reaction),
+    ReactionRemove(Context, Reaction),
+}
+
+impl PendingEvent {
+    fn shard_id(&self) -> u64 {
+        match self {
+            | PendingEvent::Interaction(ctx, _) => ctx.shard_id,
+            | PendingEvent::ReactionAdd(ctx, _) => ctx.shard_id,
+            | PendingEvent::ReactionRemove(ctx, _) => ctx.shard_id,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Readiness {
+    shards: HashSet<u64>,
+    pending: Vec<PendingEvent>,
+}
+
+impl State {
+    pub fn new(
+        giphy_key: String,
+        datastore: Datastore,
+        commands: Registry,
+    ) -> State {
+        State {
+            giphy_key,
+            datastore,
+            commands,
+            message_authors: TtlCache::new(CACHE_CAPACITY, MESSAGE_AUTHORS_TTL),
+            member_names: TtlCache::new(CACHE_CAPACITY, MEMBER_NAMES_TTL),
+            pending_rank_syncs: Mutex::new(HashSet::new()),
+            bot_user_id: OnceLock::new(),
+            readiness: Mutex::new(Readiness::default()),
+            scheduler_started: AtomicBool::new(false),
+        }
+    }
+
+    /// The bot's user id, available once any of the shards is ready.
+    pub fn bot_user_id(&self) -> Option<UserId> {
+        self.bot_user_id.get().copied()
+    }
+
+    /// Mark the shard with the provided id as ready, and return the
+    /// events it received before it was ready.
+    pub fn mark_ready(
+        &self,
+        shard_id: u64,
+        bot_user_id: UserId,
+    ) -> Vec<PendingEvent> {
+        let _ = self.bot_user_id.set(bot_user_id);
+
+        let mut readiness = self.readiness.lock().unwrap();
+        readiness.shards.insert(shard_id);
+        let (ready, pending) = readiness
+            .pending
+            .drain(..)
+            .partition(|event| event.shard_id() == shard_id);
+        readiness.pending = pending;
+        ready
+    }
+
+    /// Queue the sync of the rank reward roles of the guild identified by
+    /// the provided id, the guild is only queued once until it is synced.
+    pub fn queue_rank_sync(&self, guild_id: GuildId) {
+        self.pending_rank_syncs.lock().unwrap().insert(guild_id);
+    }
+
+    /// Take the guilds whose rank reward roles should be synced.
+    pub fn take_rank_syncs(&self) -> Vec<GuildId> {
+        self.pending_rank_syncs.lock().unwrap().drain().collect()
+    }
+
+    /// Returns true only the first time it is called, so that the
+    /// scheduler is started once, even when the shards reconnect.
+    pub fn start_scheduler(&self) -> bool {
+        !self.scheduler_started.swap(true, Ordering::SeqCst)
+    }
+
+    /// Return the provided event back when the shard that received it is
+    /// ready, so it may be handled right away. Otherwise the event is
+    /// queued until the shard becomes ready.
+    pub fn defer(&self, event: PendingEvent) -> Option<PendingEvent> {
+        let mut readiness = self.readiness.lock().unwrap();
+        if readiness.shards.contains(&event.shard_id()) {
+            return Some(event);
+        }
+        log::trace!("Shard {} not yet ready, queueing event", event.shard_id());
+        if readiness.pending.len() >= MAX_PENDING_EVENTS {
+            log::warn!("Too many events queued, dropping the oldest one");
+            readiness.pending.remove(0);
+        }
+        readiness.pending.push(event);
+        None
+    }
+}
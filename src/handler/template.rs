@@ -0,0 +1,178 @@
+use serenity::model::prelude::GuildId;
+
+use super::state::State;
+use crate::{
+    datastore::{self, template::TemplateBox},
+    render::{self, Align, TextBox},
+};
+
+/// The most text boxes a template may have.
+pub const MAX_BOXES: usize = 4;
+
+/// The longest name of a template.
+pub const MAX_NAME_LENGTH: usize = 32;
+
+/// The most names suggested while a template's name is typed, the most
+/// choices discord accepts.
+const MAX_SUGGESTIONS: usize = 25;
+
+/// Find the template with the provided name, along with the boxes its
+/// texts are drawn in. The guild's own templates come first, then the
+/// ones installed with the bot, which have the top and bottom text boxes. If
+/// there is no such template, `None` is returned.
+pub async fn find(
+    state: &State,
+    guild_id: Option<GuildId>,
+    name: &str,
+) -> Result<Option<(Vec<u8>, Vec<TextBox>)>, String> {
+    if let Some(guild_id) = guild_id {
+        let template = datastore::template::get_template(
+            &state.datastore.pool,
+            guild_id,
+            name,
+        )
+        .await?;
+        if let Some(template) = template {
+            let boxes = template.boxes.iter().map(text_box).collect();
+            return Ok(Some((template.image, boxes)));
+        }
+    }
+    Ok(render::read_template(name)?
+        .map(|image| (image, vec![render::TOP, render::BOTTOM])))
+}
+
+/// The names of the templates containing the provided query, suggested
+/// while the name of a template is typed.
+pub async fn suggest(
+    state: &State,
+    guild_id: Option<GuildId>,
+    query: &str,
+) -> Result<Vec<String>, String> {
+    let mut names = match guild_id {
+        | Some(guild_id) => {
+            datastore::template::get_template_names(
+                &state.datastore.pool,
+                guild_id,
+                query,
+                MAX_SUGGESTIONS as i64,
+            )
+            .await?
+        }
+        | None => Vec::new(),
+    };
+    let query = query.to_lowercase();
+    for name in render::template_names() {
+        if names.len() >= MAX_SUGGESTIONS {
+            break;
+        }
+        if name.to_lowercase().contains(&query) && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Whether the provided name may be the name of an uploaded template, it
+/// may only contain lowercase letters, digits, `-` and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+        })
+}
+
+/// Parse the text boxes separated by `;`, each of them written as `x, y,
+/// width, height` in percentages of the template's size, optionally followed
+/// by the text's alignment in the box, centered by default. When the boxes
+/// are invalid, the explanation why is returned instead.
+pub fn parse_boxes(boxes: &str) -> Result<Vec<TemplateBox>, String> {
+    let boxes = boxes
+        .split(';')
+        .map(|text_box| text_box.trim())
+        .filter(|text_box| !text_box.is_empty())
+        .map(parse_box)
+        .collect::<Result<Vec<_>, _>>()?;
+    if boxes.is_empty() || boxes.len() > MAX_BOXES {
+        return Err(format!(
+            "A template must have between 1 and {} text boxes",
+            MAX_BOXES
+        ));
+    }
+    Ok(boxes)
+}
+
+fn parse_box(text_box: &str) -> Result<TemplateBox, String> {
+    let invalid = || {
+        format!(
+            "_{}_ is not a valid text box, write it as `x, y, width, height` \
+             in percentages of the template's size, optionally followed by \
+             `top`, `center` or `bottom`",
+            text_box
+        )
+    };
+    let mut values: Vec<&str> =
+        text_box.split(',').map(|value| value.trim()).collect();
+    let align = match values.last().and_then(|value| Align::from_name(value)) {
+        | Some(align) => {
+            values.pop();
+            align
+        }
+        | None => Align::Center,
+    };
+    let values = values
+        .iter()
+        .map(|value| value.trim_end_matches('%').parse::<i16>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let (x, y, width, height) = match values[..] {
+        | [x, y, width, height] => (x, y, width, height),
+        | _ => return Err(invalid()),
+    };
+    if x < 0 || y < 0 || width <= 0 || height <= 0 {
+        return Err(invalid());
+    }
+    // NOTE: the values are bounded before they are added, so that their
+    // sum may not overflow.
+    if [x, y, width, height].iter().any(|value| *value > 100)
+        || x + width > 100
+        || y + height > 100
+    {
+        return Err(format!(
+            "The text box _{}_ does not fit in the template",
+            text_box
+        ));
+    }
+    Ok(TemplateBox {
+        x,
+        y,
+        width,
+        height,
+        align,
+    })
+}
+
+/// The boxes of the templates uploaded without any, the top and bottom text
+/// of the classic meme.
+pub fn default_boxes() -> Vec<TemplateBox> {
+    [render::TOP, render::BOTTOM]
+        .iter()
+        .map(|text_box| TemplateBox {
+            x: (text_box.x * 100.0).round() as i16,
+            y: (text_box.y * 100.0).round() as i16,
+            width: (text_box.width * 100.0).round() as i16,
+            height: (text_box.height * 100.0).round() as i16,
+            align: text_box.align,
+        })
+        .collect()
+}
+
+fn text_box(text_box: &TemplateBox) -> TextBox {
+    TextBox {
+        x: f32::from(text_box.x) / 100.0,
+        y: f32::from(text_box.y) / 100.0,
+        width: f32::from(text_box.width) / 100.0,
+        height: f32::from(text_box.height) / 100.0,
+        align: text_box.align,
+    }
+}
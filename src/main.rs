@@ -2,8 +2,11 @@ use std::env;
 
 use serenity::prelude::{Client, GatewayIntents};
 
+mod cache;
 mod datastore;
 mod handler;
+mod media;
+mod render;
 mod util;
 
 #[tokio::main]
@@ -16,7 +19,8 @@ async fn main() {
     log::info!("Setting discord event handler ...");
     let mut client = Client::builder(
         &discord_token,
-        GatewayIntents::GUILD_MESSAGES
+        GatewayIntents::GUILDS
+            | GatewayIntents::GUILD_MESSAGES
             | GatewayIntents::MESSAGE_CONTENT
             | GatewayIntents::GUILD_MESSAGE_REACTIONS,
     )
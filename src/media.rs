@@ -0,0 +1,577 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageReader,
+};
+
+/// The quality the opaque images are encoded with as jpeg.
+const JPEG_QUALITY: u8 = 85;
+
+/// How much the image is downscaled each time it still exceeds the limit.
+const DOWNSCALE_FACTOR: f32 = 0.75;
+
+/// The smallest width and height an image is downscaled to, the images that
+/// would have to be smaller to fit are left as they are.
+const MIN_DIMENSION: u32 = 256;
+
+/// The markers of the jpeg segments holding the EXIF and XMP metadata, and
+/// the IPTC metadata.
+const JPEG_APP1: u8 = 0xe1;
+const JPEG_APP13: u8 = 0xed;
+
+/// The header of the jpeg segments holding the EXIF metadata, and the tag of
+/// the image's orientation within it.
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const EXIF_ORIENTATION_TAG: u16 = 0x0112;
+
+/// The types of the png chunks holding the EXIF metadata, and the text
+/// metadata, such as the XMP metadata.
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 4] = [b"eXIf", b"tEXt", b"iTXt", b"zTXt"];
+
+/// The types of the png chunks holding the animation control, and the image
+/// data.
+const PNG_ANIMATION_CHUNK: &[u8] = b"acTL";
+const PNG_DATA_CHUNK: &[u8] = b"IDAT";
+
+/// The flag of the webp extended header telling the image is animated.
+const WEBP_ANIMATION_FLAG: u8 = 0x02;
+
+/// The types of the webp chunks holding the EXIF and XMP metadata, and the
+/// flags of the extended header telling they are present.
+const WEBP_METADATA_CHUNKS: [&[u8; 4]; 2] = [b"EXIF", b"XMP "];
+const WEBP_METADATA_FLAGS: u8 = 0x08 | 0x04;
+
+/// An image encoded again, along with the extension of its new format.
+pub struct Encoded {
+    pub data: Vec<u8>,
+    pub extension: &'static str,
+}
+
+/// Encode the provided image again, so that it is at most the provided
+/// number of bytes. The opaque images are encoded as jpeg, and the ones
+/// with transparency as webp, downscaling them until they fit. Only the
+/// pixels are kept, so the metadata, such as the EXIF location, is dropped
+/// as well. Returns `None` when the image may not fit.
+pub fn compress(image: &[u8], limit: u64) -> Result<Option<Encoded>, String> {
+    let mut image = decode(image)?;
+    loop {
+        let encoded = encode(&image)?;
+        if encoded.data.len() as u64 <= limit {
+            return Ok(Some(encoded));
+        }
+        let width = (image.width() as f32 * DOWNSCALE_FACTOR) as u32;
+        let height = (image.height() as f32 * DOWNSCALE_FACTOR) as u32;
+        if width.min(height) < MIN_DIMENSION {
+            return Ok(None);
+        }
+        image = image.resize(width, height, FilterType::Triangle);
+    }
+}
+
+/// Decode the provided image, rotated as its EXIF orientation says, as the
+/// orientation is lost along with the rest of the metadata once the image
+/// is encoded again.
+fn decode(image: &[u8]) -> Result<DynamicImage, String> {
+    let mut decoder = ImageReader::new(Cursor::new(image))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?
+        .into_decoder()
+        .map_err(|err| err.to_string())?;
+    let orientation = decoder.orientation().map_err(|err| err.to_string())?;
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|err| err.to_string())?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn encode(image: &DynamicImage) -> Result<Encoded, String> {
+    let mut data = Vec::new();
+    if is_transparent(image) {
+        image
+            .to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut data))
+            .map_err(|err| err.to_string())?;
+        return Ok(Encoded {
+            data,
+            extension: "webp",
+        });
+    }
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(
+            &mut data,
+            JPEG_QUALITY,
+        ))
+        .map_err(|err| err.to_string())?;
+    Ok(Encoded {
+        data,
+        extension: "jpg",
+    })
+}
+
+/// Whether any of the image's pixels is not fully opaque, the screenshots
+/// are often saved with an alpha channel they do not use.
+fn is_transparent(image: &DynamicImage) -> bool {
+    image.color().has_alpha()
+        && image.to_rgba8().pixels().any(|pixel| pixel.0[3] < u8::MAX)
+}
+
+/// Whether the provided image is animated, such as an animated png or webp.
+/// The gifs are all considered animated, as most of them are.
+pub fn is_animated(image: &[u8]) -> bool {
+    if image.starts_with(b"GIF8") {
+        return true;
+    }
+    if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return is_animated_png(image);
+    }
+    if image.starts_with(b"RIFF") && image.get(8..12) == Some(b"WEBP") {
+        return is_animated_webp(image);
+    }
+    false
+}
+
+/// Whether the png has an animation control chunk, which must come before
+/// the image data.
+fn is_animated_png(image: &[u8]) -> bool {
+    let mut i = 8;
+    while let (Some(length), Some(kind)) =
+        (image.get(i..i + 4), image.get(i + 4..i + 8))
+    {
+        match kind {
+            | PNG_ANIMATION_CHUNK => return true,
+            | PNG_DATA_CHUNK => return false,
+            | _ => (),
+        }
+        let length =
+            u32::from_be_bytes([length[0], length[1], length[2], length[3]])
+                as usize;
+        i += 12 + length;
+    }
+    false
+}
+
+/// Whether the webp has the animation flag of its extended header set.
+fn is_animated_webp(image: &[u8]) -> bool {
+    image.get(12..16) == Some(b"VP8X")
+        && image.get(20).is_some_and(|flags| flags & WEBP_ANIMATION_FLAG != 0)
+}
+
+/// Strip the EXIF, XMP and IPTC metadata from the provided jpeg, png or webp
+/// image, without encoding it again, so that the image is left untouched.
+/// Only the EXIF orientation of the jpegs is kept, as the photos would
+/// otherwise be shown sideways. Returns `None` when the image has no such
+/// metadata, or when it is of another format.
+pub fn strip_metadata(image: &[u8]) -> Option<Vec<u8>> {
+    let stripped = if image.starts_with(&[0xff, 0xd8]) {
+        strip_jpeg(image)
+    } else if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(image)
+    } else if image.starts_with(b"RIFF") && image.get(8..12) == Some(b"WEBP") {
+        strip_webp(image)
+    } else {
+        None
+    }?;
+    // NOTE: the image is kept as it is when nothing was stripped.
+    (stripped.len() < image.len()).then_some(stripped)
+}
+
+fn strip_jpeg(image: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = image[..2].to_vec();
+    let mut i = 2;
+    loop {
+        if *image.get(i)? != 0xff {
+            return None;
+        }
+        let marker = *image.get(i + 1)?;
+        // NOTE: the markers may be padded with any number of 0xff bytes.
+        if marker == 0xff {
+            i += 1;
+            continue;
+        }
+        // NOTE: the segments following the start of the scan are not
+        // prefixed by their length, but there is no metadata left in them.
+        if marker == 0xda || marker == 0xd9 {
+            stripped.extend_from_slice(&image[i..]);
+            return Some(stripped);
+        }
+        let length = usize::from(u16::from_be_bytes([
+            *image.get(i + 2)?,
+            *image.get(i + 3)?,
+        ]));
+        let end = i + 2 + length;
+        if length < 2 || end > image.len() {
+            return None;
+        }
+        if marker == JPEG_APP1 {
+            if let Some(orientation) = exif_orientation(&image[i + 4..end])
+                .filter(|orientation| *orientation != 1)
+            {
+                stripped.extend_from_slice(&orientation_segment(orientation));
+            }
+        } else if marker != JPEG_APP13 {
+            stripped.extend_from_slice(&image[i..end]);
+        }
+        i = end;
+    }
+}
+
+/// Read the orientation from the provided data of a jpeg's APP1 segment,
+/// if it holds the EXIF metadata. The orientation is found among the tags
+/// of the first image file directory.
+fn exif_orientation(segment: &[u8]) -> Option<u16> {
+    let tiff = segment.strip_prefix(EXIF_HEADER)?;
+    let big_endian = match tiff.get(..2)? {
+        | b"MM" => true,
+        | b"II" => false,
+        | _ => return None,
+    };
+    let read_u16 = |i: usize| {
+        let bytes = [*tiff.get(i)?, *tiff.get(i + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |i: usize| {
+        let bytes = [
+            *tiff.get(i)?,
+            *tiff.get(i + 1)?,
+            *tiff.get(i + 2)?,
+            *tiff.get(i + 3)?,
+        ];
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let directory = read_u32(4)? as usize;
+    // NOTE: each entry is made of its tag, type, count and value.
+    (0..usize::from(read_u16(directory)?))
+        .map(|entry| directory + 2 + entry * 12)
+        .find(|entry| read_u16(*entry) == Some(EXIF_ORIENTATION_TAG))
+        .and_then(|entry| read_u16(entry + 8))
+}
+
+/// A jpeg APP1 segment holding the EXIF metadata with only the provided
+/// orientation, in a single image file directory.
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut tiff = b"MM\0\x2a".to_vec();
+    tiff.extend_from_slice(&8u32.to_be_bytes());
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend_from_slice(&EXIF_ORIENTATION_TAG.to_be_bytes());
+    // NOTE: the orientation is a single short, padded to the four bytes
+    // of the entry's value.
+    tiff.extend_from_slice(&3u16.to_be_bytes());
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    // NOTE: there is no next image file directory.
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+    let length = (2 + EXIF_HEADER.len() + tiff.len()) as u16;
+    let mut segment = vec![0xff, JPEG_APP1];
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(EXIF_HEADER);
+    segment.extend_from_slice(&tiff);
+    segment
+}
+
+fn strip_png(image: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = image[..8].to_vec();
+    let mut i = 8;
+    while i < image.len() {
+        let length =
+            u32::from_be_bytes(image.get(i..i + 4)?.try_into().ok()?) as usize;
+        let kind = image.get(i + 4..i + 8)?;
+        // NOTE: the chunks are made of their length, type, data and crc.
+        let end = i + 12 + length;
+        if end > image.len() {
+            return None;
+        }
+        if !PNG_METADATA_CHUNKS.iter().any(|chunk| chunk[..] == *kind) {
+            stripped.extend_from_slice(&image[i..end]);
+        }
+        i = end;
+    }
+    Some(stripped)
+}
+
+fn strip_webp(image: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = image[..12].to_vec();
+    let mut i = 12;
+    while i < image.len() {
+        let kind = image.get(i..i + 4)?;
+        let length =
+            u32::from_le_bytes(image.get(i + 4..i + 8)?.try_into().ok()?)
+                as usize;
+        if i + 8 + length > image.len() {
+            return None;
+        }
+        // NOTE: the chunks are padded to an even length.
+        let end = (i + 8 + length + length % 2).min(image.len());
+        if kind == b"VP8X" {
+            let start = stripped.len();
+            stripped.extend_from_slice(&image[i..end]);
+            *stripped.get_mut(start + 8)? &= !WEBP_METADATA_FLAGS;
+        } else if !WEBP_METADATA_CHUNKS.iter().any(|chunk| chunk[..] == *kind) {
+            stripped.extend_from_slice(&image[i..end]);
+        }
+        i = end;
+    }
+    let size = u32::try_from(stripped.len() - 8).ok()?;
+    stripped[4..8].copy_from_slice(&size.to_le_bytes());
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::png::PngEncoder, RgbImage, RgbaImage};
+
+    use super::*;
+
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    fn png() -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 50]))
+            .write_with_encoder(PngEncoder::new(&mut data))
+            .unwrap();
+        data
+    }
+
+    /// A png chunk of the provided type, with a zeroed crc.
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    /// Insert the provided chunks right after the png's header chunk.
+    fn with_png_chunks(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let png = png();
+        // NOTE: the header chunk holds 13 bytes of data.
+        let header_end = PNG_SIGNATURE.len() + 12 + 13;
+        let mut image = png[..header_end].to_vec();
+        chunks.iter().for_each(|chunk| image.extend_from_slice(chunk));
+        image.extend_from_slice(&png[header_end..]);
+        image
+    }
+
+    /// A webp chunk of the provided type, padded to an even length.
+    fn webp_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// A webp with an extended header with the provided flags, followed by
+    /// the provided chunks and a lossless image.
+    fn webp(flags: u8, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut lossless = Vec::new();
+        RgbaImage::from_pixel(4, 4, image::Rgba([200, 100, 50, 128]))
+            .write_with_encoder(WebPEncoder::new_lossless(&mut lossless))
+            .unwrap();
+        let mut header = vec![flags, 0, 0, 0];
+        header.extend_from_slice(&[3, 0, 0, 3, 0, 0]);
+        let mut body = b"WEBP".to_vec();
+        body.extend_from_slice(&webp_chunk(b"VP8X", &header));
+        // NOTE: the image chunk of the simple format is the encoded image
+        // without its RIFF header.
+        body.extend_from_slice(&lossless[12..]);
+        chunks.iter().for_each(|chunk| body.extend_from_slice(chunk));
+        let mut image = b"RIFF".to_vec();
+        image.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        image.extend_from_slice(&body);
+        image
+    }
+
+    #[test]
+    fn gifs_are_animated() {
+        assert!(is_animated(b"GIF89a\x01\x00\x01\x00"));
+    }
+
+    #[test]
+    fn pngs_with_animation_control_are_animated() {
+        assert!(!is_animated(&png()));
+        let control = png_chunk(PNG_ANIMATION_CHUNK, &[0, 0, 0, 2, 0, 0, 0, 0]);
+        assert!(is_animated(&with_png_chunks(std::slice::from_ref(&control))));
+
+        // NOTE: an animation control following the image data is ignored.
+        let mut image = png();
+        let end = image.len() - 12;
+        image.splice(end..end, control);
+        assert!(!is_animated(&image));
+    }
+
+    #[test]
+    fn webps_with_animation_flag_are_animated() {
+        assert!(is_animated(&webp(WEBP_ANIMATION_FLAG, &[])));
+        assert!(!is_animated(&webp(0, &[])));
+    }
+
+    #[test]
+    fn other_images_are_not_animated() {
+        assert!(!is_animated(b""));
+        assert!(!is_animated(&[0xff, 0xd8, 0xff, 0xd9]));
+    }
+
+    #[test]
+    fn truncated_images_are_not_animated() {
+        assert!(!is_animated(&png()[..PNG_SIGNATURE.len() + 6]));
+        assert!(!is_animated(&webp(WEBP_ANIMATION_FLAG, &[])[..20]));
+        assert!(!is_animated(b"RIFF\0\0\0\0WEBP"));
+    }
+
+    #[test]
+    fn malformed_images_are_not_compressed() {
+        assert!(compress(b"not an image", 1024).is_err());
+        assert!(compress(&png()[..40], 1024).is_err());
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbImage::from_pixel(4, 4, image::Rgb([200, 100, 50]))
+            .write_with_encoder(JpegEncoder::new(&mut data))
+            .unwrap();
+        data
+    }
+
+    /// A jpeg segment with the provided marker.
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xff, marker];
+        segment.extend_from_slice(&(data.len() as u16 + 2).to_be_bytes());
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    /// Insert the provided segments right after the jpeg's start marker.
+    fn with_jpeg_segments(segments: &[Vec<u8>]) -> Vec<u8> {
+        let jpeg = jpeg();
+        let mut image = jpeg[..2].to_vec();
+        segments.iter().for_each(|segment| image.extend_from_slice(segment));
+        image.extend_from_slice(&jpeg[2..]);
+        image
+    }
+
+    /// The data of an APP1 segment holding little endian EXIF metadata with
+    /// the provided orientation, followed by a GPS directory pointer.
+    fn exif(orientation: u16) -> Vec<u8> {
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(b"II\x2a\0");
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        exif.extend_from_slice(&2u16.to_le_bytes());
+        exif.extend_from_slice(&EXIF_ORIENTATION_TAG.to_le_bytes());
+        exif.extend_from_slice(&3u16.to_le_bytes());
+        exif.extend_from_slice(&1u32.to_le_bytes());
+        exif.extend_from_slice(&orientation.to_le_bytes());
+        exif.extend_from_slice(&[0, 0]);
+        exif.extend_from_slice(&0x8825u16.to_le_bytes());
+        exif.extend_from_slice(&4u16.to_le_bytes());
+        exif.extend_from_slice(&1u32.to_le_bytes());
+        exif.extend_from_slice(&0x1234u32.to_le_bytes());
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        exif
+    }
+
+    fn decoded_orientation(image: &[u8]) -> image::metadata::Orientation {
+        ImageReader::new(Cursor::new(image))
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap()
+            .orientation()
+            .unwrap()
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn jpeg_metadata_is_stripped_and_orientation_kept() {
+        let image = with_jpeg_segments(&[
+            jpeg_segment(JPEG_APP1, &exif(6)),
+            jpeg_segment(
+                JPEG_APP1,
+                b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>",
+            ),
+            jpeg_segment(JPEG_APP13, b"Photoshop 3.0\0IPTC"),
+        ]);
+        let stripped = strip_metadata(&image).unwrap();
+        assert!(!contains(&stripped, b"xmpmeta"));
+        assert!(!contains(&stripped, b"IPTC"));
+        assert!(!contains(&stripped, &exif(6)));
+        assert!(contains(&stripped, &orientation_segment(6)));
+        assert_eq!(
+            decoded_orientation(&stripped),
+            image::metadata::Orientation::Rotate90
+        );
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn jpeg_default_orientation_is_dropped() {
+        let image = with_jpeg_segments(&[jpeg_segment(JPEG_APP1, &exif(1))]);
+        assert_eq!(strip_metadata(&image), Some(jpeg()));
+    }
+
+    #[test]
+    fn jpeg_without_metadata_is_left_as_it_is() {
+        assert_eq!(strip_metadata(&jpeg()), None);
+    }
+
+    #[test]
+    fn png_metadata_chunks_are_stripped() {
+        let image = with_png_chunks(&[
+            png_chunk(b"eXIf", &exif(6)[EXIF_HEADER.len()..]),
+            png_chunk(b"tEXt", b"Comment\0hello"),
+            png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x:xmpmeta/>"),
+            png_chunk(b"zTXt", b"Comment\0\0x"),
+        ]);
+        let stripped = strip_metadata(&image).unwrap();
+        assert_eq!(stripped, png());
+    }
+
+    #[test]
+    fn webp_metadata_chunks_and_flags_are_stripped() {
+        let image = webp(WEBP_METADATA_FLAGS, &[
+            webp_chunk(b"EXIF", &exif(6)[EXIF_HEADER.len()..]),
+            webp_chunk(b"XMP ", b"<x:xmpmeta/>!"),
+        ]);
+        let stripped = strip_metadata(&image).unwrap();
+        assert_eq!(stripped, webp(0, &[]));
+        let size = u32::from_le_bytes(stripped[4..8].try_into().unwrap());
+        assert_eq!(size as usize, stripped.len() - 8);
+        assert!(image::load_from_memory(&stripped).is_ok());
+    }
+
+    #[test]
+    fn malformed_images_are_not_stripped() {
+        let jpeg = with_jpeg_segments(&[jpeg_segment(JPEG_APP1, &exif(6))]);
+        assert_eq!(strip_metadata(&jpeg[..10]), None);
+        let mut overlong = jpeg.clone();
+        overlong[4..6].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert_eq!(strip_metadata(&overlong), None);
+
+        let png = with_png_chunks(&[png_chunk(b"tEXt", b"Comment\0hello")]);
+        assert_eq!(strip_metadata(&png[..png.len() - 3]), None);
+
+        let webp = webp(0, &[webp_chunk(b"EXIF", b"exif")]);
+        assert_eq!(strip_metadata(&webp[..webp.len() - 3]), None);
+
+        assert_eq!(strip_metadata(b""), None);
+        assert_eq!(strip_metadata(&[0xff, 0xd8]), None);
+        assert_eq!(strip_metadata(b"\x89PNG\r\n\x1a\n\0\0"), None);
+        assert_eq!(strip_metadata(b"RIFF\0\0\0\0WEBPVP8X"), None);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{env, fs, io::Cursor, path::PathBuf};
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
+use image::{
+    imageops::FilterType, GrayImage, ImageFormat, ImageReader, Luma, Rgba,
+    RgbaImage,
+};
+use imageproc::{
+    distance_transform::Norm,
+    drawing::{draw_text_mut, text_size},
+    morphology::dilate,
+};
+
+/// The font the text is rendered with. It is bundled with the binary, so
+/// that the memes are rendered entirely offline.
+const FONT: &[u8] =
+    include_bytes!("../assets/fonts/DejaVuSansCondensed-Bold.ttf");
+
+/// The largest width and height of a rendered meme, the larger images are
+/// downscaled before the text is drawn on them.
+const MAX_DIMENSION: u32 = 1600;
+
+/// The smallest font size the text is shrunk to, in pixels.
+const MIN_FONT_SIZE: f32 = 12.0;
+
+/// The extensions of the images of the templates directory.
+const TEMPLATE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "webp", "gif"];
+
+/// Where the text is placed within its box.
+#[derive(Clone, Copy)]
+pub enum Align {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Align {
+    pub fn name(&self) -> &'static str {
+        match self {
+            | Align::Top => "top",
+            | Align::Center => "center",
+            | Align::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Align> {
+        match name {
+            | "top" => Some(Align::Top),
+            | "center" => Some(Align::Center),
+            | "bottom" => Some(Align::Bottom),
+            | _ => None,
+        }
+    }
+}
+
+/// A box of an image the text is fitted in. The position and the size are
+/// relative to the image's size, between 0 and 1, so that they still apply
+/// once the image is downscaled.
+#[derive(Clone, Copy)]
+pub struct TextBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub align: Align,
+}
+
+/// The box of the top text of a captioned meme.
+pub const TOP: TextBox = TextBox {
+    x: 0.03,
+    y: 0.02,
+    width: 0.94,
+    height: 0.25,
+    align: Align::Top,
+};
+
+/// The box of the bottom text of a captioned meme.
+pub const BOTTOM: TextBox = TextBox {
+    x: 0.03,
+    y: 0.73,
+    width: 0.94,
+    height: 0.25,
+    align: Align::Bottom,
+};
+
+/// Render the classic meme with the provided top and bottom text, drawn
+/// in white with a black outline. Returns the rendered image as a png.
+pub fn caption(
+    image: &[u8],
+    top: &str,
+    bottom: &str,
+) -> Result<Vec<u8>, String> {
+    render(image, &[(TOP, top), (BOTTOM, bottom)])
+}
+
+/// Draw each text on the provided image, fitted in its box. Returns the
+/// rendered image as a png.
+pub fn render(
+    image: &[u8],
+    texts: &[(TextBox, &str)],
+) -> Result<Vec<u8>, String> {
+    let font = FontRef::try_from_slice(FONT).map_err(|err| err.to_string())?;
+    let mut image =
+        image::load_from_memory(image).map_err(|err| err.to_string())?;
+    if image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+        image =
+            image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Triangle);
+    }
+    let mut image = image.to_rgba8();
+    for (text_box, text) in texts.iter() {
+        draw_text_box(&mut image, &font, text_box, text);
+    }
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|err| err.to_string())?;
+    Ok(png)
+}
+
+/// Read the width and height of the provided image, without decoding all
+/// of it.
+pub fn dimensions(image: &[u8]) -> Result<(u32, u32), String> {
+    ImageReader::new(Cursor::new(image))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?
+        .into_dimensions()
+        .map_err(|err| err.to_string())
+}
+
+/// Draw the provided text in uppercase, wrapped and shrunk until it fits
+/// in the provided box.
+fn draw_text_box(
+    image: &mut RgbaImage,
+    font: &FontRef,
+    text_box: &TextBox,
+    text: &str,
+) {
+    let text = text.trim().to_uppercase();
+    if text.is_empty() {
+        return;
+    }
+    let x = text_box.x * image.width() as f32;
+    let y = text_box.y * image.height() as f32;
+    let width = text_box.width * image.width() as f32;
+    let height = text_box.height * image.height() as f32;
+
+    let mut size = height.max(MIN_FONT_SIZE);
+    let lines = loop {
+        let lines = wrap(font, size, &text, width);
+        let line_height = font.as_scaled(PxScale::from(size)).height();
+        let fits = lines.len() as f32 * line_height <= height
+            && lines.iter().all(|(_, line_width)| *line_width <= width);
+        if fits || size <= MIN_FONT_SIZE {
+            break lines;
+        }
+        size = (size * 0.9).max(MIN_FONT_SIZE);
+    };
+    let scale = PxScale::from(size);
+    let line_height = font.as_scaled(scale).height();
+    let total_height = lines.len() as f32 * line_height;
+    let top = match text_box.align {
+        | Align::Top => y,
+        | Align::Center => y + (height - total_height) / 2.0,
+        | Align::Bottom => y + height - total_height,
+    };
+    // NOTE: the lines are drawn once on a mask, which is dilated into their
+    // outline, as drawing the text around each pixel of the outline would
+    // take a pass per pixel.
+    let mut mask = GrayImage::new(image.width(), image.height());
+    for (i, (line, line_width)) in lines.iter().enumerate() {
+        let line_x = (x + (width - line_width) / 2.0) as i32;
+        let line_y = (top + i as f32 * line_height) as i32;
+        draw_text_mut(
+            &mut mask,
+            Luma([255]),
+            line_x,
+            line_y,
+            scale,
+            font,
+            line,
+        );
+    }
+    let radius = (size / 16.0).clamp(1.0, f32::from(u8::MAX)) as u8;
+    let outline = dilate(&mask, Norm::L2, radius);
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        if outline.get_pixel(x, y)[0] == 0 {
+            continue;
+        }
+        // NOTE: the white text is blended over its black outline by its
+        // coverage of the pixel.
+        let value = mask.get_pixel(x, y)[0];
+        *pixel = Rgba([value, value, value, 255]);
+    }
+}
+
+/// Wrap the provided text into the lines that fit in the provided width,
+/// along with their widths. A word wider than the width is kept on its own
+/// line.
+fn wrap(
+    font: &FontRef,
+    size: f32,
+    text: &str,
+    width: f32,
+) -> Vec<(String, f32)> {
+    let measure = |line: &str| text_size(size, font, line).0 as f32;
+    let mut lines: Vec<(String, f32)> = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some((line, line_width)) = lines.last_mut() {
+            let joined = format!("{} {}", line, word);
+            let joined_width = measure(&joined);
+            if joined_width <= width {
+                *line = joined;
+                *line_width = joined_width;
+                continue;
+            }
+        }
+        lines.push((word.to_string(), measure(word)));
+    }
+    lines
+}
+
+/// The directory of the templates installed with the bot, set with the
+/// `MEMSTHER_TEMPLATES_DIR` env variable, `templates` by default.
+fn templates_dir() -> PathBuf {
+    env::var("MEMSTHER_TEMPLATES_DIR")
+        .map_or(PathBuf::from("templates"), PathBuf::from)
+}
+
+/// The names of the templates in the templates directory, the names of
+/// their image files without the extensions.
+pub fn template_names() -> Vec<String> {
+    template_files().into_iter().map(|(name, _)| name).collect()
+}
+
+/// Read the image of the template with the provided name from the
+/// templates directory. If there is no such template, `None` is returned.
+pub fn read_template(name: &str) -> Result<Option<Vec<u8>>, String> {
+    // NOTE: only the files listed in the directory are read, so that the
+    // provided name may not point outside of it.
+    match template_files().into_iter().find(|(template, _)| template == name) {
+        | Some((_, path)) => {
+            fs::read(path).map(Some).map_err(|err| err.to_string())
+        }
+        | None => Ok(None),
+    }
+}
+
+/// The image files of the templates directory, along with their names,
+/// sorted by the names.
+fn template_files() -> Vec<(String, PathBuf)> {
+    let entries = match fs::read_dir(templates_dir()) {
+        | Ok(entries) => entries,
+        | Err(why) => {
+            log::debug!("Failed to read the templates: {}", why);
+            return Vec::new();
+        }
+    };
+    let mut files: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    TEMPLATE_EXTENSIONS
+                        .contains(&extension.to_lowercase().as_str())
+                })
+        })
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            Some((name, path))
+        })
+        .collect();
+    files.sort();
+    files
+}
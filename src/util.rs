@@ -1,3 +1,5 @@
+use std::env;
+
 use regex::Regex;
 
 pub fn get_thumbs_up() -> String { String::from("👍") }
@@ -10,3 +12,29 @@ pub fn is_url(url: &str) -> bool {
     ).unwrap();
     re.is_match(url)
 }
+
+/// Whether the env variable with the provided name is set
+/// to a truthy value, such as `1` or `true`.
+pub fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|value| {
+        matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+    })
+}
+
+/// The number the env variable with the provided name is set to, or `None`
+/// when it is not set or is not a number.
+pub fn env_number(name: &str) -> Option<u64> {
+    env::var(name).ok().and_then(|value| value.trim().parse().ok())
+}
+
+/// The comma separated values of the env variable with the provided
+/// name, or an empty vector when it is not set.
+pub fn env_list(name: &str) -> Vec<String> {
+    env::var(name).map_or(Vec::new(), |value| {
+        value
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    })
+}